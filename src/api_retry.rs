@@ -0,0 +1,342 @@
+use web_sys::{window, Request, RequestInit, RequestMode, Response};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use gloo_timers::future::TimeoutFuture;
+
+use crate::browser::console_log;
+use crate::identity;
+
+// How a single API call should behave when the backend or network is flaky
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    // Total number of attempts, including the first one
+    pub max_attempts: u32,
+    // Delay before the first retry; doubles on every following attempt
+    pub base_delay_ms: u32,
+    // Upper bound for any single wait, including waits asked for by Retry-After
+    pub max_delay_ms: u32,
+    // Randomise each delay ("full jitter") so clients don't retry in lockstep
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    // Policy for things the user clicked: retry quickly a few times before giving up
+    pub fn user_action() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay_ms: 500,
+            max_delay_ms: 8_000,
+            jitter: true,
+        }
+    }
+
+    // Policy for background polling: the next poll is a retry anyway, so back off hard
+    pub fn polling() -> Self {
+        Self {
+            max_attempts: 2,
+            base_delay_ms: 2_000,
+            max_delay_ms: 30_000,
+            jitter: true,
+        }
+    }
+
+    // Single attempt, no retries
+    pub fn no_retry() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay_ms: 0,
+            max_delay_ms: 0,
+            jitter: false,
+        }
+    }
+
+    // Delay before retry number `retry` (0 for the first retry).
+    // `random` is expected in [0, 1) and is only used when jitter is enabled.
+    pub fn backoff_delay_ms(&self, retry: u32, random: f64) -> u32 {
+        let exponential = (self.base_delay_ms as u64)
+            .saturating_mul(1u64 << retry.min(31))
+            .min(self.max_delay_ms as u64) as u32;
+
+        if self.jitter {
+            (exponential as f64 * random.clamp(0.0, 1.0)) as u32
+        } else {
+            exponential
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::user_action()
+    }
+}
+
+// Statuses that are worth retrying: timeouts, rate limiting and transient server errors
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 429 | 500 | 502 | 503 | 504)
+}
+
+// Parse a Retry-After header value into milliseconds.
+// The header is either a number of seconds or an HTTP date.
+pub fn parse_retry_after(value: &str, now_ms: f64) -> Option<u32> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    if let Ok(seconds) = value.parse::<u32>() {
+        return Some(seconds.saturating_mul(1000));
+    }
+
+    let date_ms = js_sys::Date::parse(value);
+    if date_ms.is_nan() {
+        return None;
+    }
+
+    Some((date_ms - now_ms).max(0.0) as u32)
+}
+
+// fetch rejects with a TypeError when the network is unreachable (offline, DNS,
+// CORS preflight failure); API errors are surfaced as plain strings instead
+pub fn is_network_error(error: &JsValue) -> bool {
+    error.is_instance_of::<js_sys::TypeError>()
+}
+
+// Why a call to the backend failed
+#[derive(Debug, Clone)]
+pub enum ApiError {
+    // The backend couldn't be reached at all
+    Network(JsValue),
+    // The backend answered, but not with success
+    Status { status: u16, message: String },
+    // Anything else, such as a response we couldn't read
+    Other(JsValue),
+}
+
+impl ApiError {
+    pub fn is_network(&self) -> bool {
+        matches!(self, ApiError::Network(_))
+    }
+
+    // Whether trying again later could succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ApiError::Network(_) => true,
+            ApiError::Status { status, .. } => is_retryable_status(*status),
+            ApiError::Other(_) => false,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::Status { message, .. } => message.clone(),
+            ApiError::Network(e) | ApiError::Other(e) => e.as_string().unwrap_or_else(|| format!("{:?}", e)),
+        }
+    }
+}
+
+impl From<JsValue> for ApiError {
+    fn from(error: JsValue) -> Self {
+        if is_network_error(&error) {
+            ApiError::Network(error)
+        } else {
+            ApiError::Other(error)
+        }
+    }
+}
+
+impl From<ApiError> for JsValue {
+    fn from(error: ApiError) -> Self {
+        match error {
+            ApiError::Network(e) | ApiError::Other(e) => e,
+            ApiError::Status { message, .. } => JsValue::from_str(&message),
+        }
+    }
+}
+
+// Build a fresh request for every attempt; a request body can only be consumed once.
+// Each attempt is signed again so its timestamp is current.
+async fn build_request(
+    method: &str,
+    url: &str,
+    body: Option<&str>,
+    idempotency_key: Option<&str>,
+) -> Result<Request, JsValue> {
+    let opts = RequestInit::new();
+    opts.set_method(method);
+    opts.set_mode(RequestMode::Cors);
+
+    let headers = web_sys::Headers::new()?;
+    headers.append("Accept", "application/json")?;
+    // Only a request with a body has content to describe
+    if body.is_some() {
+        headers.append("Content-Type", "application/json")?;
+    }
+    if let Some(key) = idempotency_key {
+        headers.append("Idempotency-Key", key)?;
+    }
+    if let Some(signature_headers) = identity::signature_headers(method, url, body).await {
+        for (name, value) in signature_headers {
+            headers.append(name, &value)?;
+        }
+    }
+    opts.set_headers(&headers);
+
+    if let Some(body) = body {
+        opts.set_body(&JsValue::from_str(body));
+    }
+
+    Request::new_with_str_and_init(url, &opts)
+}
+
+// Fetch `url`, retrying network errors and retryable statuses according to `policy`.
+//
+// POST requests carry one Idempotency-Key for all attempts so the server can
+// recognise a retried create/join/send and not apply it twice. Whatever the
+// last attempt returned is handed back, so callers keep their own handling of
+// non-ok responses.
+pub async fn fetch_with_retry(
+    method: &str,
+    url: &str,
+    body: Option<&str>,
+    policy: &RetryPolicy,
+) -> Result<Response, JsValue> {
+    let idempotency_key = (method == "POST").then(|| uuid::Uuid::new_v4().to_string());
+    fetch_with_idempotency_key(method, url, body, policy, idempotency_key.as_deref()).await
+}
+
+// Same as `fetch_with_retry`, but with a caller-chosen Idempotency-Key so the
+// same logical request can be replayed later (e.g. from the offline outbox)
+pub async fn fetch_with_idempotency_key(
+    method: &str,
+    url: &str,
+    body: Option<&str>,
+    policy: &RetryPolicy,
+    idempotency_key: Option<&str>,
+) -> Result<Response, JsValue> {
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        let is_last_attempt = attempt >= max_attempts;
+
+        let request = build_request(method, url, body, idempotency_key).await?;
+        let window = window().ok_or_else(|| JsValue::from_str("No window found"))?;
+
+        let delay_ms = match JsFuture::from(window.fetch_with_request(&request)).await {
+            Ok(resp_value) => {
+                let resp: Response = resp_value.dyn_into()?;
+                let status = resp.status();
+
+                if resp.ok() || is_last_attempt || !is_retryable_status(status) {
+                    return Ok(resp);
+                }
+
+                // Honour Retry-After on rate limiting / maintenance responses
+                let retry_after = if status == 429 || status == 503 {
+                    resp.headers()
+                        .get("Retry-After")
+                        .ok()
+                        .flatten()
+                        .and_then(|value| parse_retry_after(&value, js_sys::Date::now()))
+                } else {
+                    None
+                };
+
+                let delay = retry_after
+                    .map(|ms| ms.min(policy.max_delay_ms))
+                    .unwrap_or_else(|| policy.backoff_delay_ms(attempt - 1, js_sys::Math::random()));
+                console_log(&format!(
+                    "{} {} returned {}, retrying in {}ms (attempt {}/{})",
+                    method, url, status, delay, attempt + 1, max_attempts
+                ));
+                delay
+            },
+            Err(e) => {
+                // fetch only rejects on network failures
+                if is_last_attempt {
+                    return Err(e);
+                }
+
+                let delay = policy.backoff_delay_ms(attempt - 1, js_sys::Math::random());
+                console_log(&format!(
+                    "{} {} failed with a network error, retrying in {}ms (attempt {}/{})",
+                    method, url, delay, attempt + 1, max_attempts
+                ));
+                delay
+            }
+        };
+
+        TimeoutFuture::new(delay_ms).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_backoff_doubles_and_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 6,
+            base_delay_ms: 100,
+            max_delay_ms: 1_000,
+            jitter: false,
+        };
+
+        assert_eq!(policy.backoff_delay_ms(0, 0.5), 100);
+        assert_eq!(policy.backoff_delay_ms(1, 0.5), 200);
+        assert_eq!(policy.backoff_delay_ms(2, 0.5), 400);
+        assert_eq!(policy.backoff_delay_ms(3, 0.5), 800);
+        assert_eq!(policy.backoff_delay_ms(4, 0.5), 1_000);
+        assert_eq!(policy.backoff_delay_ms(40, 0.5), 1_000);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_backoff_jitter_stays_within_bounds() {
+        let policy = RetryPolicy::user_action();
+
+        assert_eq!(policy.backoff_delay_ms(2, 0.0), 0);
+        assert_eq!(policy.backoff_delay_ms(2, 0.5), 1_000);
+        assert!(policy.backoff_delay_ms(2, 0.999) < 2_000);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_retryable_statuses() {
+        assert!(is_retryable_status(429));
+        assert!(is_retryable_status(503));
+        assert!(!is_retryable_status(400));
+        assert!(!is_retryable_status(404));
+        assert!(!is_retryable_status(501));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("3", 0.0), Some(3_000));
+        assert_eq!(parse_retry_after(" 0 ", 0.0), Some(0));
+        assert_eq!(parse_retry_after("not a date", 0.0), None);
+        assert_eq!(parse_retry_after("", 0.0), None);
+
+        let now = js_sys::Date::parse("Wed, 21 Oct 2015 07:28:00 GMT");
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:10 GMT", now), Some(10_000));
+        // Dates in the past mean "retry now"
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now), Some(0));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_api_error_retryable() {
+        let busy = ApiError::Status { status: 503, message: "API error: 503 Service Unavailable".to_string() };
+        assert!(busy.is_retryable());
+        let rejected = ApiError::Status { status: 403, message: "Not a member".to_string() };
+        assert!(!rejected.is_retryable());
+        assert_eq!(rejected.message(), "Not a member");
+
+        let unreachable = ApiError::from(JsValue::from(js_sys::TypeError::new("Failed to fetch")));
+        assert!(unreachable.is_network() && unreachable.is_retryable());
+        assert!(!ApiError::from(JsValue::from_str("bad json")).is_retryable());
+    }
+}
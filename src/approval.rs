@@ -0,0 +1,221 @@
+use leptos::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::api_retry::RetryPolicy;
+use crate::blocklist;
+use crate::browser::console_log;
+use crate::connect_component::{get_stored_player_id, Connection, ConnectionStatus};
+use crate::connection_utils::{self, JoinRequest, RequestState};
+use crate::invite_policy;
+use crate::profile::{self, ProfileCard};
+use crate::toast;
+
+// Saved-connection field marking a connection we asked to join and haven't
+// been let into yet; the saved list doesn't otherwise keep statuses
+const AWAITING_FIELD: &str = "awaiting_approval";
+
+pub fn is_awaiting(connection_id: &str) -> bool {
+    connection_utils::get_saved_connection(connection_id)
+        .and_then(|record| record.get(AWAITING_FIELD).and_then(|v| v.as_bool()))
+        .unwrap_or(false)
+}
+
+pub fn set_awaiting(connection_id: &str, awaiting: bool) {
+    connection_utils::update_saved_connection(connection_id, |record| {
+        if awaiting {
+            record.insert(AWAITING_FIELD.to_string(), serde_json::Value::Bool(true));
+        } else {
+            record.remove(AWAITING_FIELD);
+        }
+    });
+}
+
+// The status a request we made has reached, once it's been answered
+pub fn status_for(state: RequestState) -> Option<ConnectionStatus> {
+    match state {
+        RequestState::Pending => None,
+        RequestState::Accepted => Some(ConnectionStatus::Active),
+        // There's nothing left to do with a declined request but delete it
+        RequestState::Declined => Some(ConnectionStatus::Expired),
+    }
+}
+
+// Whether friends can still ask to join an invite of ours: the first accept
+// makes it Active, but a group or reusable link keeps taking requests until
+// its seats or uses run out
+pub fn takes_requests(connection: &Connection) -> bool {
+    matches!(connection.status, ConnectionStatus::Pending | ConnectionStatus::Active)
+        && invite_policy::remaining_uses(connection) != Some(0)
+        && (connection.players.len() as u32) < connection.max_players
+}
+
+// The invite as it stands after we let `requester_id` in
+pub fn with_accepted(connection: &Connection, requester_id: &str) -> Connection {
+    let mut accepted = connection.clone();
+    if !accepted.players.iter().any(|player| player == requester_id) {
+        accepted.players.push(requester_id.to_string());
+        accepted.uses += 1;
+    }
+    accepted.status = ConnectionStatus::Active;
+    accepted
+}
+
+// Ask the server whether the friend has answered our request to join
+pub async fn check_request(connection_id: &str) -> Option<ConnectionStatus> {
+    let player_id = get_stored_player_id()?;
+    let state = match connection_utils::get_join_request_state(connection_id, &player_id, &RetryPolicy::polling()).await {
+        Ok(state) => state,
+        Err(e) => {
+            console_log(&format!("Couldn't check join request for {}: {:?}", connection_id, e));
+            return None;
+        }
+    };
+
+    let status = status_for(state)?;
+    set_awaiting(connection_id, false);
+    Some(status)
+}
+
+// Friends waiting to be let into one of our connections, with accept and
+// decline buttons. Reloads whenever `refresh` changes.
+#[component]
+pub fn JoinRequests(
+    connection_id: String,
+    #[prop(into)] refresh: Signal<Option<u32>>,
+    // Called with the requester's player id once their request has been accepted
+    on_accept: Callback<String>,
+) -> impl IntoView {
+    let (requests, set_requests) = signal(Vec::<JoinRequest>::new());
+    let (busy, set_busy) = signal(false);
+    let toasts = toast::use_toasts();
+    let connection_id = StoredValue::new(connection_id);
+
+    Effect::new(move |_| {
+        refresh.track();
+        let connection_id = connection_id.get_value();
+        spawn_local(async move {
+            match connection_utils::get_join_requests(&connection_id, &RetryPolicy::polling()).await {
+                // Requests from blocked players are never shown
+                Ok(found) => set_requests.set(found.into_iter().filter(|r| !blocklist::is_blocked(&r.player_id)).collect()),
+                Err(e) => console_log(&format!("Couldn't load join requests: {:?}", e)),
+            }
+        });
+    });
+
+    let answer = move |requester_id: String, accept: bool| {
+        let Some(player_id) = get_stored_player_id() else {
+            return;
+        };
+        set_busy.set(true);
+        spawn_local(async move {
+            let connection_id = connection_id.get_value();
+            match connection_utils::answer_join_request(&connection_id, &player_id, &requester_id, accept, &RetryPolicy::user_action()).await {
+                Ok(()) => {
+                    console_log(&format!("{} join request from {}", if accept { "Accepted" } else { "Declined" }, requester_id));
+                    toasts.success(if accept { "Request accepted." } else { "Request declined." });
+                    set_requests.update(|requests| requests.retain(|r| r.player_id != requester_id));
+                    if accept {
+                        on_accept.run(requester_id);
+                    }
+                },
+                Err(e) => toasts.error(format!("Couldn't answer the request: {}", e.as_string().unwrap_or_else(|| format!("{:?}", e)))),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    view! {
+        {move || (!requests.get().is_empty()).then(|| view! {
+            <div class="px-3 pb-3" data-test-id="join-requests">
+                {requests.get().into_iter().map(|request| {
+                    let accept_id = request.player_id.clone();
+                    let decline_id = request.player_id.clone();
+                    let profile = request.profile.clone().and_then(|p| profile::normalize_profile(p).ok()).unwrap_or_default();
+                    view! {
+                        <div class="flex justify-between items-center gap-2 p-2 mt-2 rounded bg-gray-900 border border-gray-700">
+                            <div>
+                                <div class="text-xs text-gray-400 mb-1">"Wants to connect"</div>
+                                <ProfileCard player_id=request.player_id.clone() profile=profile />
+                            </div>
+                            <div class="flex gap-2">
+                                <button
+                                    class="px-3 py-1 bg-green-600 hover:bg-green-700 rounded text-sm text-gray-100"
+                                    prop:disabled=busy
+                                    on:click=move |_| answer(accept_id.clone(), true)
+                                >
+                                    "Accept"
+                                </button>
+                                <button
+                                    class="px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200"
+                                    prop:disabled=busy
+                                    on:click=move |_| answer(decline_id.clone(), false)
+                                >
+                                    "Decline"
+                                </button>
+                            </div>
+                        </div>
+                    }
+                }).collect_view()}
+            </div>
+        })}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_component::test_connection;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_status_for_answered_requests() {
+        assert_eq!(status_for(RequestState::Pending), None);
+        assert_eq!(status_for(RequestState::Accepted), Some(ConnectionStatus::Active));
+        assert_eq!(status_for(RequestState::Declined), Some(ConnectionStatus::Expired));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_awaiting_flag_round_trips_through_saved_connection() {
+        let connection = test_connection(&format!("approval-test-{}", uuid::Uuid::new_v4()), ConnectionStatus::Requested);
+        connection_utils::save_connection_to_local_storage(&connection, "Friend").unwrap();
+        assert!(!is_awaiting(&connection.id));
+
+        set_awaiting(&connection.id, true);
+        assert!(is_awaiting(&connection.id));
+
+        set_awaiting(&connection.id, false);
+        assert!(!is_awaiting(&connection.id));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_group_keeps_taking_requests_after_first_accept() {
+        let group = Connection {
+            players: vec!["me".to_string()],
+            max_uses: None,
+            max_players: 3,
+            ..test_connection("approval-group", ConnectionStatus::Pending)
+        };
+        assert!(takes_requests(&group));
+
+        // The first accept makes it Active, and a second request still shows
+        let first = with_accepted(&group, "alice");
+        assert_eq!(first.status, ConnectionStatus::Active);
+        assert!(takes_requests(&first));
+
+        // Once the last seat is taken the panel goes away
+        let full = with_accepted(&first, "bob");
+        assert!(!takes_requests(&full));
+        assert_eq!(with_accepted(&full, "bob").uses, full.uses);
+
+        let one_to_one = Connection { max_players: 2, max_uses: Some(1), ..group.clone() };
+        assert!(!takes_requests(&with_accepted(&one_to_one, "alice")));
+
+        let reusable = Connection { max_players: 10, max_uses: Some(2), ..group };
+        let reused = with_accepted(&reusable, "alice");
+        assert!(takes_requests(&reused));
+        assert!(!takes_requests(&with_accepted(&reused, "bob")));
+        assert!(!takes_requests(&Connection { status: ConnectionStatus::Expired, ..reusable }));
+    }
+}
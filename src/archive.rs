@@ -0,0 +1,231 @@
+use leptos::prelude::*;
+
+use crate::browser::storage;
+use crate::connect_component::{Connection, ConnectionStatus};
+use crate::connection_utils;
+use crate::expiry;
+
+// Saved-connection field with when a connection was archived
+const ARCHIVED_FIELD: &str = "archived_at";
+// localStorage key for how many days expired invites are kept before being
+// archived automatically; absent means never
+const CLEANUP_KEY: &str = "cleanup-after-days";
+// Choices offered for automatic clean-up
+pub const CLEANUP_DAY_CHOICES: [u32; 3] = [1, 7, 30];
+
+pub fn is_archived(connection_id: &str) -> bool {
+    connection_utils::get_saved_connection(connection_id)
+        .is_some_and(|record| record.contains_key(ARCHIVED_FIELD))
+}
+
+// Hide a connection from the list, or bring it back
+pub fn set_archived(connection_id: &str, archived: bool) {
+    connection_utils::update_saved_connection(connection_id, |record| {
+        if archived {
+            record.insert(ARCHIVED_FIELD.to_string(), serde_json::json!(expiry::now_secs()));
+        } else {
+            record.remove(ARCHIVED_FIELD);
+        }
+    });
+}
+
+pub fn load_cleanup_days() -> Option<u32> {
+    storage()
+        .and_then(|storage| storage.get_item(CLEANUP_KEY).ok().flatten())
+        .and_then(|days| days.parse().ok())
+}
+
+pub fn save_cleanup_days(days: Option<u32>) {
+    let Some(storage) = storage() else {
+        return;
+    };
+    match days {
+        Some(days) => {
+            let _ = storage.set_item(CLEANUP_KEY, &days.to_string());
+        },
+        None => {
+            let _ = storage.remove_item(CLEANUP_KEY);
+        },
+    }
+}
+
+// Whether an invite has been expired long enough to archive on its own
+pub fn due_for_cleanup(connection: &Connection, now: i64, days: u32) -> bool {
+    expiry::status_at(connection, now) == ConnectionStatus::Expired
+        && now - connection.expires_at >= i64::from(days) * 86400
+}
+
+// Archive every connection the clean-up policy says is due, returning their ids
+pub fn run_cleanup(connections: &[Connection], now: i64) -> Vec<String> {
+    let Some(days) = load_cleanup_days() else {
+        return Vec::new();
+    };
+    let due: Vec<String> = connections
+        .iter()
+        .filter(|c| due_for_cleanup(c, now, days) && !is_archived(&c.id))
+        .map(|c| c.id.clone())
+        .collect();
+    for id in &due {
+        set_archived(id, true);
+    }
+    due
+}
+
+// Settings section for archiving old expired invites automatically
+#[component]
+pub fn CleanupSettings() -> impl IntoView {
+    let (days, set_days) = signal(load_cleanup_days());
+
+    view! {
+        <div class="mt-4 pt-4 border-t border-gray-700" data-test-id="cleanup-settings">
+            <div class="font-medium">"Clean-up"</div>
+            <div class="flex flex-wrap gap-2 items-center mt-1 text-sm text-gray-300">
+                <span>"Archive expired invites"</span>
+                <select
+                    class="px-2 py-1 rounded bg-gray-900 border border-gray-700 text-sm text-gray-100"
+                    on:change=move |ev| {
+                        let chosen = event_target_value(&ev).parse::<u32>().ok();
+                        save_cleanup_days(chosen);
+                        set_days.set(chosen);
+                    }
+                >
+                    <option value="" selected=move || days.get().is_none()>"never"</option>
+                    {CLEANUP_DAY_CHOICES.into_iter().map(|choice| view! {
+                        <option value=choice.to_string() selected=move || days.get() == Some(choice)>
+                            {format!("{} day{} after they expire", choice, if choice == 1 { "" } else { "s" })}
+                        </option>
+                    }).collect_view()}
+                </select>
+            </div>
+            <div class="text-sm text-gray-400">"Archived connections can be restored from the archive."</div>
+        </div>
+    }
+}
+
+// Bar above the list for the archive view, multi-select and clearing out
+// expired connections. Bulk actions are run by the parent.
+#[component]
+pub fn ListActions(
+    // Whether the archive is being shown instead of the list
+    #[prop(into)] viewing_archive: Signal<bool>,
+    on_toggle_archive: Callback<()>,
+    #[prop(into)] archived_count: Signal<usize>,
+    #[prop(into)] expired_count: Signal<usize>,
+    selecting: RwSignal<bool>,
+    selected: RwSignal<Vec<String>>,
+    on_archive_selected: Callback<()>,
+    on_delete_selected: Callback<()>,
+    on_clear_expired: Callback<()>,
+) -> impl IntoView {
+    let (confirm_clear, set_confirm_clear) = signal(false);
+    let small_button = "px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200";
+
+    view! {
+        <div class="flex flex-wrap gap-2 items-center mb-2 text-sm" data-test-id="list-actions">
+            {move || if selecting.get() {
+                let count = selected.get().len();
+                view! {
+                    <span class="text-gray-300">{format!("{} selected", count)}</span>
+                    <button class=small_button prop:disabled=count == 0 on:click=move |_| on_archive_selected.run(())>
+                        {move || if viewing_archive.get() { "Restore" } else { "Archive" }}
+                    </button>
+                    <button
+                        class="px-3 py-1 bg-red-600 hover:bg-red-700 rounded text-sm text-gray-100"
+                        prop:disabled=count == 0
+                        on:click=move |_| on_delete_selected.run(())
+                    >
+                        "Delete"
+                    </button>
+                    <button
+                        class=small_button
+                        on:click=move |_| {
+                            selected.set(Vec::new());
+                            selecting.set(false);
+                        }
+                    >
+                        "Done"
+                    </button>
+                }.into_any()
+            } else {
+                view! {
+                    <button class=small_button on:click=move |_| selecting.set(true)>"Select"</button>
+                    {move || (expired_count.get() > 0 && !viewing_archive.get()).then(|| if confirm_clear.get() {
+                        view! {
+                            <span class="text-gray-300">{format!("Delete {} expired?", expired_count.get())}</span>
+                            <button
+                                class="px-3 py-1 bg-red-600 hover:bg-red-700 rounded text-sm text-gray-100"
+                                on:click=move |_| {
+                                    set_confirm_clear.set(false);
+                                    on_clear_expired.run(());
+                                }
+                            >
+                                "Delete them"
+                            </button>
+                            <button class=small_button on:click=move |_| set_confirm_clear.set(false)>"Keep"</button>
+                        }.into_any()
+                    } else {
+                        view! {
+                            <button class=small_button on:click=move |_| set_confirm_clear.set(true)>
+                                {format!("Clear expired ({})", expired_count.get())}
+                            </button>
+                        }.into_any()
+                    })}
+                    {move || (archived_count.get() > 0 || viewing_archive.get()).then(|| view! {
+                        <button class=small_button on:click=move |_| on_toggle_archive.run(())>
+                            {move || if viewing_archive.get() {
+                                "Back to list".to_string()
+                            } else {
+                                format!("Archived ({})", archived_count.get())
+                            }}
+                        </button>
+                    })}
+                }.into_any()
+            }}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_component::test_connection;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn connection(status: ConnectionStatus, expires_at: i64) -> Connection {
+        Connection { expires_at, ..test_connection(&format!("archive-test-{}", uuid::Uuid::new_v4()), status) }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_due_for_cleanup() {
+        let day = 86400;
+        let lapsed = connection(ConnectionStatus::Pending, 10 * day);
+        assert!(!due_for_cleanup(&lapsed, 16 * day, 7));
+        assert!(due_for_cleanup(&lapsed, 17 * day, 7));
+
+        let active = connection(ConnectionStatus::Active, 10 * day);
+        assert!(!due_for_cleanup(&active, 100 * day, 7));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_archive_and_cleanup_policy() {
+        let expired = connection(ConnectionStatus::Expired, 0);
+        connection_utils::save_connection_to_local_storage(&expired, "Old friend").unwrap();
+
+        save_cleanup_days(None);
+        assert!(run_cleanup(&[expired.clone()], 30 * 86400).is_empty());
+        assert!(!is_archived(&expired.id));
+
+        save_cleanup_days(Some(7));
+        assert_eq!(load_cleanup_days(), Some(7));
+        assert_eq!(run_cleanup(&[expired.clone()], 30 * 86400), vec![expired.id.clone()]);
+        assert!(is_archived(&expired.id));
+        // Already archived connections aren't reported again
+        assert!(run_cleanup(&[expired.clone()], 30 * 86400).is_empty());
+
+        set_archived(&expired.id, false);
+        assert!(!is_archived(&expired.id));
+        save_cleanup_days(None);
+    }
+}
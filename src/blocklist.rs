@@ -0,0 +1,429 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::api_retry::{ApiError, RetryPolicy};
+use crate::browser::{console_log, storage};
+use crate::connect_component::get_stored_player_id;
+use crate::connection_utils;
+use crate::outbox::{self, OutboxAction};
+use crate::toast;
+
+// localStorage key for the players we've blocked
+const BLOCKED_KEY: &str = "blocked-players";
+// Longest free-text explanation sent with a report
+pub const MAX_REPORT_DETAILS_CHARS: usize = 500;
+
+// Someone we never want to hear from again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockedPlayer {
+    pub player_id: String,
+    // What we called them when they were blocked, so the list is readable
+    pub name: String,
+    pub blocked_at: i64,
+}
+
+// Why blocking didn't fully go through. The local block always holds.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockError {
+    // The connection is ended (or queued to end), but the server didn't record the block
+    NotSent(String),
+    // The connection we share with them couldn't be left and is still there
+    NotLeft(String),
+}
+
+impl BlockError {
+    pub fn message(&self) -> &str {
+        match self {
+            BlockError::NotSent(message) | BlockError::NotLeft(message) => message,
+        }
+    }
+}
+
+// Why a player is being reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    Impersonation,
+    Other,
+}
+
+impl ReportReason {
+    pub const ALL: [ReportReason; 4] =
+        [ReportReason::Spam, ReportReason::Harassment, ReportReason::Impersonation, ReportReason::Other];
+
+    // The value the server expects
+    pub fn code(self) -> &'static str {
+        match self {
+            ReportReason::Spam => "spam",
+            ReportReason::Harassment => "harassment",
+            ReportReason::Impersonation => "impersonation",
+            ReportReason::Other => "other",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReportReason::Spam => "Spam or advertising",
+            ReportReason::Harassment => "Harassment or abuse",
+            ReportReason::Impersonation => "Pretending to be someone else",
+            ReportReason::Other => "Something else",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<ReportReason> {
+        ReportReason::ALL.into_iter().find(|reason| reason.code() == code)
+    }
+}
+
+pub fn load_blocked() -> Vec<BlockedPlayer> {
+    storage()
+        .and_then(|storage| storage.get_item(BLOCKED_KEY).ok().flatten())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_blocked(blocked: &[BlockedPlayer]) {
+    if let (Some(storage), Ok(json)) = (storage(), serde_json::to_string(blocked)) {
+        let _ = storage.set_item(BLOCKED_KEY, &json);
+    }
+}
+
+pub fn is_blocked(player_id: &str) -> bool {
+    load_blocked().iter().any(|blocked| blocked.player_id == player_id)
+}
+
+pub fn add_blocked(player_id: &str, name: &str) {
+    let mut blocked = load_blocked();
+    if blocked.iter().any(|b| b.player_id == player_id) {
+        return;
+    }
+    blocked.push(BlockedPlayer {
+        player_id: player_id.to_string(),
+        name: name.to_string(),
+        blocked_at: (js_sys::Date::now() / 1000.0) as i64,
+    });
+    save_blocked(&blocked);
+}
+
+pub fn remove_blocked(player_id: &str) {
+    let mut blocked = load_blocked();
+    blocked.retain(|b| b.player_id != player_id);
+    save_blocked(&blocked);
+}
+
+// Whether a server notification is about someone we've blocked. Notifications
+// are plain strings, so this looks for a blocked player's id in the text.
+pub fn mentions_blocked(text: &str, blocked: &[BlockedPlayer]) -> bool {
+    blocked.iter().any(|b| !b.player_id.is_empty() && text.contains(&b.player_id))
+}
+
+// Whether a leave that failed should wait in the outbox: it only couldn't
+// reach the server, so it goes through once we're back online
+fn should_queue_leave(error: &ApiError, online: bool) -> bool {
+    error.is_network() || !online
+}
+
+// Block a player here and on the server. With `leave` set the connection we
+// share with them is ended too, queued in the outbox if we're offline; in a
+// group we stay for everyone else. The local block holds even if the server
+// can't be reached.
+pub async fn block(player_id: &str, name: &str, connection_id: Option<&str>, leave: bool) -> Result<(), BlockError> {
+    add_blocked(player_id, name);
+    console_log(&format!("Blocked {} locally", player_id));
+
+    let me = get_stored_player_id().ok_or_else(|| BlockError::NotSent("Blocked on this device.".to_string()))?;
+    let policy = RetryPolicy::user_action();
+    let describe = |e: JsValue| e.as_string().unwrap_or_else(|| format!("{:?}", e));
+
+    let mut not_left = None;
+    if leave {
+        if let Some(connection_id) = connection_id {
+            let client_id = uuid::Uuid::new_v4().to_string();
+            match connection_utils::leave_connection(connection_id, &me, &client_id, &policy).await {
+                Ok(()) => {},
+                Err(e) if should_queue_leave(&e, outbox::is_online()) => {
+                    console_log(&format!("Queued leaving {} after blocking", connection_id));
+                    outbox::enqueue(&me, OutboxAction::Leave { connection_id: connection_id.to_string() });
+                },
+                Err(e) => {
+                    console_log(&format!("Couldn't leave {} after blocking: {:?}", connection_id, e));
+                    not_left = Some(format!("Blocked {}, but couldn't end the connection ({}).", name, e.message()));
+                },
+            }
+        }
+    }
+
+    let sent = connection_utils::block_player(&me, player_id, connection_id, &policy).await;
+    if let Some(message) = not_left {
+        return Err(BlockError::NotLeft(message));
+    }
+    sent.map_err(|e| BlockError::NotSent(format!("Blocked on this device, but the server didn't get it ({}).", describe(e))))
+}
+
+// Lift a block here and on the server
+pub async fn unblock(player_id: &str) -> Result<(), String> {
+    remove_blocked(player_id);
+    let Some(me) = get_stored_player_id() else {
+        return Ok(());
+    };
+    connection_utils::unblock_player(&me, player_id, &RetryPolicy::user_action())
+        .await
+        .map_err(|e| e.as_string().unwrap_or_else(|| format!("{:?}", e)))
+}
+
+// Block and report actions for one player. Blocking asks for confirmation
+// first; reporting takes a reason and optional details.
+#[component]
+pub fn BlockReport(
+    player_id: String,
+    name: String,
+    #[prop(optional)] connection_id: Option<String>,
+    // Whether blocking also ends the connection, as it does one-to-one
+    #[prop(optional)] ends_connection: bool,
+    // Called once the player is blocked
+    on_blocked: Callback<()>,
+) -> impl IntoView {
+    let (confirming, set_confirming) = signal(false);
+    let (reporting, set_reporting) = signal(false);
+    let (reason, set_reason) = signal(ReportReason::Spam);
+    let (details, set_details) = signal(String::new());
+    let (busy, set_busy) = signal(false);
+    let toasts = toast::use_toasts();
+    let target = StoredValue::new((player_id, name, connection_id));
+
+    let handle_block = move |_| {
+        set_busy.set(true);
+        spawn_local(async move {
+            let (player_id, name, connection_id) = target.get_value();
+            let result = block(&player_id, &name, connection_id.as_deref(), ends_connection).await;
+            set_busy.set(false);
+            set_confirming.set(false);
+            match result {
+                Ok(()) => toasts.success(format!("Blocked {}.", name)),
+                Err(BlockError::NotSent(e)) => {
+                    console_log(&e);
+                    toasts.warning(e);
+                },
+                // The connection is still there, so it stays on the list
+                Err(e) => {
+                    console_log(e.message());
+                    toasts.error(e.message().to_string());
+                    return;
+                },
+            }
+            on_blocked.run(());
+        });
+    };
+
+    let handle_report = move |_| {
+        let Some(me) = get_stored_player_id() else {
+            toasts.warning("Create or join a connection before sending a report.");
+            return;
+        };
+        set_busy.set(true);
+        spawn_local(async move {
+            let (player_id, _, connection_id) = target.get_value();
+            let details: String = details.get_untracked().trim().chars().take(MAX_REPORT_DETAILS_CHARS).collect();
+            match connection_utils::report_player(
+                &me,
+                &player_id,
+                connection_id.as_deref(),
+                reason.get_untracked().code(),
+                &details,
+                &RetryPolicy::user_action(),
+            )
+            .await
+            {
+                Ok(()) => {
+                    set_reporting.set(false);
+                    set_details.set(String::new());
+                    toasts.success("Thanks, your report was sent.");
+                },
+                Err(e) => toasts.error(format!(
+                    "Couldn't send the report: {}",
+                    e.as_string().unwrap_or_else(|| format!("{:?}", e))
+                )),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let small_button = "px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200";
+
+    view! {
+        <div class="text-sm" data-test-id="block-report">
+            <div class="flex gap-2">
+                <button
+                    class=small_button
+                    prop:disabled=busy
+                    on:click=move |_| {
+                        set_reporting.set(false);
+                        set_confirming.update(|shown| *shown = !*shown);
+                    }
+                >
+                    "Block"
+                </button>
+                <button
+                    class=small_button
+                    prop:disabled=busy
+                    on:click=move |_| {
+                        set_confirming.set(false);
+                        set_reporting.update(|shown| *shown = !*shown);
+                    }
+                >
+                    "Report"
+                </button>
+            </div>
+            {move || confirming.get().then(|| view! {
+                <div class="mt-2 p-3 rounded bg-gray-900 border border-gray-700" data-test-id="block-confirm">
+                    <div class="text-gray-300">
+                        {if ends_connection {
+                            "Block this player? The connection ends and they can't reach you again."
+                        } else {
+                            "Block this player? You won't see their messages or hear about them again."
+                        }}
+                    </div>
+                    <div class="flex gap-2 mt-2">
+                        <button
+                            class="px-3 py-1 bg-red-600 hover:bg-red-700 rounded text-sm text-gray-100"
+                            prop:disabled=busy
+                            on:click=handle_block
+                        >
+                            "Block"
+                        </button>
+                        <button class=small_button on:click=move |_| set_confirming.set(false)>
+                            "Keep"
+                        </button>
+                    </div>
+                </div>
+            })}
+            {move || reporting.get().then(|| view! {
+                <div class="mt-2 p-3 rounded bg-gray-900 border border-gray-700" data-test-id="report-form">
+                    <select
+                        class="w-full px-2 py-1 rounded bg-gray-900 border border-gray-700 text-sm text-gray-100"
+                        on:change=move |ev| {
+                            if let Some(chosen) = ReportReason::from_code(&event_target_value(&ev)) {
+                                set_reason.set(chosen);
+                            }
+                        }
+                    >
+                        {ReportReason::ALL.into_iter().map(|choice| view! {
+                            <option value=choice.code() selected=move || reason.get() == choice>{choice.label()}</option>
+                        }).collect_view()}
+                    </select>
+                    <textarea
+                        class="w-full mt-2 px-2 py-1 rounded bg-gray-900 border border-gray-700 text-sm text-gray-100"
+                        rows="3"
+                        placeholder="What happened? (optional)"
+                        maxlength=MAX_REPORT_DETAILS_CHARS.to_string()
+                        prop:value=details
+                        on:input=move |ev| set_details.set(event_target_value(&ev))
+                    ></textarea>
+                    <div class="flex gap-2 mt-2">
+                        <button
+                            class="px-3 py-1 bg-indigo-600 hover:bg-indigo-700 rounded text-sm text-gray-100"
+                            prop:disabled=busy
+                            on:click=handle_report
+                        >
+                            "Send report"
+                        </button>
+                        <button class=small_button on:click=move |_| set_reporting.set(false)>
+                            "Cancel report"
+                        </button>
+                    </div>
+                </div>
+            })}
+        </div>
+    }
+}
+
+// Settings section listing blocked players, each of whom can be unblocked
+#[component]
+pub fn BlockedPlayers() -> impl IntoView {
+    let (blocked, set_blocked) = signal(load_blocked());
+    let toasts = toast::use_toasts();
+
+    let handle_unblock = move |player_id: String| {
+        spawn_local(async move {
+            if let Err(e) = unblock(&player_id).await {
+                toasts.warning(format!("Unblocked on this device, but the server didn't get it ({}).", e));
+            }
+            set_blocked.set(load_blocked());
+        });
+    };
+
+    view! {
+        <div class="mt-4 pt-4 border-t border-gray-700" data-test-id="blocked-players">
+            <div class="font-medium">"Blocked players"</div>
+            {move || if blocked.get().is_empty() {
+                view! { <div class="text-sm text-gray-400">"You haven't blocked anyone."</div> }.into_any()
+            } else {
+                blocked.get().into_iter().map(|player| {
+                    let player_id = player.player_id.clone();
+                    view! {
+                        <div class="flex justify-between items-center mt-2 text-sm">
+                            <span class="text-gray-300">{player.name}</span>
+                            <button
+                                class="px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200"
+                                on:click=move |_| handle_unblock(player_id.clone())
+                            >
+                                "Unblock"
+                            </button>
+                        </div>
+                    }
+                }).collect_view().into_any()
+            }}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_blocklist_round_trip() {
+        let player_id = format!("blocked-test-{}", uuid::Uuid::new_v4());
+        assert!(!is_blocked(&player_id));
+
+        add_blocked(&player_id, "Pest");
+        add_blocked(&player_id, "Pest again");
+        assert!(is_blocked(&player_id));
+        assert_eq!(load_blocked().iter().filter(|b| b.player_id == player_id).count(), 1);
+
+        remove_blocked(&player_id);
+        assert!(!is_blocked(&player_id));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_mentions_blocked() {
+        let blocked = vec![BlockedPlayer { player_id: "pest-123".to_string(), name: "Pest".to_string(), blocked_at: 0 }];
+        assert!(mentions_blocked("Player pest-123 joined link-1", &blocked));
+        assert!(!mentions_blocked("Player friend-9 joined link-1", &blocked));
+        assert!(!mentions_blocked("anything", &[]));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_only_unreachable_leaves_are_queued() {
+        let offline = ApiError::Network(JsValue::from_str("Failed to fetch"));
+        let refused = ApiError::Status { status: 403, message: "Not a member".to_string() };
+        assert!(should_queue_leave(&offline, true));
+        assert!(should_queue_leave(&refused, false));
+        assert!(!should_queue_leave(&refused, true));
+        assert!(!should_queue_leave(&ApiError::Other(JsValue::from_str("Bad response")), true));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_report_reason_codes_round_trip() {
+        for reason in ReportReason::ALL {
+            assert_eq!(ReportReason::from_code(reason.code()), Some(reason));
+        }
+        assert_eq!(ReportReason::from_code("nonsense"), None);
+    }
+}
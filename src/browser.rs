@@ -0,0 +1,13 @@
+use wasm_bindgen::JsValue;
+use web_sys::{console, window};
+
+// Small helpers every module that talks to the browser needs
+
+pub fn console_log(msg: &str) {
+    console::log_1(&JsValue::from_str(msg));
+}
+
+// This site's localStorage, if the browser lets us use it
+pub fn storage() -> Option<web_sys::Storage> {
+    window()?.local_storage().ok()?
+}
@@ -0,0 +1,349 @@
+use leptos::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::api_retry::RetryPolicy;
+use crate::blocklist;
+use crate::browser::console_log;
+use crate::connect_component::{get_connection_name, get_stored_player_id, Connection, ConnectionStatus};
+use crate::connection_list;
+use crate::connection_utils;
+use crate::e2e;
+use crate::group;
+use crate::notifications::{self, NotificationEvent, NotificationKind, UnreadBadge};
+use crate::outbox::{self, OutboxAction};
+use crate::profile::PlayerAvatar;
+use crate::toast;
+
+// How often the open chat checks for new messages
+const CHAT_POLL_SECONDS: u64 = 5;
+
+// One rendered line of the conversation
+#[derive(Debug, Clone, PartialEq)]
+struct ChatLine {
+    key: String,
+    sender: String,
+    text: String,
+    mine: bool,
+    encrypted: bool,
+    // Plaintext on an encrypted connection, replaced by a warning
+    rejected: bool,
+    pending: bool,
+}
+
+// Decrypt server messages and queued outgoing ones into chat lines
+async fn build_chat_lines(
+    link_id: &str,
+    connection_id: &str,
+    me: &str,
+    messages: Vec<connection_utils::Message>,
+) -> Vec<ChatLine> {
+    let mut lines = Vec::new();
+    let blocked = blocklist::load_blocked();
+
+    for (index, message) in messages.into_iter().enumerate() {
+        // Nothing a blocked player sends is shown
+        if blocked.iter().any(|b| b.player_id == message.player_id) {
+            continue;
+        }
+        let opened = e2e::open_message(link_id, &message.player_id, &message.content).await;
+        lines.push(ChatLine {
+            key: format!("{}-{}-{}", index, message.timestamp, message.player_id),
+            sender: message.player_id.clone(),
+            text: opened.text,
+            mine: message.player_id == me,
+            encrypted: opened.encrypted,
+            rejected: opened.rejected,
+            pending: false,
+        });
+    }
+
+    // Messages written while offline are shown until the outbox delivers them
+    for entry in outbox::load_outbox() {
+        if let OutboxAction::SendMessage { connection_id: queued_for, content } = &entry.action {
+            if queued_for == connection_id {
+                let opened = e2e::open_message(link_id, &entry.player_id, content).await;
+                lines.push(ChatLine {
+                    key: entry.id.clone(),
+                    sender: entry.player_id.clone(),
+                    text: opened.text,
+                    mine: true,
+                    encrypted: opened.encrypted,
+                    rejected: opened.rejected,
+                    pending: true,
+                });
+            }
+        }
+    }
+
+    lines
+}
+
+// Other people's messages that weren't on screen last time. Nothing is new on
+// the first load, when `seen` is None.
+fn fresh_lines(seen: Option<&[String]>, lines: &[ChatLine]) -> Vec<ChatLine> {
+    let Some(seen) = seen else {
+        return Vec::new();
+    };
+    lines
+        .iter()
+        .filter(|line| !line.mine && !line.pending && !seen.contains(&line.key))
+        .cloned()
+        .collect()
+}
+
+#[component]
+pub fn ChatPanel(#[prop(into)] connection: Connection) -> impl IntoView {
+    let connection_id = StoredValue::new(connection.id.clone());
+    let link_id = StoredValue::new(connection.link_id.clone());
+    let (lines, set_lines) = signal(Vec::<ChatLine>::new());
+    let (draft, set_draft) = signal(String::new());
+    let toasts = toast::use_toasts();
+    let encrypted_chat = e2e::has_connection_key(&connection.link_id);
+    let is_group = connection.is_group();
+    // Names to put on other members' messages in a group
+    let (member_names, set_member_names) = signal(Vec::<(String, String)>::new());
+    if is_group {
+        let link = connection.link_id.clone();
+        spawn_local(async move {
+            if let Ok(members) = group::fetch_members(&link).await {
+                set_member_names.set(members.iter().map(|member| (member.player_id.clone(), group::member_name(member))).collect());
+            }
+        });
+    }
+    let sender_name = move |sender: &str| {
+        member_names
+            .get()
+            .into_iter()
+            .find(|(player_id, _)| player_id == sender)
+            .map(|(_, name)| name)
+            .unwrap_or_else(|| "Someone who left".to_string())
+    };
+
+    // Messages that arrive while the tab is hidden count towards the unread
+    // badge and raise a desktop notification, like server notifications do
+    let seen = StoredValue::new(None::<Vec<String>>);
+    let badge = use_context::<UnreadBadge>();
+    let announce = move |lines: &[ChatLine]| {
+        let fresh = fresh_lines(seen.get_value().as_deref(), lines);
+        seen.set_value(Some(lines.iter().map(|line| line.key.clone()).collect()));
+        let Some(latest) = fresh.last() else {
+            return;
+        };
+        if !notifications::tab_hidden() {
+            return;
+        }
+        if let Some(badge) = badge {
+            for _ in &fresh {
+                badge.bump();
+            }
+        }
+
+        let conn_id = connection_id.get_value();
+        let event = NotificationEvent {
+            kind: NotificationKind::NewMessage,
+            connection_id: Some(conn_id.clone()),
+            text: latest.text.clone(),
+        };
+        if !notifications::should_notify(&event) {
+            return;
+        }
+        let name = get_connection_name(&conn_id).unwrap_or_else(|| "Your friend".to_string());
+        let title = if is_group {
+            let sender = member_names.with_untracked(|names| {
+                names.iter().find(|(player_id, _)| *player_id == latest.sender).map(|(_, name)| name.clone())
+            });
+            format!("New message from {} in {}", sender.unwrap_or_else(|| "someone".to_string()), name)
+        } else {
+            format!("New message from {}", name)
+        };
+        notifications::show_notification(&title, &event, || {});
+    };
+
+    let refresh = move || {
+        let Some(me) = get_stored_player_id() else {
+            return;
+        };
+        let conn_id = connection_id.get_value();
+        let link = link_id.get_value();
+
+        spawn_local(async move {
+            match connection_utils::get_messages(&conn_id, &RetryPolicy::polling()).await {
+                Ok(messages) => {
+                    let built = build_chat_lines(&link, &conn_id, &me, messages).await;
+                    announce(&built);
+                    set_lines.set(built);
+                },
+                Err(e) => {
+                    console_log(&format!("Failed to load messages: {:?}", e));
+                    // Still show what's waiting in the outbox
+                    set_lines.set(build_chat_lines(&link, &conn_id, &me, Vec::new()).await);
+                }
+            }
+        });
+    };
+
+    refresh();
+    if let Ok(handle) = set_interval_with_handle(refresh, std::time::Duration::from_secs(CHAT_POLL_SECONDS)) {
+        on_cleanup(move || handle.clear());
+    }
+
+    let send = move || {
+        let text = draft.get();
+        if text.trim().is_empty() {
+            return;
+        }
+        let Some(me) = get_stored_player_id() else {
+            toasts.error("No player ID found");
+            return;
+        };
+        let conn_id = connection_id.get_value();
+        let link = link_id.get_value();
+
+        set_draft.set(String::new());
+        connection_list::touch_activity(&conn_id);
+
+        spawn_local(async move {
+            let content = match e2e::seal_message(&link, &me, &text).await {
+                Ok(content) => content,
+                Err(e) => {
+                    // Never fall back to sending it in the clear
+                    toasts.error(format!("Message not sent: {}", e.as_string().unwrap_or_else(|| format!("{:?}", e))));
+                    set_draft.set(text);
+                    return;
+                }
+            };
+
+            let client_id = uuid::Uuid::new_v4().to_string();
+            match connection_utils::send_message_with_id(&conn_id, &me, &content, &client_id, &RetryPolicy::user_action()).await {
+                Ok(()) => {},
+                Err(e) if e.is_network() || !outbox::is_online() => {
+                    // Keep the (already encrypted) message and send it when we're back online
+                    outbox::enqueue(&me, OutboxAction::SendMessage {
+                        connection_id: conn_id.clone(),
+                        content,
+                    });
+                },
+                Err(e) => {
+                    let error_msg = e.message();
+                    toasts.error(format!("Message not sent: {}", error_msg));
+                    set_draft.set(text);
+                }
+            }
+            refresh();
+        });
+    };
+
+    view! {
+        <div class="mt-4 border-t border-gray-700 pt-4" data-test-id="chat-panel">
+            <div class="flex justify-between items-center mb-2">
+                <span class="text-sm font-medium text-gray-200">"Chat"</span>
+                {if encrypted_chat {
+                    view! {
+                        <span class="text-xs text-green-400" data-test-id="chat-encrypted" title=if is_group { "Only members of this group can read these messages" } else { "Only you and your friend can read these messages" }>
+                            "🔒 End-to-end encrypted"
+                        </span>
+                    }.into_any()
+                } else {
+                    view! {
+                        <span class="text-xs text-yellow-400" data-test-id="chat-unencrypted" title="This connection was made from a link without an encryption key">
+                            "Not encrypted"
+                        </span>
+                    }.into_any()
+                }}
+            </div>
+            {(connection.status == ConnectionStatus::Pending).then(|| view! {
+                <div class="text-xs text-gray-400 mb-2">
+                    {if is_group {
+                        "Nobody has joined yet; members will see messages once they do."
+                    } else {
+                        "Your friend hasn't joined yet; they'll see messages once they do."
+                    }}
+                </div>
+            })}
+            <div class="max-h-48 overflow-y-auto flex flex-col gap-1 mb-2">
+                <For
+                    each=move || lines.get()
+                    key=|line| (line.key.clone(), line.pending)
+                    let:line
+                >
+                    <div class=if line.mine { "self-end max-w-xs flex gap-2" } else { "self-start max-w-xs flex gap-2" }>
+                        {(!line.mine).then(|| view! {
+                            <PlayerAvatar player_id=line.sender.clone() size="h-6 w-6" />
+                        })}
+                        <div>
+                            {(is_group && !line.mine).then(|| {
+                                let sender = line.sender.clone();
+                                view! { <div class="text-xs text-gray-400">{move || sender_name(&sender)}</div> }
+                            })}
+                            <div class=if line.rejected {
+                                "px-3 py-1 rounded bg-yellow-900 text-yellow-100 text-sm"
+                            } else if line.mine {
+                                "px-3 py-1 rounded bg-indigo-700 text-gray-100 text-sm"
+                            } else {
+                                "px-3 py-1 rounded bg-gray-900 text-gray-100 text-sm"
+                            }>
+                                {line.text.clone()}
+                            </div>
+                            <div class="text-xs text-gray-500">
+                                {line.encrypted.then_some("🔒 ")}
+                                {line.pending.then_some("Pending")}
+                            </div>
+                        </div>
+                    </div>
+                </For>
+            </div>
+            <div class="flex gap-2">
+                <input
+                    type="text"
+                    class="w-full px-3 py-1 rounded bg-gray-900 border border-gray-700 text-gray-100"
+                    placeholder="Write a message"
+                    prop:value=draft
+                    on:input=move |ev| set_draft.set(event_target_value(&ev))
+                    on:keydown=move |ev: web_sys::KeyboardEvent| {
+                        if ev.key() == "Enter" {
+                            send();
+                        }
+                    }
+                />
+                <button
+                    class="px-3 py-1 bg-indigo-600 hover:bg-indigo-700 rounded text-sm text-gray-100"
+                    on:click=move |_| send()
+                >
+                    "Send"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn line(key: &str, mine: bool, pending: bool) -> ChatLine {
+        ChatLine {
+            key: key.to_string(),
+            sender: if mine { "me" } else { "friend" }.to_string(),
+            text: format!("message {}", key),
+            mine,
+            encrypted: true,
+            rejected: false,
+            pending,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_fresh_lines() {
+        let lines = vec![line("0", false, false), line("1", true, false), line("2", false, false), line("q", true, true)];
+        // The first load is history, not news
+        assert!(fresh_lines(None, &lines).is_empty());
+
+        let seen = vec!["0".to_string(), "1".to_string()];
+        let fresh = fresh_lines(Some(&seen), &lines);
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].key, "2");
+    }
+}
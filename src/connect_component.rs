@@ -1,13 +1,31 @@
 use leptos::*;
 use leptos::prelude::*;  
 use serde::{Serialize, Deserialize};
-use std::ops::Not;
+use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::spawn_local;
 use web_sys::console;
 
 use crate::connection_modal::ConnectionModal; 
 use crate::connection_utils;
 use crate::connection_item::ConnectionItem;
+use crate::api_retry::{is_network_error, RetryPolicy};
+use crate::outbox::{self, OutboxAction, ReplayOutcome};
+use crate::notifications::{self, NotificationKind};
+use crate::identity;
+use crate::blocklist::{self, BlockedPlayers};
+use crate::identity_settings::IdentitySettings;
+use crate::profile_settings::ProfileSettings;
+use crate::qr_scan::QrJoin;
+use crate::join_friend::JoinFriend;
+use crate::e2e;
+use crate::expiry;
+use crate::connection_list::{self, ListControls, TagFilter};
+use crate::tags;
+use crate::archive::{self, CleanupSettings, ListActions};
+use crate::toast::{self, Severity, ToastRegion};
+use crate::undo::{self, PendingUndo, UndoAction};
+use crate::approval;
+use crate::group;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ConnectionModalMode {
@@ -18,6 +36,8 @@ pub enum ConnectionModalMode {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ConnectionStatus {
     Pending,
+    // We asked to join and the friend who made the invite hasn't answered yet
+    Requested,
     Active,
     Expired,
 }
@@ -30,37 +50,181 @@ pub struct Connection {
     pub created_at: i64,
     pub status: ConnectionStatus,
     pub expires_at: i64,
+    // How many friends may join by the invite link; `None` for no limit
+    #[serde(default)]
+    pub max_uses: Option<u32>,
+    // How many have joined by it so far
+    #[serde(default)]
+    pub uses: u32,
+    // Seats on the connection, counting whoever made it; 2 for one-to-one
+    #[serde(default = "group::one_to_one")]
+    pub max_players: u32,
+}
+
+impl Connection {
+    pub fn is_group(&self) -> bool {
+        self.max_players > group::one_to_one()
+    }
+}
+
+// A single-use one-to-one connection for tests; change other fields with
+// `Connection { field, ..test_connection(id, status) }`
+#[cfg(test)]
+pub fn test_connection(id: &str, status: ConnectionStatus) -> Connection {
+    Connection {
+        id: id.to_string(),
+        link_id: format!("link-{}", id),
+        players: Vec::new(),
+        created_at: 0,
+        status,
+        expires_at: 0,
+        max_uses: Some(1),
+        uses: 0,
+        max_players: group::one_to_one(),
+    }
 }
 
 #[component]
 pub fn FriendsConnect() -> impl IntoView {
     let (show_connection, set_show_connection) = signal(false);
+    // Panel for joining from a picture of an invite QR code
+    let (show_qr_join, set_show_qr_join) = signal(false);
+    // Panel for joining by a pasted invite link, link id or join code
+    let (show_join_friend, set_show_join_friend) = signal(false);
     let (connection_name, set_connection_name) = signal(String::new());
     let (show_name_error, set_show_name_error) = signal(false);
-    let (api_error, set_api_error) = signal(String::new());
+    // Feedback shows as toasts; the last failed join is also kept so a retry
+    // creates a fresh invite instead of joining the same link again
+    let toasts = toast::provide_toasts();
+    let (join_error, set_join_error) = signal(String::new());
     let (current_connection, set_current_connection) = signal(None::<Connection>);
 
+    // Deletes and leaves whose undo window was cut short by the page closing
+    // are finished before anything is loaded; they had already happened on screen
+    undo::finish_interrupted();
+
     // Signal for active connections
     let (connections, set_connections) = signal(Vec::<Connection>::new());
 
+    // Actions waiting to reach the server, and whether the browser is online
+    let (outbox_entries, set_outbox_entries) = signal(outbox::load_outbox());
+    let (is_online, set_is_online) = signal(outbox::is_online());
+
+    // Desktop notification settings and the unread badge shown in the tab title
+    let (show_settings, set_show_settings) = signal(false);
+    let (notifications_on, set_notifications_on) = signal(
+        notifications::notifications_enabled() && notifications::permission_granted()
+    );
+    let (unread_count, set_unread_count) = signal(0usize);
+    provide_context(notifications::UnreadBadge(set_unread_count));
+    // Connection to open in response to a notification click; the counter makes repeat clicks count
+    let (focus_request, set_focus_request) = signal(None::<(String, u32)>);
+    // Connection whose join requests changed, per the server; bumped like `focus_request`
+    let (request_update, set_request_update) = signal(None::<(String, u32)>);
+    // Ticks so pending invites count down and lapse without a reload
+    let now = expiry::use_clock();
+    // Search, sort and status filter for the list, remembered across reloads
+    let list_options = RwSignal::new(connection_list::load_options());
+    // Bumped whenever a connection is pinned or tagged, so the list re-sorts
+    let (organised, set_organised) = signal(0u32);
+    let shown_connections = move || {
+        organised.track();
+        connection_list::arrange(&connections.get(), &list_options.get(), now.get(), connection_list::list_info)
+    };
+    // Multi-select for archiving or deleting several connections at once
+    let selecting = RwSignal::new(false);
+    let selected = RwSignal::new(Vec::<String>::new());
+    let archived_count = move || {
+        organised.track();
+        connections.get().iter().filter(|c| archive::is_archived(&c.id)).count()
+    };
+    let expired_ids = move || {
+        organised.track();
+        let now = now.get();
+        connections.get()
+            .iter()
+            .filter(|c| expiry::status_at(c, now) == ConnectionStatus::Expired && !archive::is_archived(&c.id))
+            .map(|c| c.id.clone())
+            .collect::<Vec<_>>()
+    };
+    let connections_with = move |ids: &[String]| {
+        connections.get_untracked().into_iter().filter(|c| ids.contains(&c.id)).collect::<Vec<_>>()
+    };
+
+    // Deletes, leaves and archives take effect on screen at once and can be
+    // undone for a few seconds before they're carried out
+    let undo_queue = StoredValue::new(Vec::<PendingUndo>::new());
+    let next_undo_id = StoredValue::new(0u32);
+    let take_undo = move |id: u32| {
+        let mut taken = None;
+        undo_queue.update_value(|queue| {
+            if let Some(index) = queue.iter().position(|entry| entry.id == id) {
+                taken = Some(queue.remove(index));
+            }
+        });
+        taken
+    };
+    let handle_undo = move |id: u32| {
+        let Some(pending) = take_undo(id) else {
+            return;
+        };
+        pending.undo();
+        if pending.action != UndoAction::Archive {
+            set_connections.update(|conns| {
+                for connection in pending.connections {
+                    if !conns.iter().any(|c| c.id == connection.id) {
+                        conns.push(connection);
+                    }
+                }
+            });
+        }
+        set_organised.update(|count| *count += 1);
+    };
+    let start_undo = move |action: UndoAction, affected: Vec<Connection>| {
+        if affected.is_empty() {
+            return;
+        }
+        let id = next_undo_id.get_value();
+        next_undo_id.set_value(id + 1);
+
+        let pending = PendingUndo::start(id, action, affected);
+        let ids = pending.ids();
+        if action == UndoAction::Archive {
+            set_organised.update(|count| *count += 1);
+        } else {
+            set_connections.update(|conns| conns.retain(|c| !ids.contains(&c.id)));
+        }
+        toasts.show_with_action(
+            Severity::Info,
+            pending.message.clone(),
+            "Undo",
+            Callback::new(move |_| handle_undo(id)),
+            std::time::Duration::from_secs(undo::UNDO_SECS),
+        );
+        undo_queue.update_value(|queue| queue.push(pending));
+
+        set_timeout(
+            move || {
+                if let Some(pending) = take_undo(id) {
+                    pending.commit();
+                }
+            },
+            std::time::Duration::from_secs(undo::UNDO_SECS),
+        );
+    };
+
     // Helper for logging
     let console_log = move |msg: &str| {
         console::log_1(&wasm_bindgen::JsValue::from_str(msg));
     };
 
-    // Effect to ensure a player ID exists
+    // Effect to ensure a player ID (and, where WebCrypto is available, a signing keypair) exists
     Effect::new(move |_| {
-        if get_stored_player_id().is_none() {
-            // No player ID exists, create one
-            let window = web_sys::window().expect("no global window exists");
-            let storage = window.local_storage()
-                .expect("failed to get localStorage")
-                .expect("localStorage not available");
-                
-            let new_id = uuid::Uuid::new_v4().to_string();
-            storage.set_item("player-id", &new_id)
-                .expect("failed to store player-id");
-        }
+        spawn_local(async move {
+            // Falls back to an unsigned id if setting up the keypair fails
+            let player_id = identity::player_id().await;
+            console_log(&format!("Player ID ready: {}", player_id));
+        });
     });
 
     // Effect to check for link ID in URL
@@ -68,6 +232,11 @@ pub fn FriendsConnect() -> impl IntoView {
         if let Some(link_id) = connection_utils::get_link_id_from_url() {
             console_log(&format!("Found link ID in URL: {}", link_id));
             
+            // Keep the end-to-end key from the invite before it leaves the address bar
+            if e2e::store_secret_from_url(&link_id) {
+                console_log("Stored end-to-end key from invite link");
+            }
+            
             // Auto-open the connection modal
             set_show_connection.set(true);
         }
@@ -100,8 +269,10 @@ pub fn FriendsConnect() -> impl IntoView {
                     // Convert expires_at to milliseconds for comparison with js_sys::Date::now()
                     let expires_at_ms = expires_at * 1000;
 
-                    // Set status based on expiration time
-                    let status = if expires_at_ms > js_sys::Date::now() as i64 {
+                    // Set status based on expiration time, unless we're waiting to be let in
+                    let status = if approval::is_awaiting(id) {
+                        ConnectionStatus::Requested
+                    } else if expires_at_ms > js_sys::Date::now() as i64 {
                         ConnectionStatus::Pending
                     } else {
                         ConnectionStatus::Expired
@@ -114,6 +285,12 @@ pub fn FriendsConnect() -> impl IntoView {
                         created_at,
                         status,
                         expires_at,
+                        max_uses: saved_conn.get("max_uses").and_then(|v| v.as_u64()).map(|max| max as u32),
+                        uses: 0,
+                        max_players: saved_conn.get("max_players")
+                            .and_then(|v| v.as_u64())
+                            .map(|max| max as u32)
+                            .unwrap_or_else(group::one_to_one),
                     };
                     
                     // Add to connections list if not already present
@@ -127,6 +304,277 @@ pub fn FriendsConnect() -> impl IntoView {
         }
     });
 
+    // Archive long-expired invites if the clean-up policy asks for it
+    Effect::new(move |_| {
+        let archived = archive::run_cleanup(&connections.get(), now.get());
+        if !archived.is_empty() {
+            console_log(&format!("Archived {} expired connection(s)", archived.len()));
+            set_organised.update(|count| *count += 1);
+        }
+    });
+
+    // Replay queued actions and fold the results back into the UI
+    let replay_outbox = move || {
+        if outbox_entries.get_untracked().is_empty() {
+            return;
+        }
+        
+        spawn_local(async move {
+            for outcome in outbox::replay_outbox().await {
+                match outcome {
+                    ReplayOutcome::Delivered { connection: Some(connection), .. } => {
+                        set_connections.update(|conns| {
+                            if let Some(index) = conns.iter().position(|c| c.id == connection.id) {
+                                conns[index] = connection;
+                            } else {
+                                conns.push(connection);
+                            }
+                        });
+                    },
+                    ReplayOutcome::Delivered { connection: None, .. } => {},
+                    ReplayOutcome::Rejected { entry, error } => {
+                        toasts.error(format!("Couldn't {}: {}", entry.action.describe(), error));
+                    }
+                }
+            }
+            set_outbox_entries.set(outbox::load_outbox());
+        });
+    };
+
+    // Effect to replay the outbox when the browser comes back online
+    Effect::new(move |_| {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        
+        let on_online = wasm_bindgen::closure::Closure::<dyn Fn()>::new(move || {
+            console_log("Browser is back online, replaying outbox");
+            set_is_online.set(true);
+            replay_outbox();
+        });
+        let on_offline = wasm_bindgen::closure::Closure::<dyn Fn()>::new(move || {
+            set_is_online.set(false);
+        });
+        
+        let _ = window.add_event_listener_with_callback("online", on_online.as_ref().unchecked_ref());
+        let _ = window.add_event_listener_with_callback("offline", on_offline.as_ref().unchecked_ref());
+        
+        // The listeners live as long as the page
+        on_online.forget();
+        on_offline.forget();
+        
+        // navigator.onLine can claim we're online while the backend is down,
+        // so also retry periodically while anything is queued
+        replay_outbox();
+        let _ = set_interval_with_handle(replay_outbox, std::time::Duration::from_secs(30));
+    });
+
+    // Keep the tab title in sync with the number of unseen events
+    Effect::new(move |_| {
+        let title = notifications::title_with_badge(unread_count.get());
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            document.set_title(&title);
+        }
+    });
+
+    // Effect to clear the unread badge whenever the tab becomes visible again
+    Effect::new(move |_| {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        
+        let on_visible = wasm_bindgen::closure::Closure::<dyn Fn()>::new(move || {
+            let hidden = web_sys::window()
+                .and_then(|w| w.document())
+                .map(|d| d.hidden())
+                .unwrap_or(false);
+            if !hidden {
+                set_unread_count.set(0);
+            }
+        });
+        
+        let _ = window.add_event_listener_with_callback("focus", on_visible.as_ref().unchecked_ref());
+        if let Some(document) = window.document() {
+            let _ = document.add_event_listener_with_callback("visibilitychange", on_visible.as_ref().unchecked_ref());
+        }
+        on_visible.forget();
+    });
+
+    // Turn server notifications into badge counts and desktop notifications
+    let handle_server_notifications = move |texts: Vec<String>| {
+        let known_connections = connections.get_untracked();
+        let hidden = web_sys::window()
+            .and_then(|w| w.document())
+            .map(|d| d.hidden())
+            .unwrap_or(false);
+        
+        let blocked = blocklist::load_blocked();
+        
+        for text in texts {
+            // Blocked players don't get to reach us through notifications either
+            if blocklist::mentions_blocked(&text, &blocked) {
+                console_log("Ignoring a notification about a blocked player");
+                continue;
+            }
+            let event = notifications::classify_notification(&text, &known_connections);
+            console_log(&format!("Notification: {}", event.text));
+            if let Some(id) = &event.connection_id {
+                connection_list::touch_activity(id);
+            }
+            
+            if hidden {
+                set_unread_count.update(|count| *count += 1);
+            }
+            
+            if matches!(event.kind, NotificationKind::JoinRequest | NotificationKind::RequestAnswered) {
+                if let Some(id) = event.connection_id.clone() {
+                    set_request_update.update(|request| {
+                        let nonce = request.as_ref().map(|(_, n)| n + 1).unwrap_or(0);
+                        *request = Some((id, nonce));
+                    });
+                }
+            }
+            
+            if !notifications::should_notify(&event) {
+                continue;
+            }
+            
+            let name = event.connection_id
+                .as_deref()
+                .and_then(get_connection_name)
+                .unwrap_or_else(|| "Your friend".to_string());
+            
+            // In a group, say who joined rather than just which group
+            let joined_group = event.connection_id
+                .as_deref()
+                .and_then(|id| known_connections.iter().find(|c| c.id == id && c.is_group()))
+                .filter(|_| event.kind == NotificationKind::FriendJoined)
+                .cloned();
+            if let Some(group_connection) = joined_group {
+                let event = event.clone();
+                spawn_local(async move {
+                    for member in group::newly_joined(&group_connection).await {
+                        let target = group_connection.id.clone();
+                        notifications::show_notification(
+                            &format!("{} joined {}", group::member_name(&member), name),
+                            &event,
+                            move || {
+                                set_focus_request.update(|request| {
+                                    let nonce = request.as_ref().map(|(_, n)| n + 1).unwrap_or(0);
+                                    *request = Some((target.clone(), nonce));
+                                });
+                            },
+                        );
+                    }
+                });
+                continue;
+            }
+            
+            let title = match event.kind {
+                NotificationKind::FriendJoined => format!("{} joined", name),
+                NotificationKind::NewMessage => format!("New message from {}", name),
+                NotificationKind::JoinRequest => format!("{} wants to connect", name),
+                NotificationKind::RequestAnswered => format!("{} answered your request", name),
+                NotificationKind::InviteExpiring => format!("Your invite to {} is about to expire", name),
+                NotificationKind::Other => notifications::APP_TITLE.to_string(),
+            };
+            
+            let target = event.connection_id.clone();
+            notifications::show_notification(&title, &event, move || {
+                if let Some(id) = target.clone() {
+                    set_focus_request.update(|request| {
+                        let nonce = request.as_ref().map(|(_, n)| n + 1).unwrap_or(0);
+                        *request = Some((id, nonce));
+                    });
+                }
+            });
+        }
+    };
+
+    // Effect to expire invites whose deadline passed and warn once about any
+    // that are about to lapse unanswered
+    Effect::new(move |_| {
+        let now = now.get();
+        let known_connections = connections.get_untracked();
+        
+        if known_connections.iter().any(|c| expiry::status_at(c, now) != c.status) {
+            set_connections.update(|conns| {
+                for conn in conns.iter_mut() {
+                    conn.status = expiry::status_at(conn, now);
+                }
+            });
+        }
+        
+        for conn in known_connections.iter().filter(|c| expiry::expiring_soon(c, now)) {
+            if expiry::already_warned(&conn.id) {
+                continue;
+            }
+            expiry::mark_warned(&conn.id);
+            
+            let event = notifications::NotificationEvent {
+                kind: NotificationKind::InviteExpiring,
+                connection_id: Some(conn.id.clone()),
+                text: format!(
+                    "It expires in {} and nobody has joined yet. Share it again or make a new one.",
+                    expiry::format_remaining(expiry::seconds_left(conn, now))
+                ),
+            };
+            if notifications::should_notify(&event) {
+                let name = get_connection_name(&conn.id).unwrap_or_else(|| "your friend".to_string());
+                let target = conn.id.clone();
+                notifications::show_notification(&format!("Your invite to {} is about to expire", name), &event, move || {
+                    set_focus_request.update(|request| {
+                        let nonce = request.as_ref().map(|(_, n)| n + 1).unwrap_or(0);
+                        *request = Some((target.clone(), nonce));
+                    });
+                });
+            }
+        }
+    });
+
+    // Effect to poll the server for friend-joined and message notifications
+    Effect::new(move |_| {
+        let poll = move || {
+            let Some(player_id) = get_stored_player_id() else {
+                return;
+            };
+            
+            spawn_local(async move {
+                match connection_utils::poll_notifications(&player_id, &RetryPolicy::polling()).await {
+                    Ok(texts) if !texts.is_empty() => handle_server_notifications(texts),
+                    Ok(_) => {},
+                    Err(e) => console_log(&format!("Failed to poll notifications: {:?}", e)),
+                }
+            });
+        };
+        
+        let _ = set_interval_with_handle(poll, std::time::Duration::from_secs(15));
+    });
+
+    // Ask for permission before switching desktop notifications on
+    let toggle_notifications = move |_| {
+        if notifications_on.get_untracked() {
+            notifications::set_notifications_enabled(false);
+            set_notifications_on.set(false);
+            return;
+        }
+        
+        spawn_local(async move {
+            match notifications::request_permission().await {
+                Ok(granted) => {
+                    notifications::set_notifications_enabled(granted);
+                    set_notifications_on.set(granted);
+                    if !granted {
+                        toasts.warning("Notifications are blocked for this site. You can allow them in your browser settings.");
+                    }
+                },
+                Err(e) => {
+                    console_log(&format!("Failed to request notification permission: {:?}", e));
+                }
+            }
+        });
+    };
+
     let create_connection = move || {
         let name = connection_name.get();
         if name.trim().is_empty() {
@@ -134,28 +582,23 @@ pub fn FriendsConnect() -> impl IntoView {
             return;
         }
     
-        let player_id = get_stored_player_id().unwrap_or_else(|| {
-            let new_id = uuid::Uuid::new_v4().to_string();
-            if let Some(window) = web_sys::window() {
-                if let Ok(Some(storage)) = window.local_storage() {
-                    let _ = storage.set_item("player-id", &new_id);
-                }
-            }
-            new_id
-        });
-    
         let name_clone = name.clone();
         console_log(&format!("Creating connection with name: {}", name));
         
         // Reset error state
-        set_api_error.set(String::new());
+        set_join_error.set(String::new());
         
         spawn_local(async move {
-            match connection_utils::create_connection(&player_id).await {
+            // Wait for the identity so we don't create under an id it's about to replace
+            let player_id = identity::player_id().await;
+            match connection_utils::create_connection(&player_id, &RetryPolicy::user_action()).await {
                 Ok(mut connection) => {
                     console_log(&format!("Connection created with ID: {} and link_id: {}", 
                         connection.id, connection.link_id));
                     
+                    // Generate the end-to-end key that travels in the invite link, if it gets one
+                    let _ = e2e::set_up_new_link(&connection.link_id);
+                    
                     // Check if we already have multiple players
                     if connection.players.len() >= 2 {
                         console_log("Two players are connected, setting status to Active");
@@ -195,7 +638,7 @@ pub fn FriendsConnect() -> impl IntoView {
                 Err(e) => {
                     let error_msg = format!("Error creating connection: {:?}", e);
                     console_log(&error_msg);
-                    set_api_error.set(error_msg);
+                    toasts.error(error_msg);
                 }
             }
         });
@@ -209,30 +652,22 @@ pub fn FriendsConnect() -> impl IntoView {
             return;
         }
 
-        let player_id = get_stored_player_id().unwrap_or_else(|| {
-            let new_id = uuid::Uuid::new_v4().to_string();
-            if let Some(window) = web_sys::window() {
-                if let Ok(Some(storage)) = window.local_storage() {
-                    let _ = storage.set_item("player-id", &new_id);
-                }
-            }
-            new_id
-        });
-
         let name_clone = name.clone();
         console_log(&format!("Joining connection with link ID: {}", link_id));
         
         // Reset error state
-        set_api_error.set(String::new());
+        set_join_error.set(String::new());
         
         spawn_local(async move {
-            match connection_utils::join_connection(&link_id, &player_id).await {
+            let player_id = identity::player_id().await;
+            match connection_utils::join_connection(&link_id, &player_id, &RetryPolicy::user_action()).await {
                 Ok(mut connection) => {
                     console_log(&format!("Connection joined with ID: {} and link_id: {}", 
                         connection.id, connection.link_id));
                     
-                    // Check if we already have multiple players
-                    if connection.players.len() >= 2 {
+                    // Check if we already have multiple players; an invite that needs
+                    // approval leaves us waiting instead
+                    if connection.status != ConnectionStatus::Requested && connection.players.len() >= 2 {
                         console_log("Two players are connected, setting status to Active");
                         connection.status = ConnectionStatus::Active;
                     }
@@ -246,6 +681,9 @@ pub fn FriendsConnect() -> impl IntoView {
                     
                     // Save the connection for later
                     let _ = connection_utils::save_connection_to_local_storage(&connection, &name_clone);
+                    if connection.status == ConnectionStatus::Requested {
+                        approval::set_awaiting(&connection.id, true);
+                    }
                     
                     // Update current connection
                     set_current_connection.set(Some(connection.clone()));
@@ -272,6 +710,26 @@ pub fn FriendsConnect() -> impl IntoView {
                     let error_msg = e.as_string().unwrap_or_else(|| format!("{:?}", e));
                     console_log(&format!("Error joining connection: {}", error_msg));
                     
+                    // Offline: queue the join and replay it once the backend is reachable
+                    if is_network_error(&e) || !outbox::is_online() {
+                        outbox::enqueue(&player_id, OutboxAction::Join {
+                            link_id: link_id.clone(),
+                            friendly_name: name_clone.clone(),
+                        });
+                        set_outbox_entries.set(outbox::load_outbox());
+                        let message = format!(
+                            "You're offline. We'll join {} as soon as the connection is back.",
+                            name_clone
+                        );
+                        set_join_error.set(message.clone());
+                        toasts.warning(message);
+                        
+                        // Close the modal
+                        set_show_connection.set(false);
+                        set_connection_name.set(String::new());
+                        return;
+                    }
+                    
                     // Check for specific error cases we want to handle specially
                     if error_msg.contains("Connection already has maximum players") || 
                     error_msg.contains("Player already in connection") {
@@ -288,7 +746,8 @@ pub fn FriendsConnect() -> impl IntoView {
                                 name_clone
                             )
                         };
-                        set_api_error.set(message);
+                        set_join_error.set(message.clone());
+                        toasts.info(message);
                         
                         // Clear the URL to switch to Create mode
                         if let Some(window) = web_sys::window() {
@@ -306,10 +765,13 @@ pub fn FriendsConnect() -> impl IntoView {
                         let name_clone2 = name_clone.clone();
                         
                         spawn_local(async move {
-                            match connection_utils::create_connection(&player_id_clone).await {
+                            match connection_utils::create_connection(&player_id_clone, &RetryPolicy::user_action()).await {
                                 Ok(connection) => {
                                     console_log(&format!("Auto-created new connection with ID: {}", connection.id));
                                     
+                                    // Generate the end-to-end key that travels in the invite link, if it gets one
+                                    let _ = e2e::set_up_new_link(&connection.link_id);
+                                    
                                     // Save friendly name for this connection
                                     if let Some(window) = web_sys::window() {
                                         if let Ok(Some(storage)) = window.local_storage() {
@@ -335,14 +797,16 @@ pub fn FriendsConnect() -> impl IntoView {
                                 Err(e) => {
                                     let error_msg = format!("Error creating new connection: {:?}", e);
                                     console_log(&error_msg);
-                                    set_api_error.set(error_msg);
+                                    toasts.error(error_msg);
                                 }
                             }
                         });
                     }
                     else {
                         // For all other errors, simply display them
-                        set_api_error.set(format!("Error joining connection: {}", error_msg));
+                        let message = format!("Error joining connection: {}", error_msg);
+                        set_join_error.set(message.clone());
+                        toasts.error(message);
                     }
                 }
             }
@@ -354,15 +818,15 @@ pub fn FriendsConnect() -> impl IntoView {
             <h2 class="text-xl font-bold mb-4 text-gray-100">"Connect with Friends"</h2>
             
             {move || {
-                if let Some(err) = api_error.get().as_str().is_empty().not().then(|| api_error.get()) {
-                    view! {
-                        <div class="bg-red-900 text-red-100 p-4 rounded mb-4">
-                            {err}
-                        </div>
-                    }.into_any()
-                } else {
-                    view! { <></> }.into_any()
-                }
+                let queued = outbox_entries.get().len();
+                (!is_online.get() || queued > 0).then(|| view! {
+                    <div class="bg-gray-800 text-gray-300 text-sm p-3 rounded mb-4 border border-gray-700" data-test-id="outbox-status">
+                        {if is_online.get() { "Reconnecting to the server." } else { "You're offline. Showing your saved connections." }}
+                        {(queued > 0).then(|| {
+                            format!(" {} action{} waiting to be sent.", queued, if queued == 1 { "" } else { "s" })
+                        })}
+                    </div>
+                })
             }}
             
             <button
@@ -374,6 +838,94 @@ pub fn FriendsConnect() -> impl IntoView {
             >
                 "New Connection"
             </button>
+            <button
+                class="px-4 py-2 bg-gray-800 hover:bg-gray-700 rounded text-gray-200 mb-4 ml-2"
+                on:click=move |_| set_show_settings.update(|shown| *shown = !*shown)
+            >
+                "Settings"
+            </button>
+            <button
+                class="px-4 py-2 bg-gray-800 hover:bg-gray-700 rounded text-gray-200 mb-4 ml-2"
+                on:click=move |_| set_show_qr_join.update(|shown| *shown = !*shown)
+            >
+                "Join from QR image"
+            </button>
+            <button
+                class="px-4 py-2 bg-gray-800 hover:bg-gray-700 rounded text-gray-200 mb-4 ml-2"
+                on:click=move |_| set_show_join_friend.update(|shown| *shown = !*shown)
+            >
+                "Join a friend"
+            </button>
+
+            {move || show_qr_join.get().then(|| view! {
+                <QrJoin on_join=Callback::new(move |_link_id: String| {
+                    set_show_qr_join.set(false);
+                    set_show_name_error.set(false);
+                    set_show_connection.set(true);
+                }) />
+            })}
+
+            {move || show_join_friend.get().then(|| view! {
+                <JoinFriend on_join=Callback::new(move |_link_id: String| {
+                    set_show_join_friend.set(false);
+                    set_show_name_error.set(false);
+                    set_show_connection.set(true);
+                }) />
+            })}
+
+            {move || show_settings.get().then(|| view! {
+                <div class="bg-gray-800 border border-gray-700 rounded p-4 mb-4" data-test-id="settings-panel">
+                    <div class="flex justify-between items-center">
+                        <div>
+                            <div class="font-medium">"Desktop notifications"</div>
+                            <div class="text-sm text-gray-400">
+                                {move || if !notifications::is_supported() {
+                                    "Your browser doesn't support notifications."
+                                } else if notifications::permission_denied() {
+                                    "Blocked in your browser settings."
+                                } else {
+                                    "Get notified when a friend joins or sends a message."
+                                }}
+                            </div>
+                        </div>
+                        <button
+                            class="px-3 py-1 bg-indigo-600 hover:bg-indigo-700 rounded text-sm text-gray-100"
+                            prop:disabled=move || !notifications::is_supported()
+                            on:click=toggle_notifications
+                        >
+                            {move || if notifications_on.get() { "Turn off" } else { "Turn on" }}
+                        </button>
+                    </div>
+                    <ProfileSettings />
+                    <IdentitySettings />
+                    <BlockedPlayers />
+                    <CleanupSettings />
+                </div>
+            })}
+
+            // Invites about to lapse without anyone joining
+            {move || {
+                let now = now.get();
+                let expiring: Vec<String> = connections.get()
+                    .iter()
+                    .filter(|c| expiry::expiring_soon(c, now))
+                    .map(|c| format!(
+                        "{} ({})",
+                        get_connection_name(&c.id).unwrap_or_else(|| "Unnamed Connection".to_string()),
+                        expiry::format_remaining(expiry::seconds_left(c, now))
+                    ))
+                    .collect();
+                (!expiring.is_empty()).then(|| view! {
+                    <div class="mt-4 p-3 rounded bg-yellow-900 text-yellow-100 text-sm" data-test-id="expiring-invites">
+                        {format!(
+                            "Invite{} about to expire with nobody joined: {}. Open {} to share it again.",
+                            if expiring.len() == 1 { "" } else { "s" },
+                            expiring.join(", "),
+                            if expiring.len() == 1 { "it" } else { "one" }
+                        )}
+                    </div>
+                })
+            }}
 
             // Display the list of connections
             <div class="mt-4">
@@ -387,26 +939,111 @@ pub fn FriendsConnect() -> impl IntoView {
                         }.into_any()
                     } else {
                         view! {
+                            {move || (connections.get().len() > connection_list::CONTROLS_MIN_CONNECTIONS || list_options.get().is_filtering())
+                                .then(|| view! { <ListControls options=list_options /> })}
+                            <TagFilter
+                                options=list_options
+                                known=Signal::derive(move || {
+                                    organised.track();
+                                    connections.track();
+                                    tags::known_tags()
+                                })
+                            />
+                            <ListActions
+                                viewing_archive=Signal::derive(move || list_options.get().archived)
+                                on_toggle_archive=Callback::new(move |_| {
+                                    selected.set(Vec::new());
+                                    list_options.update(|options| options.archived = !options.archived);
+                                })
+                                archived_count=Signal::derive(archived_count)
+                                expired_count=Signal::derive(move || expired_ids().len())
+                                selecting=selecting
+                                selected=selected
+                                on_archive_selected=Callback::new(move |_| {
+                                    let chosen = selected.get_untracked();
+                                    selected.set(Vec::new());
+                                    if list_options.get_untracked().archived {
+                                        // In the archive, the same action restores them
+                                        for id in chosen {
+                                            archive::set_archived(&id, false);
+                                        }
+                                        set_organised.update(|count| *count += 1);
+                                    } else {
+                                        start_undo(UndoAction::Archive, connections_with(&chosen));
+                                    }
+                                })
+                                on_delete_selected=Callback::new(move |_| {
+                                    let chosen = selected.get_untracked();
+                                    selected.set(Vec::new());
+                                    start_undo(UndoAction::Delete, connections_with(&chosen));
+                                })
+                                on_clear_expired=Callback::new(move |_| {
+                                    start_undo(UndoAction::Delete, connections_with(&expired_ids()));
+                                })
+                            />
+                            {move || shown_connections().is_empty().then(|| view! {
+                                <div class="text-gray-400 text-sm mt-2">
+                                    {if list_options.get().archived { "Nothing is archived." } else { "No connections match your search." }}
+                                </div>
+                            })}
                             <div class="border border-gray-700 rounded overflow-hidden">
                                 // Update the ConnectionItem rendering in FriendsConnect
                                 <For
-                                    each=move || connections.get()
+                                    each=shown_connections
                                     key=|conn| conn.id.clone()
                                     let:connection
                                 >
                                     {move || {
                                         let conn_id = connection.id.clone();
                                         let name = get_connection_name(&conn_id).unwrap_or_else(|| "Unnamed Connection".to_string());
+                                        let pending_id = conn_id.clone();
+                                        let focus_id = conn_id.clone();
+                                        let request_id = conn_id.clone();
+                                        let select_id = conn_id.clone();
+                                        let chosen_id = conn_id.clone();
                                         view! {
                                             <ConnectionItem 
                                                 connection=connection.clone() 
                                                 name=name 
-                                                on_delete=Callback::new(move |deleted_id: String| {
-                                                    // Remove the deleted connection from the connections list
-                                                    set_connections.update(|conns| {
-                                                        conns.retain(|c| c.id != deleted_id);
+                                                pending_actions=Signal::derive(move || {
+                                                    outbox::pending_for_connection(&outbox_entries.get(), &pending_id)
+                                                })
+                                                now=now
+                                                focus_request=Signal::derive(move || {
+                                                    focus_request.get()
+                                                        .filter(|(id, _)| *id == focus_id)
+                                                        .map(|(_, nonce)| nonce)
+                                                })
+                                                request_update=Signal::derive(move || {
+                                                    request_update.get()
+                                                        .filter(|(id, _)| *id == request_id)
+                                                        .map(|(_, nonce)| nonce)
+                                                })
+                                                on_organise=Callback::new(move |_| {
+                                                    set_organised.update(|count| *count += 1);
+                                                })
+                                                selecting=selecting
+                                                selected=Signal::derive(move || selected.get().contains(&chosen_id))
+                                                on_select=Callback::new(move |chosen: bool| {
+                                                    selected.update(|ids| {
+                                                        ids.retain(|id| *id != select_id);
+                                                        if chosen {
+                                                            ids.push(select_id.clone());
+                                                        }
                                                     });
                                                 })
+                                                on_delete=Callback::new(move |deleted_id: String| {
+                                                    start_undo(UndoAction::Delete, connections_with(&[deleted_id]));
+                                                })
+                                                on_leave=Callback::new(move |left_id: String| {
+                                                    start_undo(UndoAction::Leave, connections_with(&[left_id]));
+                                                })
+                                                on_archive=Callback::new(move |archived_id: String| {
+                                                    start_undo(UndoAction::Archive, connections_with(&[archived_id]));
+                                                })
+                                                on_blocked=Callback::new(move |blocked_id: String| {
+                                                    set_connections.update(|conns| conns.retain(|c| c.id != blocked_id));
+                                                })
                                             />
                                         }
                                     }}
@@ -415,12 +1052,35 @@ pub fn FriendsConnect() -> impl IntoView {
                         }.into_any()
                     }
                 }}
+                
+                // Joins made while offline, shown until the server confirms them
+                <For
+                    each=move || {
+                        outbox_entries.get()
+                            .into_iter()
+                            .filter_map(|entry| match entry.action {
+                                OutboxAction::Join { friendly_name, .. } => Some((entry.id, friendly_name)),
+                                _ => None,
+                            })
+                            .collect::<Vec<_>>()
+                    }
+                    key=|(id, _)| id.clone()
+                    let:queued_join
+                >
+                    <div class="flex justify-between items-center p-3 mt-2 border border-dashed border-gray-700 rounded text-gray-400">
+                        <div class="font-medium">{queued_join.1}</div>
+                        <span class="px-3 py-1 bg-gray-700 rounded text-sm">"Pending sync"</span>
+                    </div>
+                </For>
             </div>
 
+            <ToastRegion />
+
             {move || show_connection.get().then(|| view! {
                 <ConnectionModal
                     connection_name=connection_name
                     show_name_error=show_name_error
+                    join_error=join_error
                     on_name_change=Callback::new(move |new_name| {
                         set_show_name_error.set(false);
                         set_connection_name.set(new_name);
@@ -435,7 +1095,7 @@ pub fn FriendsConnect() -> impl IntoView {
                         } else {
                             // Check if we have a link ID in the URL
                             let url_link_id = connection_utils::get_link_id_from_url();
-                            if url_link_id.is_some() && api_error.get().is_empty() {
+                            if url_link_id.is_some() && join_error.get().is_empty() {
                                 // Join existing connection
                                 join_connection(url_link_id.unwrap());
                             } else if let Some(connection) = existing_connection {
@@ -477,7 +1137,7 @@ pub fn FriendsConnect() -> impl IntoView {
     }
 }
 
-fn get_connection_name(connection_id: &str) -> Option<String> {
+pub fn get_connection_name(connection_id: &str) -> Option<String> {
     let window = web_sys::window()?;
     let storage = window.local_storage().ok()??;
     
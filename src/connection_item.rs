@@ -1,40 +1,169 @@
 use leptos::*;
 use leptos::prelude::*;
 use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
 use web_sys::{Event, MouseEvent};
 
 use crate::connect_component::{Connection, ConnectionStatus};
+use crate::approval::{self, JoinRequests};
+use crate::archive;
 use crate::connection_modal::ConnectionModal;
+use crate::connection_utils;
+use crate::expiry;
+use crate::notifications;
+use crate::tags;
+use crate::profile::{self, PlayerAvatar};
+use crate::verification::{self, VerificationState};
 
 #[component]
 pub fn ConnectionItem(
     #[prop(into)] connection: Connection,
     #[prop(into)] name: String,
+    // Delete, leave and archive are carried out by the parent when it
+    // provides these, so it can offer to undo them
     #[prop(optional)] on_delete: Option<Callback<String>>,
+    #[prop(optional)] on_leave: Option<Callback<String>>,
+    #[prop(optional)] on_archive: Option<Callback<String>>,
+    // Called once blocking the friend has ended the connection; it's already
+    // forgotten here, since a block isn't something to undo
+    #[prop(optional)] on_blocked: Option<Callback<String>>,
+    #[prop(optional, into)] pending_actions: Signal<usize>,
+    // Changes whenever something (e.g. a notification click) asks to open this connection
+    #[prop(optional, into)] focus_request: Signal<Option<u32>>,
+    // Current time in seconds, shared so every item ticks together
+    #[prop(optional, into)] now: Option<Signal<i64>>,
+    // Changes whenever the server says something about a join request on this connection
+    #[prop(optional, into)] request_update: Signal<Option<u32>>,
+    // Called after the connection is pinned, unpinned or re-tagged
+    #[prop(optional)] on_organise: Option<Callback<()>>,
+    // Whether the list is in multi-select mode, and whether this one is picked
+    #[prop(optional, into)] selecting: Signal<bool>,
+    #[prop(optional, into)] selected: Signal<bool>,
+    #[prop(optional)] on_select: Option<Callback<bool>>,
 ) -> impl IntoView {
+    let now = now.unwrap_or_else(|| expiry::use_clock().into());
     // Create local clone of connection values to avoid ownership issues
-    let status = create_rw_signal(connection.status);
+    let status = create_rw_signal(connection.status.clone());
     let connection_id = create_rw_signal(connection.id.clone());
     let connection_name = create_rw_signal(name);
     let show_view_modal = create_rw_signal(false);
     let show_expired_modal = create_rw_signal(false);
+    let is_muted = RwSignal::new(notifications::is_muted(&connection.id));
+    let is_pinned = RwSignal::new(tags::is_pinned(&connection.id));
+    let is_archived = archive::is_archived(&connection.id);
+    let connection_tags = RwSignal::new(tags::load_tags(&connection.id));
+    let organised = move || {
+        if let Some(callback) = on_organise {
+            callback.run(());
+        }
+    };
+    let verification_state = RwSignal::new(verification::verification_state(&connection.id));
+    let friend = RwSignal::new(profile::cached_friend(&connection.id));
+    
+    // Refresh the friend's profile in the background; the cached copy shows meanwhile
+    if connection.status == ConnectionStatus::Active {
+        let connection = connection.clone();
+        spawn_local(async move {
+            if let Some(updated) = profile::refresh_friend(&connection).await {
+                friend.set(Some(updated));
+            }
+        });
+    }
     
     // Create a signal to track if this component is still valid
     // This helps prevent errors when trying to access deleted connections
     let is_valid = create_rw_signal(true);
     
-    // Function to handle status button click
-    let handle_status_click = move |_| {
+    // Function to open whichever modal fits the current status
+    let open_connection = move || {
         match status.get() {
             ConnectionStatus::Expired => {
                 show_expired_modal.set(true);
             },
-            ConnectionStatus::Pending | ConnectionStatus::Active => {
+            ConnectionStatus::Pending | ConnectionStatus::Requested | ConnectionStatus::Active => {
                 show_view_modal.set(true);
             }
         }
     };
     
+    // Function to handle status button click
+    let handle_status_click = move |_| open_connection();
+    
+    // Effect to open the connection when asked to from outside. Only a new
+    // request counts; one already made before this item was (re)created or
+    // re-rendered has been handled.
+    Effect::new(move |previous: Option<Option<u32>>| {
+        let request = focus_request.get();
+        if let Some(previous) = previous {
+            if request.is_some() && request != previous {
+                open_connection();
+            }
+        }
+        request
+    });
+    
+    // Effect to expire a pending invite as soon as its deadline passes
+    let invite = StoredValue::new(connection.clone());
+    let group_size = connection.is_group().then_some(connection.max_players);
+    Effect::new(move |_| {
+        let current = Connection { status: status.get(), ..invite.get_value() };
+        let lapsed = expiry::status_at(&current, now.get());
+        if lapsed != current.status {
+            status.set(lapsed);
+        }
+    });
+    
+    // Effect to see whether the friend answered our request to join, on load
+    // and whenever a notification about it arrives
+    Effect::new(move |_| {
+        request_update.track();
+        if status.get_untracked() != ConnectionStatus::Requested {
+            return;
+        }
+        let invite = invite.get_value();
+        spawn_local(async move {
+            if let Some(answered) = approval::check_request(&invite.id).await {
+                if answered == ConnectionStatus::Active {
+                    if let Some(updated) = profile::refresh_friend(&invite).await {
+                        friend.set(Some(updated));
+                    }
+                }
+                status.set(answered);
+            }
+        });
+    });
+    
+    // Whether the invite can still let friends in; `invite` isn't reactive, so
+    // accepting a request notifies `seats_changed`
+    let seats_changed = Trigger::new();
+    let taking_requests = move || {
+        seats_changed.track();
+        approval::takes_requests(&Connection { status: status.get(), ..invite.get_value() })
+    };
+    
+    // Time left on a pending invite, and whether it's about to lapse
+    let countdown = move || {
+        let current = Connection { status: status.get(), ..invite.get_value() };
+        let now = now.get();
+        (current.status == ConnectionStatus::Pending).then(|| {
+            (expiry::format_remaining(expiry::seconds_left(&current, now)), expiry::expiring_soon(&current, now))
+        })
+    };
+    
+    // Effect to pick up verification changes made in the view modal
+    Effect::new(move |_| {
+        if !show_view_modal.get() {
+            verification_state.set(verification::verification_state(&connection_id.get_untracked()));
+        }
+    });
+    
+    // Function to toggle desktop notifications for this connection
+    let handle_mute_click = move |_| {
+        let muted = !is_muted.get();
+        notifications::set_muted(&connection_id.get(), muted);
+        is_muted.set(muted);
+    };
+    
     // Function to handle refresh action
     let handle_refresh = move |_: MouseEvent| {
         web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(
@@ -43,6 +172,19 @@ pub fn ConnectionItem(
         show_expired_modal.set(false);
     };
     
+    // Move the connection into the archive, or back out of it
+    let handle_archive = move |_: MouseEvent| {
+        show_expired_modal.set(false);
+        show_view_modal.set(false);
+        match on_archive.filter(|_| !is_archived) {
+            Some(callback) => callback.run(connection_id.get()),
+            None => {
+                archive::set_archived(&connection_id.get(), !is_archived);
+                organised();
+            },
+        }
+    };
+    
     // Function to handle delete action
     let handle_delete = move |_: MouseEvent| {
         if !is_valid.get() {
@@ -54,44 +196,6 @@ pub fn ConnectionItem(
             &format!("Deleting connection: {}", conn_id)
         ));
         
-        // Remove from localStorage
-        if let Some(window) = web_sys::window() {
-            if let Ok(Some(storage)) = window.local_storage() {
-                // Remove the connection name
-                let name_key = format!("conn-name-{}", conn_id);
-                let _ = storage.remove_item(&name_key);
-                
-                // Remove from saved connections
-                if let Ok(Some(saved_json)) = storage.get_item("saved-connections") {
-                    if let Ok(mut saved_connections) = serde_json::from_str::<Vec<serde_json::Value>>(&saved_json) {
-                        // Filter out the connection to delete
-                        saved_connections.retain(|conn| {
-                            if let Some(id) = conn.get("id").and_then(|v| v.as_str()) {
-                                id != conn_id
-                            } else {
-                                true // Keep entries without an id
-                            }
-                        });
-                        
-                        // Save the updated list
-                        if let Ok(updated_json) = serde_json::to_string(&saved_connections) {
-                            let _ = storage.set_item("saved-connections", &updated_json);
-                        }
-                    }
-                }
-                
-                // Also remove from connection names map if it exists
-                if let Ok(Some(names_json)) = storage.get_item("connection-names") {
-                    if let Ok(mut names_map) = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&names_json) {
-                        names_map.remove(&conn_id);
-                        if let Ok(updated_json) = serde_json::to_string(&names_map) {
-                            let _ = storage.set_item("connection-names", &updated_json);
-                        }
-                    }
-                }
-            }
-        }
-        
         // Mark this component as invalid before calling delete callback
         is_valid.set(false);
         
@@ -99,14 +203,16 @@ pub fn ConnectionItem(
         show_expired_modal.set(false);
         show_view_modal.set(false);
         
-        // Call the delete callback if provided
-        if let Some(callback) = on_delete {
-            callback.run(conn_id);
+        // Call the delete callback if provided; otherwise there's nobody to undo it
+        match on_delete {
+            Some(callback) => callback.run(conn_id),
+            None => connection_utils::forget_connection(&conn_id),
         }
     };
     
     view! {
-        <div class="flex justify-between items-center p-3 border-b border-gray-700 last:border-b-0">
+        <div class="border-b border-gray-700 last:border-b-0">
+            <div class="flex justify-between items-center p-3">
             {move || {
                 // Skip rendering if this component is no longer valid
                 if !is_valid.get() {
@@ -115,12 +221,121 @@ pub fn ConnectionItem(
                 
                 view! {
                     <>
-                        <div class="font-medium">{connection_name}</div>
+                        <div class="flex items-center gap-2">
+                            {move || selecting.get().then(|| view! {
+                                <input
+                                    type="checkbox"
+                                    class="h-4 w-4"
+                                    data-test-id="select-connection"
+                                    prop:checked=selected
+                                    on:change=move |ev| {
+                                        if let Some(callback) = on_select {
+                                            callback.run(event_target_checked(&ev));
+                                        }
+                                    }
+                                />
+                            })}
+                            {move || friend.get().map(|f| view! {
+                                <PlayerAvatar player_id=f.player_id image=f.profile.and_then(|p| p.avatar) />
+                            })}
+                            <div>
+                                <div class="font-medium">
+                                    {connection_name}
+                                    {group_size.map(|size| view! {
+                                        <span class="ml-2 text-xs text-gray-400" title="Group connection" data-test-id="group-badge">
+                                            {format!("👥 up to {}", size)}
+                                        </span>
+                                    })}
+                                    // Our own name for them wins; show theirs alongside when it differs
+                                    {move || friend.get()
+                                        .and_then(|f| f.profile)
+                                        .map(|p| p.display_name)
+                                        .filter(|display_name| !display_name.is_empty() && *display_name != connection_name.get())
+                                        .map(|display_name| view! {
+                                            <span class="ml-2 text-sm text-gray-400" data-test-id="friend-display-name">
+                                                {format!("({})", display_name)}
+                                            </span>
+                                        })}
+                                    {move || match verification_state.get() {
+                                        VerificationState::Verified => Some(view! {
+                                            <span class="ml-2 text-xs text-green-400" title="Safety number verified" data-test-id="verified-badge">"✓"</span>
+                                        }.into_any()),
+                                        VerificationState::KeyChanged => Some(view! {
+                                            <span class="ml-2 text-xs text-yellow-300" title="Your friend's key changed; verify them again" data-test-id="key-changed-badge">"⚠"</span>
+                                        }.into_any()),
+                                        VerificationState::Unverified => None,
+                                    }}
+                                </div>
+                                {move || (!connection_tags.get().is_empty()).then(|| view! {
+                                    <div class="flex flex-wrap gap-1 mt-1" data-test-id="connection-tags">
+                                        {connection_tags.get().into_iter().map(|tag| view! {
+                                            <span class="px-2 py-0.5 rounded-full bg-gray-900 border border-gray-700 text-xs text-gray-400">
+                                                {format!("#{}", tag)}
+                                            </span>
+                                        }).collect_view()}
+                                    </div>
+                                })}
+                                {move || friend.get()
+                                    .and_then(|f| f.profile)
+                                    .map(|p| p.status)
+                                    .filter(|status_line| !status_line.is_empty())
+                                    .map(|status_line| view! {
+                                        <div class="text-sm text-gray-400" data-test-id="friend-status">{status_line}</div>
+                                    })}
+                            </div>
+                        </div>
                         <div>
+                            {move || {
+                                let pending = pending_actions.get();
+                                (pending > 0).then(|| view! {
+                                    <span class="mr-2 text-xs text-gray-400" data-test-id="pending-actions">
+                                        {format!("{} pending", pending)}
+                                    </span>
+                                })
+                            }}
+                            {is_archived.then(|| view! {
+                                <button
+                                    class="mr-2 px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200"
+                                    data-test-id="restore-connection"
+                                    on:click=handle_archive
+                                >
+                                    "Restore"
+                                </button>
+                            })}
+                            <button
+                                class="mr-2 px-2 py-1 text-sm text-gray-400 hover:text-gray-200"
+                                title=move || if is_pinned.get() { "Unpin" } else { "Pin to the top" }
+                                data-test-id="pin-toggle"
+                                on:click=move |_| {
+                                    let pinned = !is_pinned.get();
+                                    tags::set_pinned(&connection_id.get(), pinned);
+                                    is_pinned.set(pinned);
+                                    organised();
+                                }
+                            >
+                                {move || if is_pinned.get() { "★" } else { "☆" }}
+                            </button>
+                            <button
+                                class="mr-2 px-2 py-1 text-sm text-gray-400 hover:text-gray-200"
+                                title=move || if is_muted.get() { "Unmute notifications" } else { "Mute notifications" }
+                                data-test-id="mute-toggle"
+                                on:click=handle_mute_click
+                            >
+                                {move || if is_muted.get() { "🔕" } else { "🔔" }}
+                            </button>
+                            {move || countdown().map(|(left, soon)| view! {
+                                <span
+                                    class=if soon { "mr-2 text-xs text-yellow-300" } else { "mr-2 text-xs text-gray-400" }
+                                    data-test-id="expiry-countdown"
+                                >
+                                    {format!("{}expires in {}", if soon { "⚠ " } else { "" }, left)}
+                                </span>
+                            })}
                             - / -
                             <button 
                                 class={move || match status.get() {
                                     ConnectionStatus::Pending => "px-3 py-1 bg-yellow-600 hover:bg-yellow-700 rounded text-sm text-gray-100",
+                                    ConnectionStatus::Requested => "px-3 py-1 bg-blue-600 hover:bg-blue-700 rounded text-sm text-gray-100",
                                     ConnectionStatus::Active => "px-3 py-1 bg-green-600 hover:bg-green-700 rounded text-sm text-gray-100", 
                                     ConnectionStatus::Expired => "px-3 py-1 bg-red-600 hover:bg-red-700 rounded text-sm text-gray-100",
                                 }}
@@ -128,6 +343,7 @@ pub fn ConnectionItem(
                             >
                                 {move || match status.get() {
                                     ConnectionStatus::Pending => "Pending",
+                                    ConnectionStatus::Requested => "Awaiting approval",
                                     ConnectionStatus::Active => "Active",
                                     ConnectionStatus::Expired => "Expired",
                                 }}
@@ -136,6 +352,26 @@ pub fn ConnectionItem(
                     </>
                 }.into_any()
             }}
+            </div>
+            
+            // Friends asking to join one of our invites, for as long as it can take them
+            {move || (taking_requests() && is_valid.get()).then(|| view! {
+                <JoinRequests
+                    connection_id=connection_id.get_untracked()
+                    refresh=request_update
+                    on_accept=Callback::new(move |requester_id: String| {
+                        invite.update_value(|current| *current = approval::with_accepted(current, &requester_id));
+                        seats_changed.notify();
+                        status.set(ConnectionStatus::Active);
+                        let accepted = invite.get_value();
+                        spawn_local(async move {
+                            if let Some(updated) = profile::refresh_friend(&accepted).await {
+                                friend.set(Some(updated));
+                            }
+                        });
+                    })
+                />
+            })}
             
             // Modal for expired connections
             {move || {
@@ -163,6 +399,12 @@ pub fn ConnectionItem(
                                     >
                                         "Delete"
                                     </button>
+                                    <button
+                                        class="px-4 py-2 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-gray-200"
+                                        on:click=handle_archive
+                                    >
+                                        {if is_archived { "Restore" } else { "Archive" }}
+                                    </button>
                                     <button
                                         class="px-4 py-2 bg-blue-600 hover:bg-blue-700 rounded text-gray-100"
                                         on:click=handle_refresh.clone()
@@ -181,16 +423,26 @@ pub fn ConnectionItem(
             // View modal for pending connections
             {move || {
                 if show_view_modal.get() && is_valid.get() {
-                    let name_signal = create_signal(connection_name.get());
+                    // The name as it's being edited; saved only through `on_rename`
+                    let (name_draft, set_name_draft) = signal(connection_name.get_untracked());
                     
                     view! {
                         <ConnectionModal
-                            connection_name=name_signal.0
+                            connection_name=name_draft
                             show_name_error=create_signal(false).0
                             is_view_mode=true
                             connection_link_id=connection.link_id.clone()
-                            on_name_change=Callback::new(move |_| {
-                                // No-op for view mode
+                            connection=Connection { status: status.get(), ..connection.clone() }
+                            on_name_change=Callback::new(move |new_name| {
+                                set_name_draft.set(new_name);
+                            })
+                            on_rename=Callback::new(move |new_name| {
+                                connection_name.set(new_name);
+                                organised();
+                            })
+                            on_tags_change=Callback::new(move |new_tags| {
+                                connection_tags.set(new_tags);
+                                organised();
                             })
                             on_cancel=Callback::new(move |_| {
                                 show_view_modal.set(false);
@@ -198,6 +450,20 @@ pub fn ConnectionItem(
                             on_delete=Callback::new(move |_| {
                                 handle_delete(web_sys::MouseEvent::new("click").unwrap());
                             })
+                            on_leave=on_leave.map(|callback| Callback::new(move |_| {
+                                is_valid.set(false);
+                                show_view_modal.set(false);
+                                callback.run(connection_id.get());
+                            }))
+                            on_blocked=Callback::new(move |_| {
+                                is_valid.set(false);
+                                show_view_modal.set(false);
+                                let conn_id = connection_id.get_untracked();
+                                connection_utils::forget_connection(&conn_id);
+                                if let Some(callback) = on_blocked {
+                                    callback.run(conn_id);
+                                }
+                            })
                             on_submit=Callback::new(move |_| {
                                 handle_refresh(web_sys::MouseEvent::new("click").unwrap());
                                 show_view_modal.set(false);
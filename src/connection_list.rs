@@ -0,0 +1,385 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::archive;
+use crate::browser::storage;
+use crate::connect_component::{get_connection_name, Connection, ConnectionStatus};
+use crate::connection_utils;
+use crate::expiry;
+use crate::tags;
+
+// localStorage key for how the connections list is searched, sorted and filtered
+const LIST_OPTIONS_KEY: &str = "connection-list-options";
+// Saved-connection field with the last time something happened on a connection
+const ACTIVITY_FIELD: &str = "last_activity";
+// Lists longer than this get search, sort and filter controls
+pub const CONTROLS_MIN_CONNECTIONS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SortOrder {
+    #[default]
+    Recent,
+    Name,
+    Expiry,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 3] = [SortOrder::Recent, SortOrder::Name, SortOrder::Expiry];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Recent => "Recent activity",
+            SortOrder::Name => "Name",
+            SortOrder::Expiry => "Expiring first",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<SortOrder> {
+        SortOrder::ALL.into_iter().find(|order| order.label() == label)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum StatusFilter {
+    #[default]
+    All,
+    Pending,
+    Active,
+    Expired,
+}
+
+impl StatusFilter {
+    pub const ALL: [StatusFilter; 4] = [StatusFilter::All, StatusFilter::Pending, StatusFilter::Active, StatusFilter::Expired];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StatusFilter::All => "All",
+            StatusFilter::Pending => "Pending",
+            StatusFilter::Active => "Active",
+            StatusFilter::Expired => "Expired",
+        }
+    }
+
+    // Connections waiting for approval count as pending
+    pub fn matches(self, status: &ConnectionStatus) -> bool {
+        match self {
+            StatusFilter::All => true,
+            StatusFilter::Pending => matches!(status, ConnectionStatus::Pending | ConnectionStatus::Requested),
+            StatusFilter::Active => *status == ConnectionStatus::Active,
+            StatusFilter::Expired => *status == ConnectionStatus::Expired,
+        }
+    }
+}
+
+// How the connections list is shown; remembered across reloads
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ListOptions {
+    #[serde(default)]
+    pub query: String,
+    #[serde(default)]
+    pub sort: SortOrder,
+    #[serde(default)]
+    pub status: StatusFilter,
+    // Only connections with this tag
+    #[serde(default)]
+    pub tag: Option<String>,
+    // Show archived connections instead of the rest; always starts off
+    #[serde(skip)]
+    pub archived: bool,
+}
+
+impl ListOptions {
+    // Whether anything could be hiding connections
+    pub fn is_filtering(&self) -> bool {
+        !self.query.trim().is_empty() || self.status != StatusFilter::All || self.tag.is_some()
+    }
+}
+
+pub fn load_options() -> ListOptions {
+    storage()
+        .and_then(|storage| storage.get_item(LIST_OPTIONS_KEY).ok().flatten())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_options(options: &ListOptions) {
+    let Some(storage) = storage() else {
+        return;
+    };
+    if *options == ListOptions::default() {
+        let _ = storage.remove_item(LIST_OPTIONS_KEY);
+    } else if let Ok(json) = serde_json::to_string(options) {
+        let _ = storage.set_item(LIST_OPTIONS_KEY, &json);
+    }
+}
+
+// What the list needs to know about a connection beyond the connection itself
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ListInfo {
+    pub name: String,
+    // When something last happened on it
+    pub activity: i64,
+    pub pinned: bool,
+    pub tags: Vec<String>,
+    pub archived: bool,
+}
+
+// Look up a connection's name, activity, pin and tags from local storage
+pub fn list_info(connection: &Connection) -> ListInfo {
+    let activity = connection_utils::get_saved_connection(&connection.id)
+        .and_then(|record| record.get(ACTIVITY_FIELD).and_then(|v| v.as_i64()))
+        .unwrap_or(connection.created_at)
+        .max(connection.created_at);
+    ListInfo {
+        name: get_connection_name(&connection.id).unwrap_or_default(),
+        activity,
+        pinned: tags::is_pinned(&connection.id),
+        tags: tags::load_tags(&connection.id),
+        archived: archive::is_archived(&connection.id),
+    }
+}
+
+// Note that something just happened on a connection
+pub fn touch_activity(connection_id: &str) {
+    connection_utils::update_saved_connection(connection_id, |record| {
+        record.insert(ACTIVITY_FIELD.to_string(), serde_json::json!(expiry::now_secs()));
+    });
+}
+
+fn status_rank(status: &ConnectionStatus) -> u8 {
+    match status {
+        ConnectionStatus::Pending | ConnectionStatus::Requested => 0,
+        ConnectionStatus::Active => 1,
+        ConnectionStatus::Expired => 2,
+    }
+}
+
+// The connections to show, in order, pinned ones first. `info` looks up
+// what isn't on the connection itself.
+pub fn arrange(
+    connections: &[Connection],
+    options: &ListOptions,
+    now: i64,
+    info: impl Fn(&Connection) -> ListInfo,
+) -> Vec<Connection> {
+    let query = options.query.trim().to_lowercase();
+    let mut shown: Vec<(Connection, ListInfo)> = connections
+        .iter()
+        .filter(|c| options.status.matches(&expiry::status_at(c, now)))
+        .map(|c| (c.clone(), info(c)))
+        .filter(|(_, found)| found.archived == options.archived)
+        .filter(|(_, found)| query.is_empty() || found.name.to_lowercase().contains(&query))
+        .filter(|(_, found)| options.tag.as_ref().is_none_or(|tag| found.tags.contains(tag)))
+        .collect();
+
+    match options.sort {
+        SortOrder::Recent => shown.sort_by_key(|(_, found)| std::cmp::Reverse(found.activity)),
+        SortOrder::Name => shown.sort_by_key(|(_, found)| found.name.to_lowercase()),
+        // Open invites closest to lapsing first, then everything else
+        SortOrder::Expiry => shown.sort_by_key(|(c, _)| (status_rank(&expiry::status_at(c, now)), c.expires_at)),
+    }
+    // Stable, so pinned and unpinned connections each keep the chosen order
+    shown.sort_by_key(|(_, found)| !found.pinned);
+
+    shown.into_iter().map(|(connection, _)| connection).collect()
+}
+
+// Search box, sort order and status filter for the connections list
+#[component]
+pub fn ListControls(options: RwSignal<ListOptions>) -> impl IntoView {
+    let update = move |change: &dyn Fn(&mut ListOptions)| {
+        options.update(|current| change(current));
+        save_options(&options.get_untracked());
+    };
+
+    let select_class = "px-2 py-1 rounded bg-gray-900 border border-gray-700 text-sm text-gray-100";
+
+    view! {
+        <div class="flex flex-wrap gap-2 items-center mb-2" data-test-id="list-controls">
+            <input
+                type="text"
+                class="flex-1 min-w-0 px-3 py-1 rounded bg-gray-900 border border-gray-700 text-sm text-gray-100"
+                placeholder="Search friends"
+                prop:value=move || options.get().query
+                on:input=move |ev| {
+                    let query = event_target_value(&ev);
+                    update(&|current| current.query = query.clone());
+                }
+            />
+            <select
+                class=select_class
+                on:change=move |ev| {
+                    if let Some(sort) = SortOrder::from_label(&event_target_value(&ev)) {
+                        update(&|current| current.sort = sort);
+                    }
+                }
+            >
+                {SortOrder::ALL.into_iter().map(|sort| view! {
+                    <option value=sort.label() selected=move || options.get().sort == sort>{sort.label()}</option>
+                }).collect_view()}
+            </select>
+            <div class="flex gap-1">
+                {StatusFilter::ALL.into_iter().map(|status| view! {
+                    <button
+                        class=move || if options.get().status == status {
+                            "px-2 py-1 rounded text-sm bg-indigo-600 text-gray-100"
+                        } else {
+                            "px-2 py-1 rounded text-sm bg-gray-800 hover:bg-gray-600 border border-gray-600 text-gray-300"
+                        }
+                        on:click=move |_| update(&|current| current.status = status)
+                    >
+                        {status.label()}
+                    </button>
+                }).collect_view()}
+            </div>
+        </div>
+    }
+}
+
+// Chips above the list for narrowing it to one tag; shown once any tag is in use
+#[component]
+pub fn TagFilter(
+    options: RwSignal<ListOptions>,
+    // Every tag in use
+    #[prop(into)] known: Signal<Vec<String>>,
+) -> impl IntoView {
+    let choose = move |tag: Option<String>| {
+        options.update(|current| current.tag = tag.clone());
+        save_options(&options.get_untracked());
+    };
+    let chip_class = move |selected: bool| if selected {
+        "px-2 py-0.5 rounded-full text-xs bg-indigo-600 text-gray-100"
+    } else {
+        "px-2 py-0.5 rounded-full text-xs bg-gray-800 hover:bg-gray-600 border border-gray-600 text-gray-300"
+    };
+
+    view! {
+        {move || (!known.get().is_empty() || options.get().tag.is_some()).then(|| view! {
+            <div class="flex flex-wrap gap-1 mb-2" data-test-id="tag-filter">
+                <button
+                    class=move || chip_class(options.get().tag.is_none())
+                    on:click=move |_| choose(None)
+                >
+                    "All tags"
+                </button>
+                {known.get().into_iter().map(|tag| {
+                    let chosen = tag.clone();
+                    let shown = tag.clone();
+                    view! {
+                        <button
+                            class=move || chip_class(options.get().tag.as_deref() == Some(shown.as_str()))
+                            on:click=move |_| choose(Some(chosen.clone()))
+                        >
+                            {format!("#{}", tag)}
+                        </button>
+                    }
+                }).collect_view()}
+            </div>
+        })}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_component::test_connection;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn connection(id: &str, status: ConnectionStatus, created_at: i64, expires_at: i64) -> Connection {
+        Connection { created_at, expires_at, ..test_connection(id, status) }
+    }
+
+    fn sample() -> Vec<Connection> {
+        vec![
+            connection("sam", ConnectionStatus::Active, 10, 100),
+            connection("alex", ConnectionStatus::Pending, 20, 500),
+            connection("kim", ConnectionStatus::Pending, 30, 300),
+            connection("old", ConnectionStatus::Expired, 5, 50),
+        ]
+    }
+
+    fn ids(connections: &[Connection]) -> Vec<&str> {
+        connections.iter().map(|c| c.id.as_str()).collect()
+    }
+
+    fn info(c: &Connection) -> ListInfo {
+        ListInfo {
+            name: c.id.to_uppercase(),
+            activity: c.created_at,
+            pinned: c.id == "old",
+            tags: if c.id == "sam" || c.id == "kim" { vec!["work".to_string()] } else { Vec::new() },
+            archived: false,
+        }
+    }
+
+    fn unpinned(c: &Connection) -> ListInfo {
+        ListInfo { pinned: false, ..info(c) }
+    }
+
+    fn arrange_sample(options: &ListOptions) -> Vec<Connection> {
+        arrange(&sample(), options, 200, unpinned)
+    }
+
+    #[wasm_bindgen_test]
+    fn test_sort_orders() {
+        let mut options = ListOptions::default();
+        assert_eq!(ids(&arrange_sample(&options)), ["kim", "alex", "sam", "old"]);
+
+        options.sort = SortOrder::Name;
+        assert_eq!(ids(&arrange_sample(&options)), ["alex", "kim", "old", "sam"]);
+
+        options.sort = SortOrder::Expiry;
+        assert_eq!(ids(&arrange_sample(&options)), ["kim", "alex", "sam", "old"]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_search_and_status_filter() {
+        let options = ListOptions { query: " AL ".to_string(), ..Default::default() };
+        assert_eq!(ids(&arrange_sample(&options)), ["alex"]);
+
+        let options = ListOptions { status: StatusFilter::Pending, sort: SortOrder::Name, ..Default::default() };
+        assert_eq!(ids(&arrange_sample(&options)), ["alex", "kim"]);
+
+        // Pending invites past their deadline count as expired
+        let lapsed = arrange(&sample(), &ListOptions { status: StatusFilter::Expired, ..Default::default() }, 400, unpinned);
+        assert_eq!(ids(&lapsed), ["kim", "old"]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pinned_first_and_tag_filter() {
+        let mut options = ListOptions { sort: SortOrder::Name, ..Default::default() };
+        assert_eq!(ids(&arrange(&sample(), &options, 200, info)), ["old", "alex", "kim", "sam"]);
+
+        options.tag = Some("work".to_string());
+        assert_eq!(ids(&arrange(&sample(), &options, 200, info)), ["kim", "sam"]);
+        assert!(options.is_filtering());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_archived_connections_shown_apart() {
+        let archived = |c: &Connection| ListInfo { archived: c.id == "old" || c.id == "sam", ..unpinned(c) };
+        let mut options = ListOptions { sort: SortOrder::Name, ..Default::default() };
+        assert_eq!(ids(&arrange(&sample(), &options, 200, archived)), ["alex", "kim"]);
+
+        options.archived = true;
+        assert_eq!(ids(&arrange(&sample(), &options, 200, archived)), ["old", "sam"]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_options_round_trip_through_storage() {
+        let chosen = ListOptions {
+            query: "sam".to_string(),
+            sort: SortOrder::Expiry,
+            status: StatusFilter::Active,
+            tag: Some("work".to_string()),
+            archived: false,
+        };
+        save_options(&chosen);
+        assert_eq!(load_options(), chosen);
+
+        save_options(&ListOptions::default());
+        assert_eq!(load_options(), ListOptions::default());
+    }
+}
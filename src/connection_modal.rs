@@ -6,6 +6,24 @@ use wasm_bindgen_futures::spawn_local;
 
 use crate::connection_utils::get_link_id_from_url;
 use crate::connect_component::{Connection, get_stored_player_id};
+use crate::api_retry::RetryPolicy;
+use crate::blocklist::BlockReport;
+use crate::chat::ChatPanel;
+use crate::notes::ConnectionNote;
+use crate::tags::TagEditor;
+use crate::toast;
+use crate::rename;
+use crate::verify_friend::VerifyFriend;
+use crate::connect_component::ConnectionStatus;
+use crate::e2e;
+use crate::identity;
+use crate::expiry;
+use crate::group::MemberList;
+use crate::qr::InviteQr;
+use crate::invite_share::{CopyButton, ShareInvite};
+use crate::join_code::JoinCodeOffer;
+use crate::invite_policy::{InvitePolicy, InvitePolicyPicker, InvitePolicySummary};
+use crate::profile::{self, Friend, ProfileCard};
 
 #[component]
 pub fn ConnectionModal(
@@ -14,15 +32,29 @@ pub fn ConnectionModal(
     #[prop(into)] on_name_change: Callback<String>,
     #[prop(into)] on_cancel: Callback<()>,
     #[prop(into)] on_submit: Callback<Option<Connection>>,
+    // Why joining from the invite in the URL last failed, if it did
+    #[prop(optional, into)] join_error: Signal<String>,
     #[prop(optional)] is_view_mode: bool,
     #[prop(optional)] on_delete: Option<Callback<()>>,
     #[prop(optional)] connection_link_id: Option<String>,
+    // The connection being viewed, used for its chat
+    #[prop(optional)] connection: Option<Connection>,
+    // Called with the new name once a viewed connection has been renamed
+    #[prop(optional)] on_rename: Option<Callback<String>>,
+    // Called with a viewed connection's tags whenever they're edited
+    #[prop(optional)] on_tags_change: Option<Callback<Vec<String>>>,
+    // Leave a viewed connection, giving up our seat on the server
+    #[prop(optional_no_strip)] on_leave: Option<Callback<()>>,
+    // Called once the friend on a viewed one-to-one connection is blocked,
+    // which has already ended the connection
+    #[prop(optional)] on_blocked: Option<Callback<()>>,
 ) -> impl IntoView {
     // Create signals for the link ID
     let (link_id, set_link_id) = signal(String::new());
     let (loading_link, set_loading_link) = signal(false);
     let (link_error, set_link_error) = signal(String::new());
     let (created_connection, set_created_connection) = signal(None::<Connection>);
+    let link_input = NodeRef::<leptos::html::Input>::new();
     
     // Function for console logging
     let console_log = move |msg: &str| {
@@ -32,43 +64,60 @@ pub fn ConnectionModal(
     // Update the request_new_link_id function to store the connection
     let request_new_link_id = move || {
         console_log("Generating new link ID via API call");
-        // Check if we have a player ID
-        if let Some(player_id) = get_stored_player_id() {
-            // Set loading state
-            set_loading_link.set(true);
-            set_link_error.set(String::new());
-            
-            // Clone player ID for the async closure
-            let player_id_clone = player_id.clone();
-            
-            // Spawn async task to request link ID
-            spawn_local(async move {
-                match crate::connection_utils::create_connection(&player_id_clone).await {
-                    Ok(connection) => {
-                        // Extract the link ID
-                        let new_link_id = connection.link_id.clone();
-                        console_log(&format!("Generated new link ID: {}", new_link_id));
-                        
-                        // Update the UI
-                        set_link_id.set(new_link_id);
-                        set_loading_link.set(false);
-                        
-                        // Store the created connection for later use
-                        set_created_connection.set(Some(connection));
-                    },
-                    Err(e) => {
-                        // Handle error, but don't block the form submission
-                        let error_msg = format!("Failed to generate link: {:?}", e);
-                        console_log(&error_msg);
-                        set_link_error.set(error_msg);
-                        set_loading_link.set(false);
+        // Set loading state
+        set_loading_link.set(true);
+        set_link_error.set(String::new());
+        
+        // Spawn async task to request link ID
+        spawn_local(async move {
+            // Wait for the identity so the link isn't created under an id it's about to replace
+            let player_id = identity::player_id().await;
+            match crate::connection_utils::create_connection(&player_id, &RetryPolicy::user_action()).await {
+                Ok(connection) => {
+                    // Extract the link ID
+                    let new_link_id = connection.link_id.clone();
+                    
+                    // Generate the end-to-end key that travels in the invite link, if it gets one
+                    if let Err(e) = e2e::set_up_new_link(&new_link_id) {
+                        console_log(&format!("Couldn't create an encryption key: {:?}", e));
                     }
+                    console_log(&format!("Generated new link ID: {}", new_link_id));
+                    
+                    // Update the UI
+                    set_link_id.set(new_link_id);
+                    set_loading_link.set(false);
+                    
+                    // Store the created connection for later use
+                    set_created_connection.set(Some(connection));
+                },
+                Err(e) => {
+                    // Handle error, but don't block the form submission
+                    let error_msg = format!("Failed to generate link: {:?}", e);
+                    console_log(&error_msg);
+                    set_link_error.set(error_msg);
+                    set_loading_link.set(false);
+                }
+            }
+        });
+    };
+    
+    // A different expiry or use limit needs a new link; the old one is
+    // withdrawn so it can't be used
+    let replace_link = move |_: InvitePolicy| {
+        if loading_link.get_untracked() {
+            return;
+        }
+        if let (Some(old), Some(player_id)) = (created_connection.get_untracked(), get_stored_player_id()) {
+            e2e::remove_connection_secret(&old.link_id);
+            spawn_local(async move {
+                let client_id = uuid::Uuid::new_v4().to_string();
+                if let Err(e) = crate::connection_utils::leave_connection(&old.id, &player_id, &client_id, &RetryPolicy::polling()).await {
+                    console::log_1(&wasm_bindgen::JsValue::from_str(&format!("Couldn't withdraw old link: {:?}", e)));
                 }
             });
-        } else {
-            // No player ID available
-            set_link_error.set("No player ID found".to_string());
         }
+        set_created_connection.set(None);
+        request_new_link_id();
     };
     
     // Check URL for link parameter or request a new link ID
@@ -96,25 +145,104 @@ pub fn ConnectionModal(
     // Initialize on component creation
     initialize_link_id(); 
 
-    let (api_error_ref, _) = signal(String::new());
-    Effect::new(move |_| {
-        // Check if there's a parent component API error about connection being full
-        if let Some(window) = web_sys::window() {
-            if let Some(error_element) = window.document()
-                .and_then(|doc| doc.query_selector(".bg-red-900").ok())
-                .flatten() 
-            {
-                let error_text = error_element.text_content().unwrap_or_default();
-                if (error_text.contains("already full") || error_text.contains("already connected")) 
-                && !loading_link.get() && link_id.get().is_empty() {
-                    // Found an API error about connection being full, generate a new link
-                    console_log("Auto-generating new link due to API error: Connection full");
-                    request_new_link_id();
+    // On an invite landing page, show who sent the link and suggest their name
+    let (inviter, set_inviter) = signal(None::<Friend>);
+    if !is_view_mode {
+        if let Some(url_link_id) = get_link_id_from_url() {
+            spawn_local(async move {
+                if let Some(found) = profile::inviter(&url_link_id).await {
+                    let display_name = found.profile.as_ref().map(|p| p.display_name.clone()).unwrap_or_default();
+                    if connection_name.get_untracked().is_empty() && !display_name.is_empty() {
+                        on_name_change.run(display_name);
+                    }
+                    set_inviter.set(Some(found));
                 }
+            });
+        }
+    }
+    
+    // In view mode, show the friend as we last saw them
+    let viewed_friend = connection
+        .as_ref()
+        .filter(|_| is_view_mode)
+        .and_then(|c| profile::cached_friend(&c.id));
+
+    // Only a connection someone has joined has a seat worth giving up
+    let can_leave = connection
+        .as_ref()
+        .is_some_and(|c| is_view_mode && c.status == ConnectionStatus::Active);
+
+    // Who blocking would be aimed at on a one-to-one connection
+    let blockable = connection
+        .as_ref()
+        .filter(|c| is_view_mode && !c.is_group())
+        .and_then(|c| {
+            let me = get_stored_player_id().unwrap_or_default();
+            viewed_friend
+                .as_ref()
+                .map(|found| found.player_id.clone())
+                .or_else(|| c.players.iter().find(|player| **player != me).cloned())
+                .map(|player_id| (player_id, c.id.clone()))
+        });
+
+    // Renaming a viewed connection; the name input holds the draft
+    let (saved_name, set_saved_name) = signal(connection_name.get_untracked());
+    let (rename_busy, set_rename_busy) = signal(false);
+    let (rename_error, set_rename_error) = signal(String::new());
+    let toasts = toast::use_toasts();
+    let rename_id = StoredValue::new(connection.as_ref().map(|c| c.id.clone()).unwrap_or_default());
+    let handle_rename = move || {
+        if !is_view_mode || rename_busy.get_untracked() {
+            return;
+        }
+        let name = match rename::clean_name(&connection_name.get_untracked()) {
+            Ok(name) => name,
+            Err(e) => {
+                set_rename_error.set(e);
+                return;
             }
+        };
+        // The new name shows straight away; the server catches up
+        set_saved_name.set(name.clone());
+        on_name_change.run(name.clone());
+        if let Some(callback) = on_rename {
+            callback.run(name.clone());
+        }
+        set_rename_busy.set(true);
+        set_rename_error.set(String::new());
+        spawn_local(async move {
+            if let Err(e) = rename::rename(&rename_id.get_value(), &name).await {
+                toasts.warning(e);
+            }
+            set_rename_busy.set(false);
+        });
+    };
+
+    Effect::new(move |_| {
+        // Joining failed because the invite is full or already ours, so offer a fresh link
+        let error_text = join_error.get();
+        if (error_text.contains("already full") || error_text.contains("already connected"))
+        && !loading_link.get() && link_id.get().is_empty() {
+            console_log("Auto-generating new link due to API error: Connection full");
+            request_new_link_id();
         }
     });
     
+    // The shareable invite URL, once we have a link id
+    let invite_url = move || {
+        if loading_link.get() || !link_error.get().is_empty() || link_id.get().is_empty() {
+            return None;
+        }
+        
+        let window = window()?;
+        let location = window.location();
+        let origin = location.origin().unwrap_or_else(|_| "http://64.181.233.1".to_string());
+        let pathname = location.pathname().unwrap_or_else(|_| "/".to_string());
+        
+        // The key goes in the fragment, which browsers never send to the server
+        Some(format!("{}{}?link={}{}", origin, pathname, link_id.get(), e2e::invite_fragment(&link_id.get())))
+    };
+    
     // Function to generate the full connection link
     let get_connection_link = move || {
         if loading_link.get() {
@@ -125,16 +253,30 @@ pub fn ConnectionModal(
             return format!("Error: {}", link_error.get());
         }
         
-        if link_id.get().is_empty() {
-            return "Waiting for link...".to_string();
-        }
-        
-        let window = window().expect("should have window");
-        let location = window.location();
-        let origin = location.origin().unwrap_or_else(|_| "http://64.181.233.1".to_string());
-        let pathname = location.pathname().unwrap_or_else(|_| "/".to_string());
-        
-        format!("{}{}?link={}", origin, pathname, link_id.get())
+        invite_url().unwrap_or_else(|| "Waiting for link...".to_string())
+    };
+    
+    // Deadline of the invite on show while nobody has joined it yet
+    let now = expiry::use_clock();
+    let pending_invite = connection.clone().filter(|c| is_view_mode && c.status == ConnectionStatus::Pending);
+    let invite_countdown = move || {
+        let invite = pending_invite.clone().or_else(|| created_connection.get())?;
+        let now = now.get();
+        Some(match expiry::status_at(&invite, now) {
+            ConnectionStatus::Pending => (
+                format!("Link expires in {}", expiry::format_remaining(expiry::seconds_left(&invite, now))),
+                expiry::expiring_soon(&invite, now),
+            ),
+            _ => ("This link has expired.".to_string(), true),
+        })
+    };
+    
+    // A QR code only helps while the friend still has to join: for a link we
+    // just made, or when viewing a connection that's still pending
+    let still_inviting = if is_view_mode {
+        connection.as_ref().is_none_or(|c| c.status == ConnectionStatus::Pending)
+    } else {
+        get_link_id_from_url().is_none()
     };
     
     view! {
@@ -175,6 +317,17 @@ pub fn ConnectionModal(
                                 view! { <></> }.into_any()
                             }
                         }}
+                        {move || inviter.get().map(|found| view! {
+                            <div class="mb-3 p-3 rounded bg-gray-900 border border-gray-700" data-test-id="inviter-profile">
+                                <div class="text-xs text-gray-400 mb-2">"Invited by"</div>
+                                <ProfileCard player_id=found.player_id profile=found.profile.unwrap_or_default() />
+                            </div>
+                        })}
+                        {viewed_friend.clone().map(|found| view! {
+                            <div class="mb-3" data-test-id="friend-profile">
+                                <ProfileCard player_id=found.player_id profile=found.profile.unwrap_or_default() />
+                            </div>
+                        })}
                         <label class="block text-sm font-medium mb-1 text-gray-200">
                              "Connect to:"
                         </label>
@@ -182,12 +335,41 @@ pub fn ConnectionModal(
                             type="text"
                             class="w-full px-4 py-2 rounded bg-gray-900 border border-gray-700 text-gray-100 focus:border-indigo-500 focus:ring-1 focus:ring-indigo-500"
                             prop:value=connection_name
-                            prop:disabled=is_view_mode
+                            maxlength=rename::MAX_NAME_CHARS.to_string()
                             on:input=move |ev| {
                                 let value = event_target_value(&ev);
                                 on_name_change.run(value);
+                                set_rename_error.set(String::new());
+                            }
+                            on:keydown=move |ev: web_sys::KeyboardEvent| {
+                                if ev.key() == "Enter" {
+                                    handle_rename();
+                                }
                             }
                         />
+                        {move || (is_view_mode && connection_name.get().trim() != saved_name.get()).then(|| view! {
+                            <div class="flex gap-2 mt-2" data-test-id="rename-actions">
+                                <button
+                                    class="px-3 py-1 bg-indigo-600 hover:bg-indigo-700 rounded text-sm text-gray-100"
+                                    prop:disabled=rename_busy
+                                    on:click=move |_| handle_rename()
+                                >
+                                    "Save name"
+                                </button>
+                                <button
+                                    class="px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200"
+                                    on:click=move |_| {
+                                        on_name_change.run(saved_name.get_untracked());
+                                        set_rename_error.set(String::new());
+                                    }
+                                >
+                                    "Reset"
+                                </button>
+                            </div>
+                        })}
+                        {move || (!rename_error.get().is_empty()).then(|| view! {
+                            <div class="mt-2 text-red-400 text-sm" data-test-id="rename-error">{rename_error.get()}</div>
+                        })}
                         <div class="mt-1 text-sm text-gray-400">
                             {move || {
                                 if get_link_id_from_url().is_some() {
@@ -208,13 +390,20 @@ pub fn ConnectionModal(
                         <label class="block text-sm font-medium mb-1 text-gray-200">
                              "Connection Link"
                         </label>
+                        {(!is_view_mode && get_link_id_from_url().is_none()).then(|| view! {
+                            <div class="mb-2">
+                                <InvitePolicyPicker on_change=Callback::new(replace_link) />
+                            </div>
+                        })}
                         <div class="flex gap-2">
                             <input
                                 type="text"
                                 class="w-full px-4 py-2 rounded bg-gray-900 border border-gray-700 text-gray-100"
                                 readonly=true
+                                node_ref=link_input
                                 prop:value=get_connection_link
                             />
+                            <CopyButton text=Signal::derive(invite_url) fallback_input=link_input />
                         </div>
                         <div class="mt-1 text-sm text-gray-400">
                             {move || {
@@ -231,8 +420,81 @@ pub fn ConnectionModal(
                                 }
                             }}
                         </div>
+                        {move || invite_countdown().map(|(text, urgent)| view! {
+                            <div
+                                class=if urgent { "mt-1 text-sm text-yellow-300" } else { "mt-1 text-sm text-gray-400" }
+                                data-test-id="modal-expiry-countdown"
+                            >
+                                {text}
+                            </div>
+                        })}
+                        {move || invite_url()
+                            .filter(|_| still_inviting)
+                            .map(|url| view! {
+                                <ShareInvite url=url.clone() friend_name=connection_name />
+                                <InviteQr url=url />
+                                <JoinCodeOffer link_id=link_id />
+                            })}
+                        {connection
+                            .clone()
+                            .filter(|c| is_view_mode && c.status == ConnectionStatus::Pending)
+                            .map(|c| view! { <InvitePolicySummary connection=c /> })}
                     </div>
 
+                    {connection
+                        .clone()
+                        // A group has no single friend to compare safety numbers with
+                        .filter(|c| is_view_mode && c.status == ConnectionStatus::Active && !c.is_group())
+                        .map(|c| view! { <VerifyFriend connection=c /> })}
+
+                    {connection
+                        .clone()
+                        .filter(|c| is_view_mode && c.is_group() && c.status != ConnectionStatus::Expired)
+                        .map(|c| view! { <MemberList connection=c /> })}
+
+                    {connection
+                        .clone()
+                        .filter(|c| is_view_mode && c.status == ConnectionStatus::Requested)
+                        .map(|_| view! {
+                            <div class="p-3 rounded bg-gray-900 border border-gray-700 text-sm text-gray-300" data-test-id="awaiting-approval">
+                                "Your friend asked to approve who joins. You can chat once they accept your request."
+                            </div>
+                        })}
+
+                    {connection
+                        .clone()
+                        .filter(|c| is_view_mode && !matches!(c.status, ConnectionStatus::Expired | ConnectionStatus::Requested))
+                        .map(|c| view! { <ChatPanel connection=c /> })}
+
+                    {connection
+                        .clone()
+                        .filter(|_| is_view_mode)
+                        .map(|c| view! {
+                            <TagEditor
+                                connection_id=c.id.clone()
+                                on_change=Callback::new(move |new_tags| {
+                                    if let Some(callback) = on_tags_change {
+                                        callback.run(new_tags);
+                                    }
+                                })
+                            />
+                            <ConnectionNote connection_id=c.id />
+                        })}
+
+                    {blockable.map(|(player_id, connection_id)| view! {
+                        <BlockReport
+                            player_id=player_id
+                            name=connection_name.get_untracked()
+                            connection_id=connection_id
+                            ends_connection=true
+                            on_blocked=Callback::new(move |_| {
+                                if let Some(ref callback) = on_blocked {
+                                    callback.run(());
+                                }
+                            })
+                        />
+                    })}
+
                     <div class="flex justify-end gap-4 mt-4">
                         <button
                             class="px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded text-gray-200"
@@ -246,6 +508,15 @@ pub fn ConnectionModal(
                             if is_view_mode {
                                 view! {
                                     <>
+                                        {on_leave.filter(|_| can_leave).map(|callback| view! {
+                                            <button
+                                                class="px-4 py-2 border border-red-500 text-red-400 hover:bg-red-900 rounded"
+                                                data-test-id="leave-connection"
+                                                on:click=move |_| callback.run(())
+                                            >
+                                                "Leave"
+                                            </button>
+                                        })}
                                         <button
                                             class="px-4 py-2 bg-red-600 hover:bg-red-700 rounded text-gray-100"
                                             on:click=move |_| {
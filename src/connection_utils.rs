@@ -1,10 +1,17 @@
-use web_sys::{window, UrlSearchParams, Request, RequestInit, RequestMode, Response, console};
+use web_sys::{window, UrlSearchParams, Response, console};
 use wasm_bindgen::prelude::*;
 use serde_wasm_bindgen;
 use wasm_bindgen_futures::JsFuture;
 use serde::{Serialize, Deserialize};
 use js_sys::{Promise, JSON, Object};
+use crate::browser::console_log;
 use crate::connect_component::{Connection, ConnectionStatus};
+use crate::api_retry::{fetch_with_retry, fetch_with_idempotency_key, ApiError, RetryPolicy};
+use crate::profile::{self, PlayerProfile};
+use crate::invite_policy;
+use crate::e2e;
+use crate::join_code;
+use crate::notifications;
 use uuid::Uuid;
 
 // API constants
@@ -14,11 +21,26 @@ const API_BASE: &str = "http://64.181.233.1/friends";
 #[derive(Serialize, Deserialize)]
 struct CreateConnectionRequest {
     player_id: String,
+    // Seconds until the invite link expires
+    expires_in: i64,
+    // How many friends may join by the link; null for no limit
+    max_uses: Option<u32>,
+    // Joiners wait for us to accept them before the connection goes live
+    require_approval: bool,
+    // Seats including ours; more than 2 makes a group
+    max_players: u32,
+    // Our profile, so the friend sees who invited them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profile: Option<PlayerProfile>,
 }
 
 #[derive(Serialize, Deserialize)]
 struct JoinConnectionRequest {
     player_id: String,
+    // Client-generated id so the server can ignore a replayed join
+    client_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profile: Option<PlayerProfile>,
 }
 
 pub fn extract_link_id_from_search(search: &str) -> Option<String> {
@@ -40,41 +62,39 @@ pub fn get_link_id_from_url() -> Option<String> {
     extract_link_id_from_search(&search)
 }
 
-// Helper function for logging
-fn console_log(msg: &str) {
-    console::log_1(&JsValue::from_str(msg));
+// Put an invite's link id in the address bar, as if its invite link had been
+// opened, so the join flow picks it up without a reload
+pub fn show_link_in_url(link_id: &str) -> Result<(), JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("No window"))?;
+    let url = format!(
+        "{}?link={}",
+        window.location().pathname().unwrap_or_default(),
+        js_sys::encode_uri_component(link_id)
+    );
+    window.history()?.push_state_with_url(&JsValue::NULL, "", Some(&url))
 }
 
 // Create a new connection with the API service
-pub async fn create_connection(player_id: &str) -> Result<Connection, JsValue> {
+pub async fn create_connection(player_id: &str, policy: &RetryPolicy) -> Result<Connection, JsValue> {
     console_log(&format!("Creating connection for player: {}", player_id));
     
-    let mut opts = RequestInit::new();
-    opts.method("POST");
-    opts.mode(RequestMode::Cors);
-    
-    // Create the request body
+    // Create the request body, with the invite settings last picked
+    let invite = invite_policy::load_policy();
     let request_data = CreateConnectionRequest {
         player_id: player_id.to_string(),
+        expires_in: invite.expiry.seconds(),
+        max_uses: invite.max_uses,
+        require_approval: invite.require_approval,
+        max_players: invite.max_players(),
+        profile: profile::load_profile(),
     };
     
     let request_json = JSON::stringify(&serde_wasm_bindgen::to_value(&request_data)?)?;
-    opts.body(Some(&request_json));
+    let request_json = String::from(request_json);
     
-    // Set headers
-    let headers = web_sys::Headers::new()?;
-    headers.append("Content-Type", "application/json")?;
-    headers.append("Accept", "application/json")?;
-    opts.headers(&headers);
-    
-    // Create the request
+    // Fetch the request, retrying transient failures
     let url = format!("{}/connections", API_BASE);
-    let request = Request::new_with_str_and_init(&url, &opts)?;
-    
-    // Fetch the request
-    let window = window().ok_or_else(|| JsValue::from_str("No window found"))?;
-    let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
-    let resp: Response = resp_value.dyn_into()?;
+    let resp = fetch_with_retry("POST", &url, Some(&request_json), policy).await?;
     
     if !resp.ok() {
         let status = resp.status();
@@ -86,7 +106,12 @@ pub async fn create_connection(player_id: &str) -> Result<Connection, JsValue> {
     
     // Parse the response as JSON
     let json = JsFuture::from(resp.json()?).await?;
-    let connection_data: Connection = serde_wasm_bindgen::from_value(json)?;
+    let mut connection_data: Connection = serde_wasm_bindgen::from_value(json)?;
+    // Servers that don't report the limit still applied the one we asked for
+    connection_data.max_uses = connection_data.max_uses.or(invite.max_uses);
+    if !connection_data.is_group() {
+        connection_data.max_players = invite.max_players();
+    }
     
     console_log(&format!("Connection created with ID: {}", connection_data.id));
     
@@ -94,62 +119,36 @@ pub async fn create_connection(player_id: &str) -> Result<Connection, JsValue> {
 }
 
 // Join an existing connection using the API service
-pub async fn join_connection(link_id: &str, player_id: &str) -> Result<Connection, JsValue> {
+pub async fn join_connection(link_id: &str, player_id: &str, policy: &RetryPolicy) -> Result<Connection, JsValue> {
+    let client_id = Uuid::new_v4().to_string();
+    join_connection_with_id(link_id, player_id, &client_id, policy).await.map_err(JsValue::from)
+}
+
+// Join with a caller-chosen client id, so a queued join can be replayed safely
+pub async fn join_connection_with_id(link_id: &str, player_id: &str, client_id: &str, policy: &RetryPolicy) -> Result<Connection, ApiError> {
     console_log(&format!("Joining connection with link ID: {} for player: {}", link_id, player_id));
     
-    let mut opts = RequestInit::new();
-    opts.method("POST");
-    opts.mode(RequestMode::Cors);
-    
     // Create the request body
     let request_data = JoinConnectionRequest {
         player_id: player_id.to_string(),
+        client_id: client_id.to_string(),
+        profile: profile::load_profile(),
     };
     
-    let request_json = JSON::stringify(&serde_wasm_bindgen::to_value(&request_data)?)?;
-    opts.body(Some(&request_json));
-    
-    // Set headers
-    let headers = web_sys::Headers::new()?;
-    headers.append("Content-Type", "application/json")?;
-    opts.headers(&headers);
+    let request_json = JSON::stringify(&serde_wasm_bindgen::to_value(&request_data).map_err(JsValue::from)?)?;
+    let request_json = String::from(request_json);
     
-    // Create the request
+    // Fetch the request, retrying transient failures
     let url = format!("{}/connections/link/{}/join", API_BASE, link_id);
-    let request = Request::new_with_str_and_init(&url, &opts)?;
-    
-    // Fetch the request
-    let window = window().ok_or_else(|| JsValue::from_str("No window found"))?;
-    let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
-    let resp: Response = resp_value.dyn_into()?;
+    let resp = fetch_with_idempotency_key("POST", &url, Some(&request_json), policy, Some(client_id)).await?;
     
-    // Enhanced error handling in join_connection function in connection_utils.rs
     if !resp.ok() {
-        let status = resp.status();
-        let status_text = resp.status_text();
-        
-        // Try to parse error response as JSON
-        let error_text = JsFuture::from(resp.text()?).await?;
-        let error_str = error_text.as_string().unwrap_or_default();
-        
-        // Try to extract the error message from JSON
-        if let Ok(error_json) = serde_json::from_str::<serde_json::Value>(&error_str) {
-            if let Some(error_msg) = error_json.get("error").and_then(|v| v.as_str()) {
-                return Err(JsValue::from_str(&format!(
-                    "{}", error_msg  // Just return the error message without additional text
-                )));
-            }
-        }
-        
-        // Fallback to generic error if JSON parsing fails
-        return Err(JsValue::from_str(&format!(
-            "API error: {} {}", status, status_text
-        )));
+        return Err(api_error_from_response(resp).await);
     }
     
     // Parse the response as JSON
     let json = JsFuture::from(resp.json()?).await?;
-    let connection_data: Connection = serde_wasm_bindgen::from_value(json)?;
+    let connection_data: Connection = serde_wasm_bindgen::from_value(json).map_err(JsValue::from)?;
     
     console_log(&format!("Joined connection with ID: {}", connection_data.id));
     
@@ -183,27 +182,70 @@ fn save_connection_name(connection_id: &str, name: &str) {
     }
 }
 
+// Put a saved record back where it was, replacing any newer copy
+pub fn restore_saved_connection(index: usize, record: serde_json::Value) -> Option<()> {
+    let id = saved_connection_id(&record)?.to_string();
+    let mut connections = load_saved_connections();
+    connections.retain(|c| saved_connection_id(c) != Some(id.as_str()));
+    connections.insert(index.min(connections.len()), record);
+    store_saved_connections(&connections)
+}
+
+// Remove everything stored locally about a connection. Tags, pins and notes
+// live in its saved record and go with it.
+pub fn forget_connection(connection_id: &str) {
+    let Some(storage) = window().and_then(|w| w.local_storage().ok().flatten()) else {
+        return;
+    };
+    let _ = storage.remove_item(&format!("conn-name-{}", connection_id));
+    notifications::set_muted(connection_id, false);
+
+    let mut connections = load_saved_connections();
+    let link_id = connections
+        .iter()
+        .find(|c| saved_connection_id(c) == Some(connection_id))
+        .and_then(|c| c.get("link_id").and_then(|v| v.as_str()))
+        .map(|link_id| link_id.to_string());
+    // Entries without an id are kept
+    connections.retain(|c| saved_connection_id(c) != Some(connection_id));
+    let _ = store_saved_connections(&connections);
+
+    // The key and join code belong to the link, which another saved connection may share
+    if let Some(link_id) = link_id.filter(|link_id| !link_id.is_empty()) {
+        let still_used = connections
+            .iter()
+            .any(|c| c.get("link_id").and_then(|v| v.as_str()) == Some(link_id.as_str()));
+        if !still_used {
+            e2e::remove_connection_secret(&link_id);
+            join_code::forget_code_for_link(&link_id);
+        }
+    }
+
+    if let Ok(Some(names_json)) = storage.get_item("connection-names") {
+        if let Ok(mut names_map) = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&names_json) {
+            names_map.remove(connection_id);
+            if let Ok(updated_json) = serde_json::to_string(&names_map) {
+                let _ = storage.set_item("connection-names", &updated_json);
+            }
+        }
+    }
+}
+
+// Change the name we use for a connection everywhere it's stored
+pub fn rename_saved_connection(connection_id: &str, name: &str) {
+    save_connection_name(connection_id, name);
+    update_saved_connection(connection_id, |record| {
+        record.insert("friendly_name".to_string(), serde_json::Value::String(name.to_string()));
+    });
+}
+
 // Get a connection by its link ID
-pub async fn get_connection_by_link_id(link_id: &str) -> Result<Connection, JsValue> {
+pub async fn get_connection_by_link_id(link_id: &str, policy: &RetryPolicy) -> Result<Connection, JsValue> {
     console_log(&format!("Getting connection with link ID: {}", link_id));
     
-    let mut opts = RequestInit::new();
-    opts.method("GET");
-    opts.mode(RequestMode::Cors);
-    
-    // Set headers
-    let headers = web_sys::Headers::new()?;
-    headers.append("Accept", "application/json")?;
-    opts.headers(&headers);
-    
-    // Create the request
+    // Fetch the request, retrying transient failures
     let url = format!("{}/connections/link/{}", API_BASE, link_id);
-    let request = Request::new_with_str_and_init(&url, &opts)?;
-    
-    // Fetch the request
-    let window = window().ok_or_else(|| JsValue::from_str("No window found"))?;
-    let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
-    let resp: Response = resp_value.dyn_into()?;
+    let resp = fetch_with_retry("GET", &url, None, policy).await?;
     
     if !resp.ok() {
         let status = resp.status();
@@ -242,6 +284,9 @@ pub fn save_connection_to_local_storage(connection: &Connection, friendly_name:
         friendly_name: String,
         created_at: i64,
         expires_at: i64, // Add expires_at field
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_uses: Option<u32>,
+        max_players: u32,
     }
     
     let saved = SavedConnection {
@@ -250,19 +295,22 @@ pub fn save_connection_to_local_storage(connection: &Connection, friendly_name:
         friendly_name: friendly_name.to_string(),
         created_at: connection.created_at,
         expires_at: connection.expires_at, // Save the expiration time
+        max_uses: connection.max_uses,
+        max_players: connection.max_players,
     };
     
     // Save in saved connections collection
     let saved_key = "saved-connections";
     let existing = storage.get_item(saved_key).ok()?;
     
-    let mut connections: Vec<SavedConnection> = existing
+    // Keep existing records as raw JSON so fields added by other features survive
+    let mut connections: Vec<serde_json::Value> = existing
         .and_then(|json| serde_json::from_str(&json).ok())
-        .unwrap_or_else(|| Vec::new());
+        .unwrap_or_default();
     
     // Add if not already present
-    if !connections.iter().any(|c| c.id == connection.id) {
-        connections.push(saved);
+    if !connections.iter().any(|c| saved_connection_id(c) == Some(connection.id.as_str())) {
+        connections.push(serde_json::to_value(&saved).ok()?);
         
         // Save back to localStorage
         let json = serde_json::to_string(&connections).ok()?;
@@ -296,25 +344,45 @@ pub fn load_saved_connections() -> Vec<serde_json::Value> {
     }
 }
 
+fn saved_connection_id(record: &serde_json::Value) -> Option<&str> {
+    record.get("id").and_then(|v| v.as_str())
+}
+
+// Write the saved connections list back to local storage
+fn store_saved_connections(connections: &[serde_json::Value]) -> Option<()> {
+    let storage = window()?.local_storage().ok()??;
+    let json = serde_json::to_string(connections).ok()?;
+    storage.set_item("saved-connections", &json).ok()
+}
+
+// Get the saved record of one connection
+pub fn get_saved_connection(connection_id: &str) -> Option<serde_json::Map<String, serde_json::Value>> {
+    load_saved_connections()
+        .into_iter()
+        .find(|c| saved_connection_id(c) == Some(connection_id))
+        .and_then(|c| c.as_object().cloned())
+}
+
+// Read-modify-write the saved record of one connection, keeping any other fields as they are
+pub fn update_saved_connection(
+    connection_id: &str,
+    update: impl FnOnce(&mut serde_json::Map<String, serde_json::Value>),
+) -> Option<()> {
+    let mut connections = load_saved_connections();
+    let record = connections
+        .iter_mut()
+        .find(|c| saved_connection_id(c) == Some(connection_id))?
+        .as_object_mut()?;
+    
+    update(record);
+    store_saved_connections(&connections)
+}
+
 // Poll for notifications
-pub async fn poll_notifications(player_id: &str) -> Result<Vec<String>, JsValue> {
-    let mut opts = RequestInit::new();
-    opts.method("GET");
-    opts.mode(RequestMode::Cors);
-    
-    // Set headers
-    let headers = web_sys::Headers::new()?;
-    headers.append("Accept", "application/json")?;
-    opts.headers(&headers);
-    
-    // Create the request
+pub async fn poll_notifications(player_id: &str, policy: &RetryPolicy) -> Result<Vec<String>, JsValue> {
+    // Fetch the request, retrying transient failures
     let url = format!("{}/players/{}/notifications", API_BASE, player_id);
-    let request = Request::new_with_str_and_init(&url, &opts)?;
-    
-    // Fetch the request
-    let window = window().ok_or_else(|| JsValue::from_str("No window found"))?;
-    let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
-    let resp: Response = resp_value.dyn_into()?;
+    let resp = fetch_with_retry("GET", &url, None, policy).await?;
     
     if !resp.ok() {
         let status = resp.status();
@@ -330,31 +398,17 @@ pub async fn poll_notifications(player_id: &str) -> Result<Vec<String>, JsValue>
     
     if !notifications.is_empty() {
         // Acknowledge notifications
-        acknowledge_notifications(player_id).await?;
+        acknowledge_notifications(player_id, policy).await?;
     }
-    
+
     Ok(notifications)
 }
 
 // Acknowledge notifications
-async fn acknowledge_notifications(player_id: &str) -> Result<(), JsValue> {
-    let mut opts = RequestInit::new();
-    opts.method("POST");
-    opts.mode(RequestMode::Cors);
-    
-    // Set headers
-    let headers = web_sys::Headers::new()?;
-    headers.append("Content-Type", "application/json")?;
-    opts.headers(&headers);
-    
-    // Create the request
+async fn acknowledge_notifications(player_id: &str, policy: &RetryPolicy) -> Result<(), JsValue> {
+    // Fetch the request, retrying transient failures
     let url = format!("{}/players/{}/notifications/ack", API_BASE, player_id);
-    let request = Request::new_with_str_and_init(&url, &opts)?;
-    
-    // Fetch the request
-    let window = window().ok_or_else(|| JsValue::from_str("No window found"))?;
-    let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
-    let resp: Response = resp_value.dyn_into()?;
+    let resp = fetch_with_retry("POST", &url, None, policy).await?;
     
     if !resp.ok() {
         let status = resp.status();
@@ -368,47 +422,419 @@ async fn acknowledge_notifications(player_id: &str) -> Result<(), JsValue> {
 }
 
 // Send a message to a connection
-pub async fn send_message(connection_id: &str, player_id: &str, content: &str) -> Result<(), JsValue> {
-    let mut opts = RequestInit::new();
-    opts.method("POST");
-    opts.mode(RequestMode::Cors);
-    
+pub async fn send_message(connection_id: &str, player_id: &str, content: &str, policy: &RetryPolicy) -> Result<(), JsValue> {
+    let client_id = Uuid::new_v4().to_string();
+    send_message_with_id(connection_id, player_id, content, &client_id, policy).await.map_err(JsValue::from)
+}
+
+// Send a message with a caller-chosen client id, so a queued message can be replayed safely
+pub async fn send_message_with_id(connection_id: &str, player_id: &str, content: &str, client_id: &str, policy: &RetryPolicy) -> Result<(), ApiError> {
     // Create the request body
     #[derive(Serialize)]
     struct MessageRequest {
         player_id: String,
         content: String,
+        client_id: String,
     }
     
     let request_data = MessageRequest {
         player_id: player_id.to_string(),
         content: content.to_string(),
+        client_id: client_id.to_string(),
     };
     
-    let request_json = JSON::stringify(&serde_wasm_bindgen::to_value(&request_data)?)?;
-    opts.body(Some(&request_json));
+    let request_json = JSON::stringify(&serde_wasm_bindgen::to_value(&request_data).map_err(JsValue::from)?)?;
+    let request_json = String::from(request_json);
+    
+    // Fetch the request, retrying transient failures
+    let url = format!("{}/connections/{}/messages", API_BASE, connection_id);
+    let resp = fetch_with_idempotency_key("POST", &url, Some(&request_json), policy, Some(client_id)).await?;
+    
+    if !resp.ok() {
+        return Err(api_error_from_response(resp).await);
+    }
+    
+    Ok(())
+}
+
+// Leave a connection, freeing our seat on the server
+pub async fn leave_connection(connection_id: &str, player_id: &str, client_id: &str, policy: &RetryPolicy) -> Result<(), ApiError> {
+    #[derive(Serialize)]
+    struct LeaveRequest {
+        player_id: String,
+        client_id: String,
+    }
+    
+    let request_data = LeaveRequest {
+        player_id: player_id.to_string(),
+        client_id: client_id.to_string(),
+    };
+    
+    let request_json = serde_json::to_string(&request_data)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    
+    let url = format!("{}/connections/{}/leave", API_BASE, connection_id);
+    let resp = fetch_with_idempotency_key("POST", &url, Some(&request_json), policy, Some(client_id)).await?;
+    
+    if !resp.ok() {
+        return Err(api_error_from_response(resp).await);
+    }
+    
+    Ok(())
+}
+
+// Tell the server the name we use for a connection
+pub async fn rename_connection(connection_id: &str, player_id: &str, name: &str, client_id: &str, policy: &RetryPolicy) -> Result<(), ApiError> {
+    #[derive(Serialize)]
+    struct RenameRequest {
+        player_id: String,
+        name: String,
+        client_id: String,
+    }
+    
+    let request_data = RenameRequest {
+        player_id: player_id.to_string(),
+        name: name.to_string(),
+        client_id: client_id.to_string(),
+    };
+    
+    let request_json = serde_json::to_string(&request_data)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
     
-    // Set headers
-    let headers = web_sys::Headers::new()?;
-    headers.append("Content-Type", "application/json")?;
-    opts.headers(&headers);
+    let url = format!("{}/connections/{}/rename", API_BASE, connection_id);
+    let resp = fetch_with_idempotency_key("POST", &url, Some(&request_json), policy, Some(client_id)).await?;
+    
+    if !resp.ok() {
+        return Err(api_error_from_response(resp).await);
+    }
+    
+    Ok(())
+}
+
+// Turn a failed response into an error, preferring the server's {"error": "..."} message
+async fn error_from_response(resp: Response) -> JsValue {
+    api_error_from_response(resp).await.into()
+}
+
+// The same, keeping the status so callers can tell a rejection from a hiccup
+async fn api_error_from_response(resp: Response) -> ApiError {
+    let status = resp.status();
+    let status_text = resp.status_text();
+    
+    if let Ok(text_promise) = resp.text() {
+        if let Ok(error_text) = JsFuture::from(text_promise).await {
+            let error_str = error_text.as_string().unwrap_or_default();
+            if let Ok(error_json) = serde_json::from_str::<serde_json::Value>(&error_str) {
+                if let Some(error_msg) = error_json.get("error").and_then(|v| v.as_str()) {
+                    return ApiError::Status { status, message: error_msg.to_string() };
+                }
+            }
+        }
+    }
     
-    // Create the request
+    ApiError::Status { status, message: format!("API error: {} {}", status, status_text) }
+}
+
+// A chat message as returned by the server; `content` may be end-to-end encrypted
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Message {
+    pub player_id: String,
+    pub content: String,
+    #[serde(default)]
+    pub timestamp: i64,
+}
+
+// Get the messages of a connection, oldest first
+pub async fn get_messages(connection_id: &str, policy: &RetryPolicy) -> Result<Vec<Message>, JsValue> {
     let url = format!("{}/connections/{}/messages", API_BASE, connection_id);
-    let request = Request::new_with_str_and_init(&url, &opts)?;
+    let resp = fetch_with_retry("GET", &url, None, policy).await?;
     
-    // Fetch the request
-    let window = window().ok_or_else(|| JsValue::from_str("No window found"))?;
-    let resp_value = JsFuture::from(window.fetch_with_request(&request)).await?;
-    let resp: Response = resp_value.dyn_into()?;
+    if !resp.ok() {
+        return Err(error_from_response(resp).await);
+    }
+    
+    let json = JsFuture::from(resp.json()?).await?;
+    let messages: Vec<Message> = serde_wasm_bindgen::from_value(json)?;
+    
+    Ok(messages)
+}
+
+// Get the public key a player signs their requests with (base64url, raw P-256)
+pub async fn get_player_public_key(player_id: &str, policy: &RetryPolicy) -> Result<String, JsValue> {
+    let url = format!("{}/players/{}/public-key", API_BASE, player_id);
+    let resp = fetch_with_retry("GET", &url, None, policy).await?;
     
     if !resp.ok() {
-        let status = resp.status();
-        let status_text = resp.status_text();
-        return Err(JsValue::from_str(&format!(
-            "API error: {} {}", status, status_text
-        )));
+        if resp.status() == 404 {
+            return Err(JsValue::from_str("Your friend hasn't published a key yet"));
+        }
+        return Err(error_from_response(resp).await);
+    }
+    
+    let text = JsFuture::from(resp.text()?).await?.as_string().unwrap_or_default();
+    serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|json| json.get("public_key").and_then(|v| v.as_str()).map(|key| key.to_string()))
+        .ok_or_else(|| JsValue::from_str("Malformed public key response"))
+}
+
+// Get the profile a player shares with friends; `None` if they haven't set one
+pub async fn get_player_profile(player_id: &str, policy: &RetryPolicy) -> Result<Option<PlayerProfile>, JsValue> {
+    let url = format!("{}/players/{}/profile", API_BASE, player_id);
+    let resp = fetch_with_retry("GET", &url, None, policy).await?;
+    
+    if !resp.ok() {
+        if resp.status() == 404 {
+            return Ok(None);
+        }
+        return Err(error_from_response(resp).await);
+    }
+    
+    let text = JsFuture::from(resp.text()?).await?.as_string().unwrap_or_default();
+    let profile: PlayerProfile = serde_json::from_str(&text)
+        .map_err(|_| JsValue::from_str("Malformed profile response"))?;
+    
+    Ok(Some(profile))
+}
+
+// Publish an edited profile so friends see the change
+pub async fn update_player_profile(player_id: &str, profile: &PlayerProfile, policy: &RetryPolicy) -> Result<(), JsValue> {
+    console_log(&format!("Updating profile for player: {}", player_id));
+    
+    let request_json = serde_json::to_string(profile)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    
+    let url = format!("{}/players/{}/profile", API_BASE, player_id);
+    let resp = fetch_with_retry("POST", &url, Some(&request_json), policy).await?;
+    
+    if !resp.ok() {
+        return Err(error_from_response(resp).await);
+    }
+    
+    Ok(())
+}
+
+// Bind a player id that predates keypairs to our public key, so signed
+// requests for it can only come from this key from now on. The request itself
+// is signed with the key being registered.
+pub async fn register_player_key(player_id: &str, public_key: &str, policy: &RetryPolicy) -> Result<(), JsValue> {
+    console_log(&format!("Registering public key for player: {}", player_id));
+    
+    let request_json = serde_json::json!({ "public_key": public_key }).to_string();
+    
+    let url = format!("{}/players/{}/public-key", API_BASE, player_id);
+    let resp = fetch_with_retry("POST", &url, Some(&request_json), policy).await?;
+    
+    if !resp.ok() {
+        return Err(error_from_response(resp).await);
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize)]
+struct JoinCodeRequest {
+    link_id: String,
+    player_id: String,
+}
+
+// Register a join code for one of our links. `false` if the code is already taken.
+pub async fn register_join_code(code: &str, link_id: &str, player_id: &str, policy: &RetryPolicy) -> Result<bool, JsValue> {
+    let request_json = serde_json::to_string(&JoinCodeRequest {
+        link_id: link_id.to_string(),
+        player_id: player_id.to_string(),
+    })
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let url = format!("{}/join-codes/{}", API_BASE, code);
+    let resp = fetch_with_retry("PUT", &url, Some(&request_json), policy).await?;
+
+    if !resp.ok() {
+        if resp.status() == 409 {
+            return Ok(false);
+        }
+        return Err(error_from_response(resp).await);
+    }
+
+    Ok(true)
+}
+
+// Look up the link id a join code stands for
+pub async fn resolve_join_code(code: &str, policy: &RetryPolicy) -> Result<String, JsValue> {
+    let url = format!("{}/join-codes/{}", API_BASE, code);
+    let resp = fetch_with_retry("GET", &url, None, policy).await?;
+
+    if !resp.ok() {
+        if resp.status() == 404 {
+            return Err(JsValue::from_str("No invite with that code. It may have been mistyped or already used."));
+        }
+        return Err(error_from_response(resp).await);
+    }
+
+    let text = JsFuture::from(resp.text()?).await?.as_string().unwrap_or_default();
+    serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|json| json.get("link_id").and_then(|v| v.as_str()).map(|link_id| link_id.to_string()))
+        .ok_or_else(|| JsValue::from_str("Malformed join code response"))
+}
+
+// Someone waiting for us to let them into a connection
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JoinRequest {
+    pub player_id: String,
+    #[serde(default)]
+    pub profile: Option<PlayerProfile>,
+}
+
+// Where our own request to join a connection stands
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestState {
+    Pending,
+    Accepted,
+    Declined,
+}
+
+// The requests waiting on one of our connections
+pub async fn get_join_requests(connection_id: &str, policy: &RetryPolicy) -> Result<Vec<JoinRequest>, JsValue> {
+    let url = format!("{}/connections/{}/requests", API_BASE, connection_id);
+    let resp = fetch_with_retry("GET", &url, None, policy).await?;
+
+    if !resp.ok() {
+        if resp.status() == 404 {
+            return Ok(Vec::new());
+        }
+        return Err(error_from_response(resp).await);
+    }
+
+    let text = JsFuture::from(resp.text()?).await?.as_string().unwrap_or_default();
+    serde_json::from_str(&text).map_err(|_| JsValue::from_str("Malformed join requests response"))
+}
+
+// Accept or decline a request to join one of our connections
+pub async fn answer_join_request(
+    connection_id: &str,
+    player_id: &str,
+    requester_id: &str,
+    accept: bool,
+    policy: &RetryPolicy,
+) -> Result<(), JsValue> {
+    #[derive(Serialize)]
+    struct AnswerRequest {
+        player_id: String,
+    }
+
+    let request_json = serde_json::to_string(&AnswerRequest { player_id: player_id.to_string() })
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let url = format!(
+        "{}/connections/{}/requests/{}/{}",
+        API_BASE,
+        connection_id,
+        requester_id,
+        if accept { "accept" } else { "decline" }
+    );
+    let resp = fetch_with_retry("POST", &url, Some(&request_json), policy).await?;
+
+    if !resp.ok() {
+        return Err(error_from_response(resp).await);
+    }
+
+    Ok(())
+}
+
+// Check whether our request to join a connection has been answered
+pub async fn get_join_request_state(connection_id: &str, player_id: &str, policy: &RetryPolicy) -> Result<RequestState, JsValue> {
+    let url = format!("{}/connections/{}/requests/{}", API_BASE, connection_id, player_id);
+    let resp = fetch_with_retry("GET", &url, None, policy).await?;
+
+    if !resp.ok() {
+        return Err(error_from_response(resp).await);
+    }
+
+    let text = JsFuture::from(resp.text()?).await?.as_string().unwrap_or_default();
+    serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|json| json.get("state").cloned())
+        .and_then(|state| serde_json::from_value(state).ok())
+        .ok_or_else(|| JsValue::from_str("Malformed join request response"))
+}
+
+#[derive(Serialize)]
+struct BlockRequest {
+    blocked_player_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connection_id: Option<String>,
+}
+
+// Add a player to our server-side blocklist so they can't reach us again
+pub async fn block_player(player_id: &str, blocked_id: &str, connection_id: Option<&str>, policy: &RetryPolicy) -> Result<(), JsValue> {
+    console_log(&format!("Blocking player {}", blocked_id));
+
+    let request_json = serde_json::to_string(&BlockRequest {
+        blocked_player_id: blocked_id.to_string(),
+        connection_id: connection_id.map(|id| id.to_string()),
+    })
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let url = format!("{}/players/{}/blocks", API_BASE, player_id);
+    let resp = fetch_with_retry("POST", &url, Some(&request_json), policy).await?;
+
+    if !resp.ok() {
+        return Err(error_from_response(resp).await);
+    }
+
+    Ok(())
+}
+
+pub async fn unblock_player(player_id: &str, blocked_id: &str, policy: &RetryPolicy) -> Result<(), JsValue> {
+    let url = format!("{}/players/{}/blocks/{}", API_BASE, player_id, blocked_id);
+    let resp = fetch_with_retry("DELETE", &url, None, policy).await?;
+
+    // Already gone is as good as removed
+    if !resp.ok() && resp.status() != 404 {
+        return Err(error_from_response(resp).await);
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ReportRequest {
+    reporter_id: String,
+    reported_player_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connection_id: Option<String>,
+    reason: String,
+    details: String,
+}
+
+// Tell the service about a player who's misbehaving
+pub async fn report_player(
+    player_id: &str,
+    reported_id: &str,
+    connection_id: Option<&str>,
+    reason: &str,
+    details: &str,
+    policy: &RetryPolicy,
+) -> Result<(), JsValue> {
+    console_log(&format!("Reporting player {} for {}", reported_id, reason));
+
+    let request_json = serde_json::to_string(&ReportRequest {
+        reporter_id: player_id.to_string(),
+        reported_player_id: reported_id.to_string(),
+        connection_id: connection_id.map(|id| id.to_string()),
+        reason: reason.to_string(),
+        details: details.to_string(),
+    })
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let url = format!("{}/reports", API_BASE);
+    let resp = fetch_with_retry("POST", &url, Some(&request_json), policy).await?;
+
+    if !resp.ok() {
+        return Err(error_from_response(resp).await);
+    }
+
+    Ok(())
+}
@@ -6,6 +6,7 @@ mod connection_utils_tests {
     use wasm_bindgen_test::*;
     use web_sys::{window, UrlSearchParams};
     use crate::{connection_modal::ConnectionModal, connection_utils::{extract_link_id_from_search, get_link_id_from_url}};
+    use crate::api_retry::RetryPolicy;
 
     wasm_bindgen_test_configure!(run_in_browser);
 
@@ -22,7 +23,7 @@ mod connection_utils_tests {
             });
         
         // Call the API to create a connection
-        match crate::connection_utils::create_connection(&player_id).await {
+        match crate::connection_utils::create_connection(&player_id, &RetryPolicy::user_action()).await {
             Ok(connection) => {
                 // Verify we got a link_id back
                 assert!(!connection.link_id.is_empty(), "Connection should have a link_id");
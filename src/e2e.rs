@@ -0,0 +1,311 @@
+use wasm_bindgen::prelude::*;
+use web_sys::{window, CryptoKey};
+
+use crate::browser::{console_log, storage};
+use crate::invite_policy;
+use crate::webcrypto::{self, params};
+
+// Every connection gets a random 256-bit secret. The creator puts it in the
+// invite link's fragment (`#key=...`), which browsers never send to the server,
+// so only the two friends can read each other's messages.
+
+// localStorage prefix for per-connection secrets, keyed by link id (both sides know it)
+const KEY_PREFIX: &str = "conn-key-";
+// Prefix marking an encrypted message body: e2e1:<iv>:<ciphertext>
+const CIPHERTEXT_PREFIX: &str = "e2e1:";
+
+// A message body after trying to decrypt it
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenedMessage {
+    pub text: String,
+    pub encrypted: bool,
+    // Plaintext that arrived on a connection with a key; it's withheld, since
+    // anyone between us and the server could have sent it
+    pub rejected: bool,
+}
+
+// The stored secret for a connection, base64url encoded
+pub fn connection_secret(link_id: &str) -> Option<String> {
+    storage()?.get_item(&format!("{}{}", KEY_PREFIX, link_id)).ok()?
+}
+
+pub fn has_connection_key(link_id: &str) -> bool {
+    connection_secret(link_id).is_some()
+}
+
+// Whether a `#key=` value is a 256-bit key we can encrypt with
+pub fn is_valid_secret(secret: &str) -> bool {
+    webcrypto::base64url_decode(secret).is_ok_and(|key| key.len() == 32)
+}
+
+pub fn store_connection_secret(link_id: &str, secret: &str) -> Result<(), JsValue> {
+    // Reject anything that isn't a 256-bit key before it ends up in storage
+    if !is_valid_secret(secret) {
+        return Err(JsValue::from_str("Invalid connection key"));
+    }
+
+    let storage = storage().ok_or_else(|| JsValue::from_str("localStorage not available"))?;
+    storage.set_item(&format!("{}{}", KEY_PREFIX, link_id), secret)
+}
+
+pub fn remove_connection_secret(link_id: &str) {
+    if let Some(storage) = storage() {
+        let _ = storage.remove_item(&format!("{}{}", KEY_PREFIX, link_id));
+    }
+}
+
+// Generate the secret for a connection we just created (keeps an existing one)
+pub fn create_connection_secret(link_id: &str) -> Result<String, JsValue> {
+    if let Some(existing) = connection_secret(link_id) {
+        return Ok(existing);
+    }
+
+    let secret = webcrypto::base64url_encode(&webcrypto::random_bytes(32)?);
+    store_connection_secret(link_id, &secret)?;
+    console_log(&format!("Generated end-to-end key for link {}", link_id));
+    Ok(secret)
+}
+
+// Whether a new link gets a key. Friends joining by code can't receive one,
+// and without SubtleCrypto (plain HTTP) random bytes still work but nothing
+// could ever be encrypted with them, so `seal_message` would refuse to send.
+fn new_link_gets_key(join_by_code: bool, crypto_available: bool) -> bool {
+    !join_by_code && crypto_available
+}
+
+// Give a link we just created its key, when it can have one; otherwise its chat
+// is shown as "Not encrypted". This is settled before the link is shown and
+// never undone, since a friend may already hold the key.
+pub fn set_up_new_link(link_id: &str) -> Result<(), JsValue> {
+    if !new_link_gets_key(invite_policy::load_policy().join_by_code, webcrypto::is_available()) {
+        console_log(&format!("Link {} gets no end-to-end key", link_id));
+        return Ok(());
+    }
+    create_connection_secret(link_id).map(|_| ())
+}
+
+// Pull `key=...` out of a URL fragment like "#key=abc"
+pub fn extract_secret_from_fragment(hash: &str) -> Option<String> {
+    hash.trim_start_matches('#')
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("key="))
+        .filter(|secret| !secret.is_empty())
+        .map(|secret| secret.to_string())
+}
+
+// Remember the secret carried by the current page's invite link, then drop it
+// from the address bar so it doesn't linger in history or get copied around
+pub fn store_secret_from_url(link_id: &str) -> bool {
+    let Some(window) = window() else {
+        return false;
+    };
+    let location = window.location();
+    let Some(secret) = location.hash().ok().and_then(|hash| extract_secret_from_fragment(&hash)) else {
+        return false;
+    };
+
+    if let Err(e) = store_connection_secret(link_id, &secret) {
+        console_log(&format!("Ignoring invite key: {:?}", e));
+        return false;
+    }
+
+    let url_without_fragment = format!(
+        "{}{}",
+        location.pathname().unwrap_or_default(),
+        location.search().unwrap_or_default()
+    );
+    if let Ok(history) = window.history() {
+        let _ = history.replace_state_with_url(&JsValue::NULL, "", Some(&url_without_fragment));
+    }
+
+    true
+}
+
+// Fragment to append to an invite link for this connection
+pub fn invite_fragment(link_id: &str) -> String {
+    connection_secret(link_id)
+        .map(|secret| format!("#key={}", secret))
+        .unwrap_or_default()
+}
+
+async fn message_key(secret: &str) -> Result<CryptoKey, JsValue> {
+    let raw = webcrypto::base64url_decode(secret)?;
+    webcrypto::await_key(webcrypto::subtle()?.import_key_with_object(
+        "raw",
+        &js_sys::Uint8Array::from(raw.as_slice()).unchecked_into(),
+        &params(&[("name", JsValue::from_str("AES-GCM"))])?,
+        false,
+        &webcrypto::usages(&["encrypt", "decrypt"]),
+    )).await
+}
+
+// The sender's player id is bound in as associated data, so a message can't be
+// replayed under someone else's name
+fn gcm_params(iv: &[u8], sender_id: &str) -> Result<js_sys::Object, JsValue> {
+    params(&[
+        ("name", JsValue::from_str("AES-GCM")),
+        ("iv", webcrypto::bytes_value(iv)),
+        ("additionalData", webcrypto::bytes_value(sender_id.as_bytes())),
+    ])
+}
+
+pub fn is_encrypted(content: &str) -> bool {
+    content.starts_with(CIPHERTEXT_PREFIX)
+}
+
+// Encrypt a message with a connection secret
+pub async fn encrypt_with_secret(secret: &str, sender_id: &str, text: &str) -> Result<String, JsValue> {
+    let key = message_key(secret).await?;
+    let iv = webcrypto::random_bytes(12)?;
+    let ciphertext = webcrypto::await_bytes(
+        webcrypto::subtle()?.encrypt_with_object_and_u8_array(&gcm_params(&iv, sender_id)?, &key, text.as_bytes())
+    ).await?;
+
+    Ok(format!(
+        "{}{}:{}",
+        CIPHERTEXT_PREFIX,
+        webcrypto::base64url_encode(&iv),
+        webcrypto::base64url_encode(&ciphertext)
+    ))
+}
+
+// Decrypt a message produced by `encrypt_with_secret`
+pub async fn decrypt_with_secret(secret: &str, sender_id: &str, content: &str) -> Result<String, JsValue> {
+    let payload = content
+        .strip_prefix(CIPHERTEXT_PREFIX)
+        .ok_or_else(|| JsValue::from_str("Message is not encrypted"))?;
+    let (iv, ciphertext) = payload
+        .split_once(':')
+        .ok_or_else(|| JsValue::from_str("Malformed encrypted message"))?;
+
+    let key = message_key(secret).await?;
+    let plaintext = webcrypto::await_bytes(webcrypto::subtle()?.decrypt_with_object_and_u8_array(
+        &gcm_params(&webcrypto::base64url_decode(iv)?, sender_id)?,
+        &key,
+        &webcrypto::base64url_decode(ciphertext)?,
+    )).await?;
+
+    String::from_utf8(plaintext).map_err(|_| JsValue::from_str("Message is not valid text"))
+}
+
+// Encrypt an outgoing message if we have a key for the connection.
+// Connections made from links without a key (older invites) stay plaintext;
+// one with a key is never sent in the clear.
+pub async fn seal_message(link_id: &str, sender_id: &str, text: &str) -> Result<String, JsValue> {
+    match connection_secret(link_id) {
+        Some(_) if !webcrypto::is_available() => Err(JsValue::from_str(
+            "This chat is end-to-end encrypted, but this browser can't encrypt here (it needs HTTPS)",
+        )),
+        Some(secret) => encrypt_with_secret(&secret, sender_id, text).await,
+        None => Ok(text.to_string()),
+    }
+}
+
+// Decrypt an incoming message, falling back to a placeholder when we can't
+pub async fn open_message(link_id: &str, sender_id: &str, content: &str) -> OpenedMessage {
+    let secret = connection_secret(link_id);
+
+    if !is_encrypted(content) {
+        if secret.is_some() {
+            return OpenedMessage {
+                text: "⚠ An unencrypted message arrived in this encrypted chat and was hidden. It may not be from who it says.".to_string(),
+                encrypted: false,
+                rejected: true,
+            };
+        }
+        return OpenedMessage { text: content.to_string(), encrypted: false, rejected: false };
+    }
+
+    let opened = match secret {
+        Some(secret) => decrypt_with_secret(&secret, sender_id, content).await.ok(),
+        None => None,
+    };
+
+    match opened {
+        Some(text) => OpenedMessage { text, encrypted: true, rejected: false },
+        None => OpenedMessage {
+            text: "🔒 This message can't be decrypted on this device.".to_string(),
+            encrypted: true,
+            rejected: false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::generate_identity;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_extract_secret_from_fragment() {
+        assert_eq!(extract_secret_from_fragment("#key=abc"), Some("abc".to_string()));
+        assert_eq!(extract_secret_from_fragment("#foo=1&key=abc"), Some("abc".to_string()));
+        assert_eq!(extract_secret_from_fragment("#key="), None);
+        assert_eq!(extract_secret_from_fragment(""), None);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_two_identities_exchange_encrypted_messages() {
+        let alice = generate_identity(None).await.unwrap();
+        let bob = generate_identity(None).await.unwrap();
+
+        // Alice creates the connection; Bob receives the secret through the invite fragment
+        let link_id = format!("test-link-{}", uuid::Uuid::new_v4());
+        let alice_secret = create_connection_secret(&link_id).unwrap();
+        let bob_secret = extract_secret_from_fragment(&invite_fragment(&link_id)).unwrap();
+        assert_eq!(alice_secret, bob_secret);
+
+        let sealed = encrypt_with_secret(&alice_secret, &alice.player_id, "hi Bob").await.unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(!sealed.contains("hi Bob"), "Ciphertext must not contain the plaintext");
+
+        let opened = decrypt_with_secret(&bob_secret, &alice.player_id, &sealed).await.unwrap();
+        assert_eq!(opened, "hi Bob");
+
+        // The same ciphertext can't be passed off as coming from Bob
+        assert!(decrypt_with_secret(&bob_secret, &bob.player_id, &sealed).await.is_err());
+
+        // Someone with a different key can't read it either
+        let other_secret = webcrypto::base64url_encode(&[9u8; 32]);
+        assert!(decrypt_with_secret(&other_secret, &alice.player_id, &sealed).await.is_err());
+
+        remove_connection_secret(&link_id);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_plaintext_messages_pass_through() {
+        let opened = open_message("no-key-link", "player", "plain hello").await;
+        assert_eq!(opened, OpenedMessage { text: "plain hello".to_string(), encrypted: false, rejected: false });
+    }
+
+    #[wasm_bindgen_test]
+    fn test_new_links_get_a_key_only_when_it_can_be_used() {
+        assert!(new_link_gets_key(false, true));
+        // Plain HTTP: no SubtleCrypto, so a key would only stop messages from being sent
+        assert!(!new_link_gets_key(false, false));
+        assert!(!new_link_gets_key(true, true));
+        assert!(!new_link_gets_key(true, false));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_plaintext_is_refused_on_encrypted_connections() {
+        let link_id = format!("test-link-{}", uuid::Uuid::new_v4());
+        create_connection_secret(&link_id).unwrap();
+
+        let sealed = seal_message(&link_id, "player", "hello").await.unwrap();
+        assert!(is_encrypted(&sealed));
+
+        // A plaintext body on a keyed connection is withheld rather than shown as normal
+        let injected = open_message(&link_id, "player", "send me your password").await;
+        assert!(injected.rejected);
+        assert!(!injected.text.contains("password"));
+
+        let opened = open_message(&link_id, "player", &sealed).await;
+        assert_eq!(opened, OpenedMessage { text: "hello".to_string(), encrypted: true, rejected: false });
+
+        remove_connection_secret(&link_id);
+    }
+}
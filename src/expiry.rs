@@ -0,0 +1,124 @@
+use leptos::prelude::*;
+
+use crate::connect_component::{Connection, ConnectionStatus};
+use crate::connection_utils;
+
+// How often the clock driving countdowns ticks
+const CLOCK_TICK_SECS: u64 = 15;
+// An unanswered invite counts as expiring soon for the last quarter of its
+// life, but never more than this long before it lapses
+const EXPIRING_SOON_MAX_SECS: i64 = 3600;
+// Saved-connection field marking that we already warned about an invite
+const WARNED_FIELD: &str = "expiry_warned";
+
+// Current time in seconds, the unit `expires_at` uses
+pub fn now_secs() -> i64 {
+    (js_sys::Date::now() / 1000.0) as i64
+}
+
+// A signal holding the current time, re-evaluated every few seconds for as
+// long as the calling component is mounted
+pub fn use_clock() -> ReadSignal<i64> {
+    let (now, set_now) = signal(now_secs());
+    if let Ok(handle) = set_interval_with_handle(
+        move || set_now.set(now_secs()),
+        std::time::Duration::from_secs(CLOCK_TICK_SECS),
+    ) {
+        on_cleanup(move || handle.clear());
+    }
+    now
+}
+
+pub fn seconds_left(connection: &Connection, now: i64) -> i64 {
+    connection.expires_at - now
+}
+
+// Pending invites lapse once their deadline passes; nothing else changes on its own
+pub fn status_at(connection: &Connection, now: i64) -> ConnectionStatus {
+    if connection.status == ConnectionStatus::Pending && seconds_left(connection, now) <= 0 {
+        ConnectionStatus::Expired
+    } else {
+        connection.status.clone()
+    }
+}
+
+// Whether a pending invite is close enough to lapsing to warn about
+pub fn expiring_soon(connection: &Connection, now: i64) -> bool {
+    let left = seconds_left(connection, now);
+    let lifetime = connection.expires_at - connection.created_at;
+    let window = (lifetime / 4).clamp(60, EXPIRING_SOON_MAX_SECS);
+    status_at(connection, now) == ConnectionStatus::Pending && left <= window
+}
+
+// Time left in words, e.g. "3h 12m" or "2d 4h"
+pub fn format_remaining(seconds: i64) -> String {
+    if seconds < 60 {
+        return "less than a minute".to_string();
+    }
+    let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+// Whether we've already warned that this invite is about to lapse
+pub fn already_warned(connection_id: &str) -> bool {
+    connection_utils::get_saved_connection(connection_id)
+        .and_then(|record| record.get(WARNED_FIELD).and_then(|v| v.as_bool()))
+        .unwrap_or(false)
+}
+
+pub fn mark_warned(connection_id: &str) {
+    connection_utils::update_saved_connection(connection_id, |record| {
+        record.insert(WARNED_FIELD.to_string(), serde_json::Value::Bool(true));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_component::test_connection;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn pending(created_at: i64, expires_at: i64) -> Connection {
+        Connection { created_at, expires_at, ..test_connection("c1", ConnectionStatus::Pending) }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_format_remaining() {
+        assert_eq!(format_remaining(30), "less than a minute");
+        assert_eq!(format_remaining(12 * 60 + 59), "12m");
+        assert_eq!(format_remaining(3 * 3600 + 12 * 60), "3h 12m");
+        assert_eq!(format_remaining(2 * 86400 + 4 * 3600 + 5), "2d 4h");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pending_invites_lapse_at_their_deadline() {
+        let connection = pending(0, 86400);
+        assert_eq!(status_at(&connection, 86399), ConnectionStatus::Pending);
+        assert_eq!(status_at(&connection, 86400), ConnectionStatus::Expired);
+
+        let active = Connection { status: ConnectionStatus::Active, ..connection };
+        assert_eq!(status_at(&active, 90000), ConnectionStatus::Active);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_expiring_soon_scales_with_lifetime() {
+        // A day-long invite warns in its last hour
+        let day = pending(0, 86400);
+        assert!(!expiring_soon(&day, 86400 - 3601));
+        assert!(expiring_soon(&day, 86400 - 3600));
+        assert!(!expiring_soon(&day, 86400));
+
+        // An hour-long invite warns in its last quarter hour
+        let hour = pending(0, 3600);
+        assert!(!expiring_soon(&hour, 2000));
+        assert!(expiring_soon(&hour, 2700));
+    }
+}
@@ -0,0 +1,181 @@
+use leptos::prelude::*;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::api_retry::RetryPolicy;
+use crate::blocklist::{self, BlockReport};
+use crate::browser::console_log;
+use crate::connect_component::{get_stored_player_id, Connection};
+use crate::connection_utils;
+use crate::profile::{self, Friend, ProfileCard};
+
+// Group sizes offered for a new invite, counting whoever makes it
+pub const GROUP_SIZE_CHOICES: [u32; 3] = [5, 10, 20];
+// Saved-connection field listing the members we've already announced
+const MEMBERS_FIELD: &str = "members";
+
+// Seats on a one-to-one connection, which stays the default
+pub fn one_to_one() -> u32 {
+    2
+}
+
+// What to call a member: their display name, or the start of their id
+pub fn member_name(member: &Friend) -> String {
+    member
+        .profile
+        .as_ref()
+        .map(|profile| profile.display_name.clone())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| format!("Player {}", member.player_id.chars().take(6).collect::<String>()))
+}
+
+// Everyone on a connection, with the profile each of them shares
+pub async fn fetch_members(link_id: &str) -> Result<Vec<Friend>, JsValue> {
+    let policy = RetryPolicy::polling();
+    let connection = connection_utils::get_connection_by_link_id(link_id, &policy).await?;
+
+    let mut members = Vec::new();
+    for player_id in connection.players {
+        let profile = match connection_utils::get_player_profile(&player_id, &policy).await {
+            Ok(profile) => profile.and_then(|profile| profile::normalize_profile(profile).ok()),
+            Err(e) => {
+                console_log(&format!("Couldn't load profile for {}: {:?}", player_id, e));
+                None
+            }
+        };
+        members.push(Friend { player_id, profile });
+    }
+
+    Ok(members)
+}
+
+fn known_members(connection_id: &str) -> Vec<String> {
+    connection_utils::get_saved_connection(connection_id)
+        .and_then(|record| record.get(MEMBERS_FIELD).cloned())
+        .and_then(|members| serde_json::from_value(members).ok())
+        .unwrap_or_default()
+}
+
+fn remember_members(connection_id: &str, members: &[Friend]) {
+    let ids: Vec<&str> = members.iter().map(|member| member.player_id.as_str()).collect();
+    connection_utils::update_saved_connection(connection_id, |record| {
+        record.insert(MEMBERS_FIELD.to_string(), serde_json::json!(ids));
+    });
+}
+
+// Members who aren't in `known`, leaving ourselves out
+pub fn new_members(members: &[Friend], known: &[String], me: &str) -> Vec<Friend> {
+    members
+        .iter()
+        .filter(|member| member.player_id != me && !known.contains(&member.player_id))
+        .cloned()
+        .collect()
+}
+
+// Members of a group who joined since we last looked, so each can be announced
+pub async fn newly_joined(connection: &Connection) -> Vec<Friend> {
+    let members = match fetch_members(&connection.link_id).await {
+        Ok(members) => members,
+        Err(e) => {
+            console_log(&format!("Couldn't load members of {}: {:?}", connection.id, e));
+            return Vec::new();
+        }
+    };
+
+    let me = get_stored_player_id().unwrap_or_default();
+    let joined = new_members(&members, &known_members(&connection.id), &me)
+        .into_iter()
+        .filter(|member| !blocklist::is_blocked(&member.player_id))
+        .collect();
+    remember_members(&connection.id, &members);
+    joined
+}
+
+// Who's in a group connection, out of how many seats
+#[component]
+pub fn MemberList(connection: Connection) -> impl IntoView {
+    let (members, set_members) = signal(None::<Vec<Friend>>);
+    let me = get_stored_player_id().unwrap_or_default();
+    let max_players = connection.max_players;
+
+    let link_id = connection.link_id.clone();
+    let connection_id = StoredValue::new(connection.id.clone());
+    spawn_local(async move {
+        match fetch_members(&link_id).await {
+            Ok(found) => {
+                remember_members(&connection_id.get_value(), &found);
+                set_members.set(Some(found));
+            },
+            Err(e) => console_log(&format!("Couldn't load members: {:?}", e)),
+        }
+    });
+
+    view! {
+        <div data-test-id="member-list">
+            <div class="text-sm font-medium text-gray-200 mb-2">
+                {move || match members.get() {
+                    Some(found) => format!("Members ({} of {})", found.len(), max_players),
+                    None => format!("Members (up to {})", max_players),
+                }}
+            </div>
+            <div class="flex flex-col gap-2 max-h-40 overflow-y-auto">
+                {move || members.get().unwrap_or_default().into_iter().filter(|member| !blocklist::is_blocked(&member.player_id)).map(|member| {
+                    let is_me = member.player_id == me;
+                    let blocked_id = member.player_id.clone();
+                    view! {
+                        <div class="flex justify-between items-center">
+                            <ProfileCard player_id=member.player_id.clone() profile=member.profile.clone().unwrap_or_default() />
+                            {if is_me {
+                                view! { <span class="text-xs text-gray-400">"You"</span> }.into_any()
+                            } else {
+                                view! {
+                                    <BlockReport
+                                        player_id=member.player_id.clone()
+                                        name=member_name(&member)
+                                        connection_id=connection_id.get_value()
+                                        on_blocked=Callback::new(move |_| {
+                                            set_members.update(|found| {
+                                                if let Some(found) = found {
+                                                    found.retain(|m| m.player_id != blocked_id);
+                                                }
+                                            });
+                                        })
+                                    />
+                                }.into_any()
+                            }}
+                        </div>
+                    }
+                }).collect_view()}
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::PlayerProfile;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn member(player_id: &str, display_name: &str) -> Friend {
+        Friend {
+            player_id: player_id.to_string(),
+            profile: Some(PlayerProfile { display_name: display_name.to_string(), ..Default::default() }),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_new_members_skips_known_and_self() {
+        let members = vec![member("me", "Me"), member("a", "Ann"), member("b", "Bo")];
+        let joined = new_members(&members, &["a".to_string()], "me");
+        assert_eq!(joined, vec![member("b", "Bo")]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_member_name_falls_back_to_id() {
+        assert_eq!(member_name(&member("a", "Ann")), "Ann");
+        assert_eq!(member_name(&Friend { player_id: "abcdef123".to_string(), profile: None }), "Player abcdef");
+    }
+}
@@ -0,0 +1,123 @@
+use base64::Engine;
+
+// Identicons give every player id a recognisable picture: a 5×5 grid,
+// mirrored left to right, in a colour picked from the same hash.
+
+// Cells per side; only the left three columns are free, the rest mirror them
+const GRID: usize = 5;
+const FREE_COLUMNS: usize = GRID.div_ceil(2);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identicon {
+    pub cells: [[bool; GRID]; GRID],
+    // Hue in degrees, 0..360
+    pub hue: u16,
+}
+
+// 64-bit FNV-1a: tiny, stable across platforms and good enough to spread ids
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+pub fn identicon(player_id: &str) -> Identicon {
+    let hash = fnv1a64(player_id.as_bytes());
+
+    let mut cells = [[false; GRID]; GRID];
+    for (row, cells_row) in cells.iter_mut().enumerate() {
+        for column in 0..FREE_COLUMNS {
+            let filled = (hash >> (row * FREE_COLUMNS + column)) & 1 == 1;
+            cells_row[column] = filled;
+            cells_row[GRID - 1 - column] = filled;
+        }
+    }
+
+    // An empty grid would just be a blank square
+    if cells.iter().flatten().all(|filled| !filled) {
+        cells[GRID / 2][GRID / 2] = true;
+    }
+
+    // The pattern uses the low 15 bits; take the colour from the top of the hash
+    let hue = ((hash >> 48) % 360) as u16;
+
+    Identicon { cells, hue }
+}
+
+// Render a player id's identicon as an SVG document
+pub fn identicon_svg(player_id: &str) -> String {
+    let icon = identicon(player_id);
+    let foreground = format!("hsl({}, 65%, 55%)", icon.hue);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {size} {size}\" shape-rendering=\"crispEdges\">\
+         <rect width=\"{size}\" height=\"{size}\" fill=\"hsl({hue}, 25%, 18%)\"/>",
+        size = GRID + 1,
+        hue = icon.hue,
+    );
+    for (row, cells_row) in icon.cells.iter().enumerate() {
+        for (column, filled) in cells_row.iter().enumerate() {
+            if *filled {
+                // Offset by half a cell to leave a margin around the pattern
+                svg.push_str(&format!(
+                    "<rect x=\"{}.5\" y=\"{}.5\" width=\"1\" height=\"1\" fill=\"{}\"/>",
+                    column, row, foreground
+                ));
+            }
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// The identicon as a `data:` URL for use in an <img>
+pub fn identicon_data_url(player_id: &str) -> String {
+    format!(
+        "data:image/svg+xml;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(identicon_svg(player_id))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_fnv1a64_known_values() {
+        assert_eq!(fnv1a64(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a64(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_identicon_is_stable() {
+        let id = "5b7e1f0c-2a6d-4c3e-9f1a-8d2b7c6e5a41";
+        assert_eq!(identicon(id), identicon(id));
+        assert_eq!(identicon_svg(id), identicon_svg(id));
+        assert_eq!(identicon("a").hue, (0xaf63 % 360) as u16);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_identicon_is_mirrored_and_never_blank() {
+        for index in 0..50 {
+            let icon = identicon(&format!("player-{}", index));
+            for row in icon.cells {
+                for column in 0..GRID {
+                    assert_eq!(row[column], row[GRID - 1 - column]);
+                }
+            }
+            assert!(icon.cells.iter().flatten().any(|filled| *filled));
+            assert!(icon.hue < 360);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_different_ids_look_different() {
+        let first = identicon("player-one");
+        let second = identicon("player-two");
+        assert_ne!(first, second);
+        assert!(identicon_data_url("player-one").starts_with("data:image/svg+xml;base64,"));
+    }
+}
@@ -0,0 +1,546 @@
+use std::cell::{Cell, RefCell};
+
+use gloo_timers::future::TimeoutFuture;
+use serde::{Serialize, Deserialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::CryptoKey;
+
+use crate::api_retry::RetryPolicy;
+use crate::browser::{console_log, storage};
+use crate::connection_utils;
+use crate::key_store;
+use crate::webcrypto::{self, params};
+
+// localStorage key for the public half of the identity; "player-id" keeps
+// holding the plain id. The private key lives in IndexedDB: SIGNING_KEY_NAME
+// can't be exported and is used for signing, BACKUP_KEY_NAME is an exportable
+// copy that only `export_identity` reads.
+const IDENTITY_KEY: &str = "player-identity";
+const PLAYER_ID_KEY: &str = "player-id";
+const SIGNING_KEY_NAME: &str = "signing-key";
+const BACKUP_KEY_NAME: &str = "signing-key-backup";
+
+// Iterations for deriving the backup encryption key from a passphrase
+const PBKDF2_ITERATIONS: u32 = 250_000;
+const BACKUP_VERSION: u32 = 1;
+
+// Our long-lived identity: the public half of an ECDSA P-256 keypair plus the
+// player id it maps to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Identity {
+    pub player_id: String,
+    pub public_key_jwk: serde_json::Value,
+    // Set in backups, where the passphrase protects it, and in identities from
+    // before the private key moved to IndexedDB. Never kept in localStorage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    private_key_jwk: Option<serde_json::Value>,
+    // Raw (uncompressed) public key, base64url; this is what the id is derived from
+    pub public_key: String,
+    // Set when the id predates the keypair and was kept so existing connections still work
+    #[serde(default)]
+    pub legacy_player_id: bool,
+    // Whether the server has bound that kept id to our public key
+    #[serde(default)]
+    pub key_registered: bool,
+    pub created_at: i64,
+}
+
+// Everything needed to restore the app after site data is cleared
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IdentityBackup {
+    identity: Identity,
+    saved_connections: Option<String>,
+    connection_names: Vec<(String, String)>,
+    // End-to-end message keys, by link id
+    #[serde(default)]
+    connection_keys: Vec<(String, String)>,
+    // The profile we share with friends
+    #[serde(default)]
+    profile: Option<String>,
+}
+
+// Encrypted backup file format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EncryptedBackup {
+    version: u32,
+    kdf: String,
+    iterations: u32,
+    salt: String,
+    iv: String,
+    ciphertext: String,
+}
+
+thread_local! {
+    // Imported signing key, so requests don't re-import the JWK every time
+    static SIGNING_KEY: RefCell<Option<(String, CryptoKey)>> = const { RefCell::new(None) };
+    // Set while an identity is being loaded or created, so two callers can't
+    // create two different ones
+    static SETTING_UP: Cell<bool> = const { Cell::new(false) };
+}
+
+fn ecdsa_key_params() -> Result<js_sys::Object, JsValue> {
+    params(&[
+        ("name", JsValue::from_str("ECDSA")),
+        ("namedCurve", JsValue::from_str("P-256")),
+    ])
+}
+
+// Player ids stay UUID-shaped so the server and existing code keep working;
+// the bits come from a hash of the public key instead of a random number
+pub fn player_id_from_public_key_hash(hash: &[u8]) -> String {
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hash[..16]);
+    uuid::Builder::from_custom_bytes(bytes).into_uuid().to_string()
+}
+
+pub fn load_identity() -> Option<Identity> {
+    let json = storage()?.get_item(IDENTITY_KEY).ok()??;
+    serde_json::from_str(&json).ok()
+}
+
+fn save_identity(identity: &Identity) -> Option<()> {
+    let storage = storage()?;
+    let json = serde_json::to_string(identity).ok()?;
+    storage.set_item(IDENTITY_KEY, &json).ok()?;
+    storage.set_item(PLAYER_ID_KEY, &identity.player_id).ok()?;
+    Some(())
+}
+
+// Generate a fresh keypair, with the private key carried along until
+// `store_identity` moves it into IndexedDB. If a bare UUID player id already
+// exists it is kept, so connections made before keypairs existed are not
+// orphaned; `bind_legacy_id` then ties it to the key.
+pub async fn generate_identity(existing_player_id: Option<String>) -> Result<Identity, JsValue> {
+    let subtle = webcrypto::subtle()?;
+
+    let key_pair = JsFuture::from(subtle.generate_key_with_object(
+        &ecdsa_key_params()?,
+        true,
+        &webcrypto::usages(&["sign", "verify"]),
+    )?).await?;
+
+    let private_key: CryptoKey = js_sys::Reflect::get(&key_pair, &JsValue::from_str("privateKey"))?.unchecked_into();
+    let public_key: CryptoKey = js_sys::Reflect::get(&key_pair, &JsValue::from_str("publicKey"))?.unchecked_into();
+
+    let raw_public_key = webcrypto::await_bytes(subtle.export_key("raw", &public_key)).await?;
+    let hash = webcrypto::sha256(&raw_public_key).await?;
+
+    let legacy_player_id = existing_player_id.is_some();
+    let player_id = existing_player_id.unwrap_or_else(|| player_id_from_public_key_hash(&hash));
+
+    let identity = Identity {
+        player_id,
+        public_key_jwk: webcrypto::export_jwk(&public_key).await?,
+        private_key_jwk: Some(webcrypto::export_jwk(&private_key).await?),
+        public_key: webcrypto::base64url_encode(&raw_public_key),
+        legacy_player_id,
+        key_registered: false,
+        created_at: js_sys::Date::now() as i64,
+    };
+    Ok(identity)
+}
+
+// Move the identity's private key into IndexedDB and keep the rest in
+// localStorage. Signing uses a copy that can't be exported; the exportable copy
+// is only read when making a backup.
+async fn store_identity(mut identity: Identity) -> Result<Identity, JsValue> {
+    let jwk = identity.private_key_jwk.take()
+        .ok_or_else(|| JsValue::from_str("Identity has no private key"))?;
+    let signing_key = webcrypto::import_jwk(&jwk, &ecdsa_key_params()?, false, &["sign"]).await?;
+    let backup_copy = webcrypto::import_jwk(&jwk, &ecdsa_key_params()?, true, &["sign"]).await?;
+
+    key_store::save_key(SIGNING_KEY_NAME, &signing_key).await?;
+    key_store::save_key(BACKUP_KEY_NAME, &backup_copy).await?;
+    save_identity(&identity).ok_or_else(|| JsValue::from_str("Failed to store identity"))?;
+    SIGNING_KEY.with(|cache| *cache.borrow_mut() = Some((identity.public_key.clone(), signing_key)));
+    Ok(identity)
+}
+
+// Ask the server to bind a kept legacy id to our key, the first and only time
+// that id gets a key. A failure is logged and tried again the next time the
+// app starts.
+async fn bind_legacy_id(mut identity: Identity) -> Identity {
+    if !identity.legacy_player_id || identity.key_registered {
+        return identity;
+    }
+    match connection_utils::register_player_key(&identity.player_id, &identity.public_key, &RetryPolicy::no_retry()).await {
+        Ok(()) => {
+            identity.key_registered = true;
+            save_identity(&identity);
+            console_log(&format!("Bound player {} to its key", identity.player_id));
+        },
+        Err(e) => console_log(&format!("Couldn't bind player id to key yet: {:?}", e)),
+    }
+    identity
+}
+
+// Load our identity, creating one on first run.
+//
+// Without WebCrypto (plain HTTP) we fall back to the old random UUID so the app
+// keeps working; requests are then simply unsigned.
+pub async fn ensure_identity() -> Result<Option<Identity>, JsValue> {
+    // Whoever comes second waits for the first setup and then finds its result
+    while SETTING_UP.with(|busy| busy.get()) {
+        TimeoutFuture::new(20).await;
+    }
+    SETTING_UP.with(|busy| busy.set(true));
+    let result = set_up_identity().await;
+    SETTING_UP.with(|busy| busy.set(false));
+    result
+}
+
+async fn set_up_identity() -> Result<Option<Identity>, JsValue> {
+    let existing_player_id = storage().and_then(|s| s.get_item(PLAYER_ID_KEY).ok().flatten());
+
+    let identity = match load_identity() {
+        Some(identity) => {
+            if identity.private_key_jwk.is_some() {
+                // Older versions kept the private key in localStorage
+                let identity = store_identity(identity).await?;
+                console_log("Moved the signing key out of localStorage");
+                identity
+            } else if key_store::load_key(SIGNING_KEY_NAME).await?.is_none() {
+                // The key store was cleared on its own. A new key can't prove it
+                // speaks for this id, so only a backup can bring it back.
+                return Err(JsValue::from_str("The signing key is missing from this browser. Import a backup to restore it."));
+            } else {
+                identity
+            }
+        },
+        None => {
+            if !webcrypto::is_available() {
+                if existing_player_id.is_none() {
+                    if let Some(storage) = storage() {
+                        storage.set_item(PLAYER_ID_KEY, &uuid::Uuid::new_v4().to_string())?;
+                    }
+                }
+                console_log("WebCrypto unavailable, using an unsigned player id");
+                return Ok(None);
+            }
+
+            let identity = store_identity(generate_identity(existing_player_id).await?).await?;
+            console_log(&format!("Created identity for player {}", identity.player_id));
+            identity
+        },
+    };
+
+    // The keypair is the source of truth for who we are
+    if let Some(storage) = storage() {
+        if storage.get_item(PLAYER_ID_KEY)?.as_deref() != Some(identity.player_id.as_str()) {
+            storage.set_item(PLAYER_ID_KEY, &identity.player_id)?;
+        }
+    }
+
+    Ok(Some(bind_legacy_id(identity).await))
+}
+
+// Our player id, once identity setup has finished. Creating and joining wait
+// for this instead of racing the setup with a random id of their own.
+pub async fn player_id() -> String {
+    if let Err(e) = ensure_identity().await {
+        console_log(&format!("Failed to set up identity: {:?}", e));
+    }
+    if let Some(player_id) = storage().and_then(|s| s.get_item(PLAYER_ID_KEY).ok().flatten()) {
+        return player_id;
+    }
+
+    // Fall back to an unsigned id so the app keeps working
+    let player_id = uuid::Uuid::new_v4().to_string();
+    if let Some(storage) = storage() {
+        let _ = storage.set_item(PLAYER_ID_KEY, &player_id);
+    }
+    player_id
+}
+
+async fn signing_key(identity: &Identity) -> Result<CryptoKey, JsValue> {
+    let cached = SIGNING_KEY.with(|cache| {
+        cache.borrow()
+            .as_ref()
+            .filter(|(public_key, _)| *public_key == identity.public_key)
+            .map(|(_, key)| key.clone())
+    });
+    if let Some(key) = cached {
+        return Ok(key);
+    }
+
+    let key = key_store::load_key(SIGNING_KEY_NAME).await?
+        .ok_or_else(|| JsValue::from_str("No signing key stored"))?;
+    SIGNING_KEY.with(|cache| *cache.borrow_mut() = Some((identity.public_key.clone(), key.clone())));
+    Ok(key)
+}
+
+// Sign `data` with our private key (ECDSA P-256 / SHA-256, raw r||s signature)
+pub async fn sign(identity: &Identity, data: &[u8]) -> Result<Vec<u8>, JsValue> {
+    let key = signing_key(identity).await?;
+    let algorithm = params(&[
+        ("name", JsValue::from_str("ECDSA")),
+        ("hash", JsValue::from_str("SHA-256")),
+    ])?;
+    webcrypto::await_bytes(webcrypto::subtle()?.sign_with_object_and_u8_array(&algorithm, &key, data)).await
+}
+
+// The string a request signature covers
+pub fn canonical_request(method: &str, url: &str, timestamp: i64, body_hash: &str) -> String {
+    format!("{}\n{}\n{}\n{}", method, url, timestamp, body_hash)
+}
+
+// Headers proving the request comes from the holder of our private key.
+// Returns nothing when there is no identity (e.g. WebCrypto unavailable).
+pub async fn signature_headers(method: &str, url: &str, body: Option<&str>) -> Option<Vec<(&'static str, String)>> {
+    let identity = load_identity()?;
+
+    let result: Result<Vec<(&'static str, String)>, JsValue> = async {
+        let timestamp = js_sys::Date::now() as i64;
+        let body_hash = webcrypto::base64url_encode(&webcrypto::sha256(body.unwrap_or("").as_bytes()).await?);
+        let canonical = canonical_request(method, url, timestamp, &body_hash);
+        let signature = sign(&identity, canonical.as_bytes()).await?;
+
+        Ok(vec![
+            ("X-Player-Id", identity.player_id.clone()),
+            ("X-Public-Key", identity.public_key.clone()),
+            ("X-Timestamp", timestamp.to_string()),
+            ("X-Signature", webcrypto::base64url_encode(&signature)),
+        ])
+    }.await;
+
+    match result {
+        Ok(headers) => Some(headers),
+        Err(e) => {
+            console_log(&format!("Failed to sign request: {:?}", e));
+            None
+        }
+    }
+}
+
+// Derive the AES-GCM key protecting a backup from the user's passphrase
+async fn backup_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<CryptoKey, JsValue> {
+    let subtle = webcrypto::subtle()?;
+
+    let base_key = webcrypto::await_key(subtle.import_key_with_object(
+        "raw",
+        &js_sys::Uint8Array::from(passphrase.as_bytes()).unchecked_into(),
+        &params(&[("name", JsValue::from_str("PBKDF2"))])?,
+        false,
+        &webcrypto::usages(&["deriveKey"]),
+    )).await?;
+
+    webcrypto::await_key(subtle.derive_key_with_object_and_object(
+        &params(&[
+            ("name", JsValue::from_str("PBKDF2")),
+            ("salt", webcrypto::bytes_value(salt)),
+            ("iterations", JsValue::from(iterations)),
+            ("hash", JsValue::from_str("SHA-256")),
+        ])?,
+        &base_key,
+        &params(&[
+            ("name", JsValue::from_str("AES-GCM")),
+            ("length", JsValue::from(256)),
+        ])?,
+        false,
+        &webcrypto::usages(&["encrypt", "decrypt"]),
+    )).await
+}
+
+// Export our identity and saved connections, encrypted with `passphrase`
+pub async fn export_identity(passphrase: &str) -> Result<String, JsValue> {
+    let mut identity = load_identity().ok_or_else(|| JsValue::from_str("No identity to export"))?;
+    let storage = storage().ok_or_else(|| JsValue::from_str("localStorage not available"))?;
+
+    // The private key travels inside the passphrase-encrypted backup, so a
+    // restore brings back the same keypair
+    let backup_copy = key_store::load_key(BACKUP_KEY_NAME).await?
+        .ok_or_else(|| JsValue::from_str("This browser's signing key can't be exported"))?;
+    identity.private_key_jwk = Some(webcrypto::export_jwk(&backup_copy).await?);
+
+    // Friendly names and message keys live under one key per connection
+    let mut connection_names = Vec::new();
+    let mut connection_keys = Vec::new();
+    for index in 0..storage.length()? {
+        if let Some(key) = storage.key(index)? {
+            if let Some(id) = key.strip_prefix("conn-name-") {
+                if let Some(name) = storage.get_item(&key)? {
+                    connection_names.push((id.to_string(), name));
+                }
+            } else if let Some(link_id) = key.strip_prefix("conn-key-") {
+                if let Some(secret) = storage.get_item(&key)? {
+                    connection_keys.push((link_id.to_string(), secret));
+                }
+            }
+        }
+    }
+
+    let backup = IdentityBackup {
+        identity,
+        saved_connections: storage.get_item("saved-connections")?,
+        connection_names,
+        connection_keys,
+        profile: storage.get_item("player-profile")?,
+    };
+    let plaintext = serde_json::to_vec(&backup).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    let salt = webcrypto::random_bytes(16)?;
+    let iv = webcrypto::random_bytes(12)?;
+    let key = backup_key(passphrase, &salt, PBKDF2_ITERATIONS).await?;
+    let ciphertext = webcrypto::await_bytes(webcrypto::subtle()?.encrypt_with_object_and_u8_array(
+        &params(&[
+            ("name", JsValue::from_str("AES-GCM")),
+            ("iv", webcrypto::bytes_value(&iv)),
+        ])?,
+        &key,
+        &plaintext,
+    )).await?;
+
+    let encrypted = EncryptedBackup {
+        version: BACKUP_VERSION,
+        kdf: "PBKDF2-SHA256".to_string(),
+        iterations: PBKDF2_ITERATIONS,
+        salt: webcrypto::base64url_encode(&salt),
+        iv: webcrypto::base64url_encode(&iv),
+        ciphertext: webcrypto::base64url_encode(&ciphertext),
+    };
+    serde_json::to_string_pretty(&encrypted).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+// Restore an exported identity, keypair included, replacing the current one
+pub async fn import_identity(backup_json: &str, passphrase: &str) -> Result<Identity, JsValue> {
+    let encrypted: EncryptedBackup = serde_json::from_str(backup_json)
+        .map_err(|_| JsValue::from_str("This doesn't look like an identity backup"))?;
+    if encrypted.version != BACKUP_VERSION {
+        return Err(JsValue::from_str("Unsupported backup version"));
+    }
+
+    let salt = webcrypto::base64url_decode(&encrypted.salt)?;
+    let iv = webcrypto::base64url_decode(&encrypted.iv)?;
+    let ciphertext = webcrypto::base64url_decode(&encrypted.ciphertext)?;
+
+    let key = backup_key(passphrase, &salt, encrypted.iterations).await?;
+    let plaintext = webcrypto::await_bytes(webcrypto::subtle()?.decrypt_with_object_and_u8_array(
+        &params(&[
+            ("name", JsValue::from_str("AES-GCM")),
+            ("iv", webcrypto::bytes_value(&iv)),
+        ])?,
+        &key,
+        &ciphertext,
+    )).await
+        .map_err(|_| JsValue::from_str("Wrong passphrase or damaged backup"))?;
+
+    let backup: IdentityBackup = serde_json::from_slice(&plaintext)
+        .map_err(|e| JsValue::from_str(&format!("Damaged backup: {}", e)))?;
+    if backup.identity.private_key_jwk.is_none() {
+        return Err(JsValue::from_str("This backup doesn't contain its signing key"));
+    }
+
+    // The key is imported before anything else is replaced, so a bad one changes nothing
+    let storage = storage().ok_or_else(|| JsValue::from_str("localStorage not available"))?;
+    let identity = store_identity(backup.identity.clone()).await?;
+    if let Some(saved_connections) = &backup.saved_connections {
+        storage.set_item("saved-connections", saved_connections)?;
+    }
+    for (id, name) in &backup.connection_names {
+        storage.set_item(&format!("conn-name-{}", id), name)?;
+    }
+    for (link_id, secret) in &backup.connection_keys {
+        storage.set_item(&format!("conn-key-{}", link_id), secret)?;
+    }
+    if let Some(profile) = &backup.profile {
+        storage.set_item("player-profile", profile)?;
+    }
+
+    console_log(&format!("Imported identity for player {}", identity.player_id));
+    Ok(bind_legacy_id(identity).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_player_id_from_hash_is_a_stable_uuid() {
+        let hash: Vec<u8> = (0u8..32).collect();
+        let id = player_id_from_public_key_hash(&hash);
+
+        assert!(uuid::Uuid::parse_str(&id).is_ok());
+        assert_eq!(id, player_id_from_public_key_hash(&hash));
+        assert_ne!(id, player_id_from_public_key_hash(&[7u8; 32]));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_new_identity_derives_player_id_from_public_key() {
+        let identity = generate_identity(None).await.expect("should generate identity");
+
+        let raw_public_key = webcrypto::base64url_decode(&identity.public_key).unwrap();
+        let hash = webcrypto::sha256(&raw_public_key).await.unwrap();
+        assert_eq!(identity.player_id, player_id_from_public_key_hash(&hash));
+        assert!(!identity.legacy_player_id);
+        assert!(identity.private_key_jwk.is_some());
+
+        // A kept id still has to be bound to the key by the server
+        let legacy = generate_identity(Some("legacy-id".to_string())).await.unwrap();
+        assert_eq!(legacy.player_id, "legacy-id");
+        assert!(legacy.legacy_player_id);
+        assert!(!legacy.key_registered);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_private_key_moves_out_of_local_storage() {
+        let storage = storage().unwrap();
+
+        // An identity as older versions stored it, private key included
+        let old = generate_identity(None).await.unwrap();
+        storage.set_item(IDENTITY_KEY, &serde_json::to_string(&old).unwrap()).unwrap();
+        assert!(storage.get_item(IDENTITY_KEY).unwrap().unwrap().contains("private_key_jwk"));
+
+        let loaded = ensure_identity().await.unwrap().unwrap();
+        assert_eq!(loaded.player_id, old.player_id);
+        assert_eq!(loaded.public_key, old.public_key);
+        assert!(!storage.get_item(IDENTITY_KEY).unwrap().unwrap().contains("private_key_jwk"));
+        let moved = key_store::load_key(SIGNING_KEY_NAME).await.unwrap().expect("key should be in IndexedDB");
+        assert!(!moved.extractable());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_export_import_round_trip() {
+        let storage = storage().unwrap();
+        storage.remove_item(IDENTITY_KEY).unwrap();
+        storage.remove_item(PLAYER_ID_KEY).unwrap();
+
+        let original = ensure_identity().await.unwrap().expect("WebCrypto should be available in tests");
+        let backup = export_identity("correct horse").await.unwrap();
+
+        assert!(import_identity(&backup, "wrong passphrase").await.is_err());
+
+        // Site data cleared
+        storage.remove_item(IDENTITY_KEY).unwrap();
+        storage.remove_item(PLAYER_ID_KEY).unwrap();
+        key_store::delete_key(SIGNING_KEY_NAME).await.unwrap();
+        key_store::delete_key(BACKUP_KEY_NAME).await.unwrap();
+        SIGNING_KEY.with(|cache| *cache.borrow_mut() = None);
+
+        // The same keypair comes back, so nothing has to be rebound
+        let restored = import_identity(&backup, "correct horse").await.unwrap();
+        assert_eq!(restored, original);
+        assert_eq!(storage.get_item(PLAYER_ID_KEY).unwrap().unwrap(), original.player_id);
+        assert!(!storage.get_item(IDENTITY_KEY).unwrap().unwrap().contains("private_key_jwk"));
+        let signing_key = key_store::load_key(SIGNING_KEY_NAME).await.unwrap().expect("key should be restored");
+        assert!(!signing_key.extractable());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_lost_key_is_not_replaced() {
+        let storage = storage().unwrap();
+        storage.remove_item(IDENTITY_KEY).unwrap();
+        let original = ensure_identity().await.unwrap().unwrap();
+
+        key_store::delete_key(SIGNING_KEY_NAME).await.unwrap();
+        SIGNING_KEY.with(|cache| *cache.borrow_mut() = None);
+
+        // A new key can't prove it speaks for the old id, so none is made
+        assert!(ensure_identity().await.is_err());
+        assert_eq!(load_identity().unwrap().public_key, original.public_key);
+        assert!(key_store::load_key(SIGNING_KEY_NAME).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,169 @@
+use leptos::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{Blob, BlobPropertyBag, HtmlAnchorElement, HtmlInputElement, Url};
+
+use crate::browser::console_log;
+use crate::identity;
+use crate::toast;
+use crate::webcrypto;
+
+// Filename offered when downloading an identity backup
+const BACKUP_FILENAME: &str = "friends-connect-identity.json";
+
+// Offer `contents` as a file download
+pub fn download_text(filename: &str, contents: &str, mime_type: &str) -> Result<(), wasm_bindgen::JsValue> {
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let parts = js_sys::Array::of1(&wasm_bindgen::JsValue::from_str(contents));
+    download_blob(filename, &Blob::new_with_str_sequence_and_options(&parts, &options)?)
+}
+
+// Offer binary `contents` (e.g. an image) as a file download
+pub fn download_bytes(filename: &str, contents: &[u8], mime_type: &str) -> Result<(), wasm_bindgen::JsValue> {
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(contents));
+    download_blob(filename, &Blob::new_with_u8_array_sequence_and_options(&parts, &options)?)
+}
+
+fn download_blob(filename: &str, blob: &Blob) -> Result<(), wasm_bindgen::JsValue> {
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| wasm_bindgen::JsValue::from_str("No document found"))?;
+
+    let url = Url::create_object_url_with_blob(blob)?;
+
+    let anchor: HtmlAnchorElement = document.create_element("a")?.unchecked_into();
+    anchor.set_href(&url);
+    anchor.set_download(filename);
+    anchor.click();
+
+    Url::revoke_object_url(&url)
+}
+
+// Settings section for backing up and restoring the player's identity
+#[component]
+pub fn IdentitySettings() -> impl IntoView {
+    let (passphrase, set_passphrase) = signal(String::new());
+    let (status, set_status) = signal(String::new());
+    let (busy, set_busy) = signal(false);
+    let toasts = toast::use_toasts();
+    let file_input = NodeRef::<leptos::html::Input>::new();
+
+    let player_id = move || {
+        identity::load_identity()
+            .map(|identity| identity.player_id)
+            .or_else(crate::connect_component::get_stored_player_id)
+            .unwrap_or_default()
+    };
+
+    let handle_export = move |_| {
+        let pass = passphrase.get();
+        if pass.len() < 8 {
+            set_status.set("Use a passphrase of at least 8 characters.".to_string());
+            return;
+        }
+
+        set_busy.set(true);
+        set_status.set(String::new());
+        spawn_local(async move {
+            match identity::export_identity(&pass).await {
+                Ok(backup) => {
+                    match download_text(BACKUP_FILENAME, &backup, "application/json") {
+                        Ok(()) => toasts.success("Backup downloaded. Keep it and your passphrase somewhere safe."),
+                        Err(e) => toasts.error(format!("Couldn't download the backup: {:?}", e)),
+                    }
+                },
+                Err(e) => {
+                    let error_msg = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    console_log(&format!("Identity export failed: {}", error_msg));
+                    toasts.error(format!("Export failed: {}", error_msg));
+                }
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let handle_import = move |_| {
+        let pass = passphrase.get();
+        let Some(file) = file_input.get()
+            .and_then(|input: HtmlInputElement| input.files())
+            .and_then(|files| files.get(0))
+        else {
+            set_status.set("Choose a backup file first.".to_string());
+            return;
+        };
+
+        set_busy.set(true);
+        set_status.set(String::new());
+        spawn_local(async move {
+            let contents = match JsFuture::from(file.text()).await {
+                Ok(text) => text.as_string().unwrap_or_default(),
+                Err(e) => {
+                    toasts.error(format!("Couldn't read the file: {:?}", e));
+                    set_busy.set(false);
+                    return;
+                }
+            };
+
+            match identity::import_identity(&contents, &pass).await {
+                Ok(_) => {
+                    // Reload so every component picks up the restored identity and connections
+                    if let Some(window) = web_sys::window() {
+                        let _ = window.location().reload();
+                    }
+                },
+                Err(e) => {
+                    let error_msg = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    toasts.error(format!("Import failed: {}", error_msg));
+                    set_busy.set(false);
+                }
+            }
+        });
+    };
+
+    view! {
+        <div class="mt-4 pt-4 border-t border-gray-700" data-test-id="identity-settings">
+            <div class="font-medium">"Your identity"</div>
+            <div class="text-sm text-gray-400 break-all">{player_id}</div>
+            {move || (!webcrypto::is_available()).then(|| view! {
+                <div class="mt-2 text-sm text-yellow-300">
+                    "Secure identities need HTTPS. Your id can't be backed up from this page."
+                </div>
+            })}
+            <input
+                type="password"
+                class="w-full mt-3 px-4 py-2 rounded bg-gray-900 border border-gray-700 text-gray-100"
+                placeholder="Backup passphrase"
+                prop:value=passphrase
+                on:input=move |ev| set_passphrase.set(event_target_value(&ev))
+            />
+            <input
+                type="file"
+                accept="application/json,.json"
+                class="w-full mt-2 text-sm text-gray-300"
+                node_ref=file_input
+            />
+            <div class="flex justify-end gap-2 mt-2">
+                <button
+                    class="px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200"
+                    prop:disabled=move || busy.get() || !webcrypto::is_available()
+                    on:click=handle_import
+                >
+                    "Import backup"
+                </button>
+                <button
+                    class="px-3 py-1 bg-indigo-600 hover:bg-indigo-700 rounded text-sm text-gray-100"
+                    prop:disabled=move || busy.get() || !webcrypto::is_available()
+                    on:click=handle_export
+                >
+                    "Export backup"
+                </button>
+            </div>
+            {move || (!status.get().is_empty()).then(|| view! {
+                <div class="mt-2 text-sm text-gray-300">{status.get()}</div>
+            })}
+        </div>
+    }
+}
@@ -0,0 +1,293 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+
+use crate::api_retry::RetryPolicy;
+use crate::browser::storage;
+use crate::connect_component::Connection;
+use crate::connection_utils;
+use crate::group;
+
+// localStorage key for the invite settings picked last, used for new links
+const POLICY_KEY: &str = "invite-policy";
+// Limits offered for how many friends can join by one link; `None` is no limit
+pub const MAX_USES_CHOICES: [Option<u32>; 4] = [Some(1), Some(5), Some(10), None];
+
+// How long a new invite link stays open
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InviteExpiry {
+    Hour,
+    Day,
+    Week,
+}
+
+impl InviteExpiry {
+    pub const ALL: [InviteExpiry; 3] = [InviteExpiry::Hour, InviteExpiry::Day, InviteExpiry::Week];
+
+    pub fn seconds(self) -> i64 {
+        match self {
+            InviteExpiry::Hour => 3600,
+            InviteExpiry::Day => 86400,
+            InviteExpiry::Week => 7 * 86400,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InviteExpiry::Hour => "1 hour",
+            InviteExpiry::Day => "1 day",
+            InviteExpiry::Week => "1 week",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<InviteExpiry> {
+        InviteExpiry::ALL.into_iter().find(|expiry| expiry.label() == label)
+    }
+}
+
+// The settings a new invite link is made with
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct InvitePolicy {
+    pub expiry: InviteExpiry,
+    pub max_uses: Option<u32>,
+    // Whether we accept or decline each friend who uses the link
+    #[serde(default)]
+    pub require_approval: bool,
+    // Seats for a group connection; `None` for one-to-one
+    #[serde(default)]
+    pub group_size: Option<u32>,
+    // Friends join by typing a code instead of opening the link. A code can't
+    // carry the encryption key, so such a link is made without one.
+    #[serde(default)]
+    pub join_by_code: bool,
+}
+
+impl Default for InvitePolicy {
+    // What the server did before links were configurable: a day, one friend
+    fn default() -> Self {
+        InvitePolicy { expiry: InviteExpiry::Day, max_uses: Some(1), require_approval: false, group_size: None, join_by_code: false }
+    }
+}
+
+impl InvitePolicy {
+    pub fn max_players(&self) -> u32 {
+        self.group_size.unwrap_or_else(group::one_to_one)
+    }
+}
+
+pub fn load_policy() -> InvitePolicy {
+    storage()
+        .and_then(|storage| storage.get_item(POLICY_KEY).ok().flatten())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_policy(policy: &InvitePolicy) {
+    let Some(storage) = storage() else {
+        return;
+    };
+    if *policy == InvitePolicy::default() {
+        let _ = storage.remove_item(POLICY_KEY);
+    } else if let Ok(json) = serde_json::to_string(policy) {
+        let _ = storage.set_item(POLICY_KEY, &json);
+    }
+}
+
+pub fn uses_label(max_uses: Option<u32>) -> String {
+    match max_uses {
+        Some(1) => "Single use".to_string(),
+        Some(max) => format!("Up to {} friends", max),
+        None => "Unlimited".to_string(),
+    }
+}
+
+fn uses_value(max_uses: Option<u32>) -> String {
+    max_uses.map(|max| max.to_string()).unwrap_or_default()
+}
+
+// A link's lifetime in words, e.g. "1 day" or "36 hours"
+pub fn describe_lifetime(seconds: i64) -> String {
+    if let Some(expiry) = InviteExpiry::ALL.into_iter().find(|expiry| expiry.seconds() == seconds) {
+        return expiry.label().to_string();
+    }
+    let (count, unit) = if seconds >= 2 * 86400 && seconds % 86400 == 0 {
+        (seconds / 86400, "day")
+    } else if seconds >= 3600 {
+        (seconds / 3600, "hour")
+    } else {
+        ((seconds / 60).max(1), "minute")
+    };
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+// How many more friends can join by a connection's link; `None` for no limit
+pub fn remaining_uses(connection: &Connection) -> Option<u32> {
+    // Older servers don't count uses; everyone but the creator joined by the link
+    let used = connection.uses.max(connection.players.len().saturating_sub(1) as u32);
+    connection.max_uses.map(|max| max.saturating_sub(used))
+}
+
+// Expiry, use-limit, group, approval and join-code choices for a new invite link. Changes are
+// remembered for the next link and reported through `on_change`.
+#[component]
+pub fn InvitePolicyPicker(on_change: Callback<InvitePolicy>) -> impl IntoView {
+    let (policy, set_policy) = signal(load_policy());
+
+    let choose = move |update: &dyn Fn(&mut InvitePolicy)| {
+        let mut chosen = policy.get_untracked();
+        update(&mut chosen);
+        if chosen != policy.get_untracked() {
+            save_policy(&chosen);
+            set_policy.set(chosen);
+            on_change.run(chosen);
+        }
+    };
+
+    let select_class = "px-2 py-1 rounded bg-gray-900 border border-gray-700 text-sm text-gray-100";
+
+    view! {
+        <div class="flex flex-wrap gap-2 items-center text-sm text-gray-300" data-test-id="invite-policy-picker">
+            <span>"Link works for"</span>
+            <select
+                class=select_class
+                on:change=move |ev| {
+                    if let Some(expiry) = InviteExpiry::from_label(&event_target_value(&ev)) {
+                        choose(&|chosen| chosen.expiry = expiry);
+                    }
+                }
+            >
+                {InviteExpiry::ALL.into_iter().map(|expiry| view! {
+                    <option value=expiry.label() selected=move || policy.get().expiry == expiry>{expiry.label()}</option>
+                }).collect_view()}
+            </select>
+            <select
+                class=select_class
+                on:change=move |ev| {
+                    let max_uses = event_target_value(&ev).parse::<u32>().ok();
+                    choose(&|chosen| chosen.max_uses = max_uses);
+                }
+            >
+                {MAX_USES_CHOICES.into_iter().map(|max_uses| view! {
+                    <option value=uses_value(max_uses) selected=move || policy.get().max_uses == max_uses>
+                        {uses_label(max_uses)}
+                    </option>
+                }).collect_view()}
+            </select>
+            <select
+                class=select_class
+                data-test-id="group-size"
+                on:change=move |ev| {
+                    let group_size = event_target_value(&ev).parse::<u32>().ok();
+                    choose(&|chosen| chosen.group_size = group_size);
+                }
+            >
+                <option value="" selected=move || policy.get().group_size.is_none()>"just the two of you"</option>
+                {group::GROUP_SIZE_CHOICES.into_iter().map(|size| view! {
+                    <option value=size.to_string() selected=move || policy.get().group_size == Some(size)>
+                        {format!("a group of up to {}", size)}
+                    </option>
+                }).collect_view()}
+            </select>
+            <select
+                class=select_class
+                data-test-id="require-approval"
+                on:change=move |ev| {
+                    let require_approval = event_target_value(&ev) == "approve";
+                    choose(&|chosen| chosen.require_approval = require_approval);
+                }
+            >
+                <option value="open" selected=move || !policy.get().require_approval>"anyone with it can join"</option>
+                <option value="approve" selected=move || policy.get().require_approval>"I approve who joins"</option>
+            </select>
+            <select
+                class=select_class
+                data-test-id="join-by-code"
+                on:change=move |ev| {
+                    let join_by_code = event_target_value(&ev) == "code";
+                    choose(&|chosen| chosen.join_by_code = join_by_code);
+                }
+            >
+                <option value="link" selected=move || !policy.get().join_by_code>"by link, end-to-end encrypted"</option>
+                <option value="code" selected=move || policy.get().join_by_code>"by join code, not encrypted"</option>
+            </select>
+        </div>
+    }
+}
+
+// The policy a pending invite was made with and how many uses it has left
+#[component]
+pub fn InvitePolicySummary(connection: Connection) -> impl IntoView {
+    let lifetime = describe_lifetime(connection.expires_at - connection.created_at);
+    let (current, set_current) = signal(connection.clone());
+
+    // Saved connections don't keep the use count, so ask the server
+    let link_id = connection.link_id.clone();
+    spawn_local(async move {
+        if let Ok(fresh) = connection_utils::get_connection_by_link_id(&link_id, &RetryPolicy::polling()).await {
+            set_current.update(|current| {
+                current.uses = fresh.uses;
+                current.players = fresh.players;
+                current.max_uses = fresh.max_uses.or(current.max_uses);
+            });
+        }
+    });
+
+    view! {
+        <div class="mt-2 text-sm text-gray-400" data-test-id="invite-policy-summary">
+            {move || {
+                let current = current.get();
+                let uses = match (current.max_uses, remaining_uses(&current)) {
+                    (Some(1), Some(1)) => "single use".to_string(),
+                    (Some(max), Some(left)) => format!("{} of {} uses left", left, max),
+                    _ => "unlimited uses".to_string(),
+                };
+                format!("Link made to last {}, {}.", lifetime, uses)
+            }}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_component::{test_connection, ConnectionStatus};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn connection(players: usize, uses: u32, max_uses: Option<u32>) -> Connection {
+        Connection {
+            players: (0..players).map(|index| format!("player-{}", index)).collect(),
+            expires_at: 86400,
+            max_uses,
+            uses,
+            ..test_connection("c1", ConnectionStatus::Pending)
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_policy_round_trips_through_storage() {
+        let chosen = InvitePolicy { expiry: InviteExpiry::Week, max_uses: None, require_approval: true, group_size: Some(5), join_by_code: true };
+        save_policy(&chosen);
+        assert_eq!(load_policy(), chosen);
+
+        save_policy(&InvitePolicy::default());
+        assert_eq!(load_policy(), InvitePolicy::default());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_describe_lifetime() {
+        assert_eq!(describe_lifetime(3600), "1 hour");
+        assert_eq!(describe_lifetime(7 * 86400), "1 week");
+        assert_eq!(describe_lifetime(3 * 86400), "3 days");
+        assert_eq!(describe_lifetime(36 * 3600), "36 hours");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_remaining_uses() {
+        assert_eq!(remaining_uses(&connection(1, 0, Some(1))), Some(1));
+        assert_eq!(remaining_uses(&connection(2, 0, Some(1))), Some(0));
+        assert_eq!(remaining_uses(&connection(1, 3, Some(5))), Some(2));
+        assert_eq!(remaining_uses(&connection(4, 0, None)), None);
+    }
+}
@@ -0,0 +1,300 @@
+use gloo_timers::future::TimeoutFuture;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{window, HtmlInputElement, ShareData};
+
+use crate::browser::{console_log, storage};
+use crate::toast;
+
+// localStorage key for the user's own invite message templates
+const TEMPLATES_KEY: &str = "invite-templates";
+// How long "Copied" stays on the copy button
+const COPIED_MS: u32 = 2000;
+
+// What we say when sending an invite by share sheet, email or text message.
+// `{name}` is who we're inviting and `{link}` the invite link.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InviteTemplates {
+    pub message: String,
+    pub subject: String,
+}
+
+impl Default for InviteTemplates {
+    fn default() -> Self {
+        InviteTemplates {
+            message: "Hi {name}! Let's connect on Friends Connect: {link}".to_string(),
+            subject: "Let's connect on Friends Connect".to_string(),
+        }
+    }
+}
+
+pub fn load_templates() -> InviteTemplates {
+    storage()
+        .and_then(|storage| storage.get_item(TEMPLATES_KEY).ok().flatten())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_templates(templates: &InviteTemplates) {
+    let Some(storage) = storage() else {
+        return;
+    };
+    if *templates == InviteTemplates::default() {
+        let _ = storage.remove_item(TEMPLATES_KEY);
+    } else if let Ok(json) = serde_json::to_string(templates) {
+        let _ = storage.set_item(TEMPLATES_KEY, &json);
+    }
+}
+
+fn fill_name(template: &str, name: &str) -> String {
+    let name = name.trim();
+    template
+        .replace("{name}", if name.is_empty() { "there" } else { name })
+        .trim()
+        .to_string()
+}
+
+// Fill in a template; the link is always included, even if the template
+// lost its `{link}` while being edited
+pub fn fill_template(template: &str, name: &str, link: &str) -> String {
+    let filled = fill_name(template, name);
+
+    if filled.contains("{link}") {
+        filled.replace("{link}", link)
+    } else if filled.is_empty() {
+        link.to_string()
+    } else {
+        format!("{} {}", filled, link)
+    }
+}
+
+pub fn mailto_url(subject: &str, body: &str) -> String {
+    format!(
+        "mailto:?subject={}&body={}",
+        js_sys::encode_uri_component(subject),
+        js_sys::encode_uri_component(body)
+    )
+}
+
+// `sms:?&body=` is the form both Android and iOS accept
+pub fn sms_url(body: &str) -> String {
+    format!("sms:?&body={}", js_sys::encode_uri_component(body))
+}
+
+// The async Clipboard API only exists in secure contexts (HTTPS or localhost)
+async fn copy_to_clipboard(text: &str) -> Result<(), JsValue> {
+    let navigator = window().ok_or_else(|| JsValue::from_str("No window"))?.navigator();
+    let clipboard = js_sys::Reflect::get(&navigator, &JsValue::from_str("clipboard"))?;
+    if clipboard.is_undefined() {
+        return Err(JsValue::from_str("Clipboard not available"));
+    }
+    JsFuture::from(navigator.clipboard().write_text(text)).await?;
+    Ok(())
+}
+
+pub fn can_web_share() -> bool {
+    window()
+        .map(|window| js_sys::Reflect::has(&window.navigator(), &JsValue::from_str("share")).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+async fn web_share(title: &str, text: &str) -> Result<(), JsValue> {
+    let navigator = window().ok_or_else(|| JsValue::from_str("No window"))?.navigator();
+    let data = ShareData::new();
+    data.set_title(title);
+    data.set_text(text);
+    JsFuture::from(navigator.share_with_data(&data)).await?;
+    Ok(())
+}
+
+// Copies text to the clipboard and says so. Where the Clipboard API isn't
+// available, selects `fallback_input` so the user can copy it themselves.
+#[component]
+pub fn CopyButton(
+    #[prop(into)] text: Signal<Option<String>>,
+    #[prop(optional)] fallback_input: Option<NodeRef<leptos::html::Input>>,
+) -> impl IntoView {
+    let (copied, set_copied) = signal(false);
+    let (hint, set_hint) = signal(String::new());
+
+    let handle_copy = move |_| {
+        let Some(text) = text.get() else {
+            return;
+        };
+        spawn_local(async move {
+            match copy_to_clipboard(&text).await {
+                Ok(()) => {
+                    set_hint.set(String::new());
+                    set_copied.set(true);
+                    TimeoutFuture::new(COPIED_MS).await;
+                    set_copied.set(false);
+                },
+                Err(e) => {
+                    console_log(&format!("Couldn't copy: {:?}", e));
+                    if let Some(input) = fallback_input.and_then(|node| node.get()) {
+                        let input: HtmlInputElement = input;
+                        input.select();
+                    }
+                    set_hint.set("Press Ctrl+C to copy".to_string());
+                }
+            }
+        });
+    };
+
+    view! {
+        <button
+            class="px-3 py-2 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200 whitespace-nowrap"
+            prop:disabled=move || text.get().is_none()
+            title=move || hint.get()
+            data-test-id="copy-link"
+            on:click=handle_copy
+        >
+            {move || if copied.get() {
+                "Copied ✓".to_string()
+            } else if !hint.get().is_empty() {
+                hint.get()
+            } else {
+                "Copy".to_string()
+            }}
+        </button>
+    }
+}
+
+// Ways to send an invite link: the system share sheet where there is one,
+// otherwise email or text message, with a message the user can edit
+#[component]
+pub fn ShareInvite(
+    #[prop(into)] url: Signal<String>,
+    // Who the invite is for, filled in for `{name}`
+    #[prop(into)] friend_name: Signal<String>,
+) -> impl IntoView {
+    let saved = load_templates();
+    let (message_template, set_message_template) = signal(saved.message);
+    let (subject_template, set_subject_template) = signal(saved.subject);
+    let (editing, set_editing) = signal(false);
+    let toasts = toast::use_toasts();
+
+    let message = move || fill_template(&message_template.get(), &friend_name.get(), &url.get());
+    let subject = move || fill_name(&subject_template.get(), &friend_name.get());
+
+    let remember = move || {
+        save_templates(&InviteTemplates {
+            message: message_template.get_untracked(),
+            subject: subject_template.get_untracked(),
+        });
+    };
+
+    let handle_share = move |_| {
+        let (title, text) = (subject(), message());
+        spawn_local(async move {
+            match web_share(&title, &text).await {
+                Ok(()) => {},
+                // Closing the share sheet rejects with AbortError; that's not a failure
+                Err(e) if format!("{:?}", e).contains("AbortError") => {},
+                Err(e) => toasts.error(format!("Couldn't share: {:?}", e)),
+            }
+        });
+    };
+
+    let reset = move |_| {
+        let defaults = InviteTemplates::default();
+        set_message_template.set(defaults.message);
+        set_subject_template.set(defaults.subject);
+        remember();
+    };
+
+    let link_button = "px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200";
+
+    view! {
+        <div class="mt-3" data-test-id="share-invite">
+            <div class="flex flex-wrap gap-2">
+                {can_web_share().then(|| view! {
+                    <button
+                        class="px-3 py-1 bg-indigo-600 hover:bg-indigo-700 rounded text-sm text-gray-100"
+                        on:click=handle_share
+                    >
+                        "Share…"
+                    </button>
+                })}
+                <a class=link_button href=move || mailto_url(&subject(), &message())>"Email"</a>
+                <a class=link_button href=move || sms_url(&message())>"Text message"</a>
+                <button class=link_button on:click=move |_| set_editing.update(|shown| *shown = !*shown)>
+                    {move || if editing.get() { "Done" } else { "Edit message" }}
+                </button>
+            </div>
+            {move || editing.get().then(|| view! {
+                <div class="mt-2" data-test-id="invite-templates">
+                    <input
+                        type="text"
+                        class="w-full px-3 py-1 rounded bg-gray-900 border border-gray-700 text-sm text-gray-100"
+                        placeholder="Email subject"
+                        prop:value=subject_template
+                        on:input=move |ev| {
+                            set_subject_template.set(event_target_value(&ev));
+                            remember();
+                        }
+                    />
+                    <textarea
+                        class="w-full mt-2 px-3 py-1 rounded bg-gray-900 border border-gray-700 text-sm text-gray-100"
+                        rows="3"
+                        prop:value=message_template
+                        on:input=move |ev| {
+                            set_message_template.set(event_target_value(&ev));
+                            remember();
+                        }
+                    ></textarea>
+                    <div class="flex justify-between items-center text-xs text-gray-400">
+                        <span>"{name} is who you're inviting, {link} the invite link."</span>
+                        <button class="underline" on:click=reset>"Reset"</button>
+                    </div>
+                    <div class="mt-1 text-sm text-gray-300">{message}</div>
+                </div>
+            })}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    const LINK: &str = "http://64.181.233.1/?link=abc#key=xyz";
+
+    #[wasm_bindgen_test]
+    fn test_fill_template() {
+        let defaults = InviteTemplates::default();
+        assert_eq!(
+            fill_template(&defaults.message, "Sam", LINK),
+            format!("Hi Sam! Let's connect on Friends Connect: {}", LINK)
+        );
+        assert!(fill_template(&defaults.message, "  ", LINK).starts_with("Hi there!"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_fill_template_always_includes_link() {
+        assert_eq!(fill_template("Come join", "Sam", LINK), format!("Come join {}", LINK));
+        assert_eq!(fill_template("", "Sam", LINK), LINK);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_fallback_urls_are_encoded() {
+        assert_eq!(mailto_url("Hi & bye", "a b"), "mailto:?subject=Hi%20%26%20bye&body=a%20b");
+        assert_eq!(sms_url(LINK), format!("sms:?&body={}", js_sys::encode_uri_component(LINK)));
+        assert!(!sms_url(LINK).contains('#'));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_templates_round_trip_through_storage() {
+        let edited = InviteTemplates { message: "Yo {name} {link}".to_string(), subject: "Hey".to_string() };
+        save_templates(&edited);
+        assert_eq!(load_templates(), edited);
+
+        save_templates(&InviteTemplates::default());
+        assert_eq!(load_templates(), InviteTemplates::default());
+    }
+}
@@ -0,0 +1,300 @@
+use leptos::prelude::*;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::api_retry::RetryPolicy;
+use crate::browser::{console_log, storage};
+use crate::connect_component::get_stored_player_id;
+use crate::connection_utils;
+use crate::e2e;
+use crate::invite_share::CopyButton;
+use crate::toast;
+use crate::webcrypto;
+
+// Join codes are a short stand-in for a link id that can be read out or
+// typed: 8 Crockford base32 symbols (40 random bits) and a check symbol,
+// shown as "ABC-DEF-GHJ". The server maps codes to link ids.
+
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+// Crockford's check symbols: the alphabet plus five extras for values 32..37
+const CHECK_SYMBOLS: &[u8; 37] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ*~$=U";
+const DATA_SYMBOLS: usize = 8;
+const CODE_SYMBOLS: usize = DATA_SYMBOLS + 1;
+// Symbols per group when displayed
+const GROUP: usize = 3;
+// localStorage prefix for the code we made for one of our links
+const CODE_PREFIX: &str = "join-code-";
+// Fresh codes to try if the server says one is taken
+const REGISTER_ATTEMPTS: usize = 3;
+
+fn symbol_value(symbol: u8) -> Option<u64> {
+    ALPHABET.iter().position(|s| *s == symbol).map(|value| value as u64)
+}
+
+// Uppercase, drop separators and undo the usual misreadings (O for 0, I and L for 1)
+fn normalize(input: &str) -> String {
+    input
+        .chars()
+        .filter(|c| !matches!(c, '-' | ' ' | '\t'))
+        .map(|c| match c.to_ascii_uppercase() {
+            'O' => '0',
+            'I' | 'L' => '1',
+            other => other,
+        })
+        .collect()
+}
+
+fn format_code(symbols: &str) -> String {
+    symbols
+        .as_bytes()
+        .chunks(GROUP)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// The join code for a 40-bit value
+pub fn encode(value: u64) -> String {
+    let value = value & ((1 << (5 * DATA_SYMBOLS)) - 1);
+    let mut symbols: String = (0..DATA_SYMBOLS)
+        .rev()
+        .map(|index| ALPHABET[((value >> (5 * index)) & 31) as usize] as char)
+        .collect();
+    symbols.push(CHECK_SYMBOLS[(value % 37) as usize] as char);
+    format_code(&symbols)
+}
+
+// Check a typed code and return it in canonical form, catching typos before
+// we ask the server about it
+pub fn parse(input: &str) -> Result<String, String> {
+    let symbols = normalize(input);
+    if symbols.is_empty() {
+        return Err("Enter a join code.".to_string());
+    }
+    if symbols.chars().count() != CODE_SYMBOLS {
+        return Err(format!("Join codes are {} characters, like ABC-DEF-GHJ.", CODE_SYMBOLS));
+    }
+
+    let bytes = symbols.as_bytes();
+    let mut value = 0u64;
+    for symbol in &bytes[..DATA_SYMBOLS] {
+        let digit = symbol_value(*symbol)
+            .ok_or_else(|| format!("'{}' can't appear in a join code.", *symbol as char))?;
+        value = (value << 5) | digit;
+    }
+
+    let check = CHECK_SYMBOLS
+        .iter()
+        .position(|s| *s == bytes[DATA_SYMBOLS])
+        .ok_or_else(|| format!("'{}' can't appear in a join code.", bytes[DATA_SYMBOLS] as char))?;
+    if check as u64 != value % 37 {
+        return Err("That code has a typo. Check it and try again.".to_string());
+    }
+
+    Ok(format_code(&symbols))
+}
+
+// Whether some pasted text is meant to be a join code rather than a link
+pub fn looks_like_join_code(input: &str) -> bool {
+    normalize(input).chars().count() == CODE_SYMBOLS
+}
+
+// A random code. Values whose check symbol would be one of Crockford's five
+// extras are skipped, so every code we hand out is plain letters and digits.
+pub fn generate() -> Result<String, JsValue> {
+    loop {
+        let bytes = webcrypto::random_bytes(5)?;
+        let value = bytes.iter().fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+        if value % 37 < 32 {
+            return Ok(encode(value));
+        }
+    }
+}
+
+// The code we made for one of our links, if any
+pub fn code_for_link(link_id: &str) -> Option<String> {
+    storage()?.get_item(&format!("{}{}", CODE_PREFIX, link_id)).ok()?
+}
+
+// Drop the code we kept for one of our links
+pub fn forget_code_for_link(link_id: &str) {
+    if let Some(storage) = storage() {
+        let _ = storage.remove_item(&format!("{}{}", CODE_PREFIX, link_id));
+    }
+}
+
+// Make and register a code for a link. A code can't carry the end-to-end key
+// the way an invite link's fragment does, so only links made without a key get
+// one; a keyed link may already have been shared, and its key is never dropped.
+pub async fn create_code(link_id: &str, player_id: &str) -> Result<String, JsValue> {
+    if let Some(existing) = code_for_link(link_id) {
+        return Ok(existing);
+    }
+    if e2e::has_connection_key(link_id) {
+        return Err(JsValue::from_str(
+            "This link is end-to-end encrypted, so it can't have a join code. Pick \"by join code\" for a new link.",
+        ));
+    }
+
+    for _ in 0..REGISTER_ATTEMPTS {
+        let code = generate()?;
+        if connection_utils::register_join_code(&code, link_id, player_id, &RetryPolicy::user_action()).await? {
+            if let Some(storage) = storage() {
+                let _ = storage.set_item(&format!("{}{}", CODE_PREFIX, link_id), &code);
+            }
+            console_log(&format!("Registered join code for link {}", link_id));
+            return Ok(code);
+        }
+    }
+
+    Err(JsValue::from_str("Couldn't find a free join code. Please try again."))
+}
+
+// Look up the link id for a typed code
+pub async fn resolve_code(input: &str) -> Result<String, String> {
+    let code = parse(input)?;
+    connection_utils::resolve_join_code(&code, &RetryPolicy::user_action())
+        .await
+        .map_err(|e| e.as_string().unwrap_or_else(|| format!("{:?}", e)))
+}
+
+// A join code for a link we made without a key, offered in place of the invite
+// link for friends who'd rather type something short. Encrypted links show nothing.
+#[component]
+pub fn JoinCodeOffer(#[prop(into)] link_id: Signal<String>) -> impl IntoView {
+    let (code, set_code) = signal(code_for_link(&link_id.get_untracked()));
+    let (busy, set_busy) = signal(false);
+    let toasts = toast::use_toasts();
+
+    let handle_create = move |_| {
+        let Some(player_id) = get_stored_player_id() else {
+            toasts.error("No player ID found");
+            return;
+        };
+        let link_id = link_id.get_untracked();
+        set_busy.set(true);
+        spawn_local(async move {
+            match create_code(&link_id, &player_id).await {
+                Ok(created) => set_code.set(Some(created)),
+                Err(e) => toasts.error(format!("Couldn't make a join code: {}", e.as_string().unwrap_or_else(|| format!("{:?}", e)))),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    view! {
+        <div class="mt-3" data-test-id="join-code-offer">
+            {move || match code.get() {
+                _ if e2e::has_connection_key(&link_id.get()) => ().into_any(),
+                Some(created) => view! {
+                    <div class="flex gap-2 items-center">
+                        <span class="flex-1 px-4 py-2 rounded bg-gray-900 border border-gray-700 font-mono text-lg tracking-widest" data-test-id="join-code">
+                            {created.clone()}
+                        </span>
+                        <CopyButton text=Signal::derive(move || Some(created.clone())) />
+                    </div>
+                    <div class="mt-1 text-sm text-gray-400">
+                        "Your friend can enter this code under \"Join a friend\". Chat on this connection isn't end-to-end encrypted."
+                    </div>
+                }.into_any(),
+                None => view! {
+                    <button
+                        class="px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200"
+                        prop:disabled=busy
+                        on:click=handle_create
+                    >
+                        {move || if busy.get() { "Making a code…" } else { "Use a join code instead" }}
+                    </button>
+                    <div class="mt-1 text-xs text-gray-400">
+                        "A code is easier to read out than the link. Chat on this connection isn't end-to-end encrypted."
+                    </div>
+                }.into_any(),
+            }}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_encode_and_parse_round_trip() {
+        for value in [0u64, 1, 31, 32, 0x12_3456_789a, (1 << 40) - 1] {
+            let code = encode(value);
+            assert_eq!(code.len(), CODE_SYMBOLS + 2);
+            assert_eq!(parse(&code), Ok(code.clone()));
+        }
+        assert_eq!(encode(0), "000-000-000");
+        assert_eq!(encode(1), "000-000-011");
+        // Check values from 32 use Crockford's extra symbols
+        assert_eq!(encode(32), "000-000-10*");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_forgives_case_separators_and_lookalikes() {
+        let code = encode(0x12_3456_789a);
+        let sloppy = code.to_lowercase().replace('-', " ").replace('1', "l").replace('0', "o");
+        assert_eq!(parse(&sloppy), Ok(code));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_catches_typos() {
+        let code = encode(0x0a_bcde_f012).replace('-', "");
+        let symbols: Vec<char> = code.chars().collect();
+
+        // Every single-symbol substitution
+        for index in 0..symbols.len() {
+            for replacement in ALPHABET.iter().map(|s| *s as char) {
+                if replacement != symbols[index] {
+                    let mut typo = symbols.clone();
+                    typo[index] = replacement;
+                    assert!(parse(&typo.iter().collect::<String>()).is_err());
+                }
+            }
+        }
+
+        // Every swap of neighbouring symbols
+        for index in 0..symbols.len() - 1 {
+            if symbols[index] != symbols[index + 1] {
+                let mut swapped = symbols.clone();
+                swapped.swap(index, index + 1);
+                assert!(parse(&swapped.iter().collect::<String>()).is_err());
+            }
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_explains_bad_input() {
+        assert!(parse("").unwrap_err().contains("Enter"));
+        assert!(parse("ABC-DEF").unwrap_err().contains("9 characters"));
+        assert!(parse("ABC-DEF-GU!").unwrap_err().contains("can't appear"));
+        assert!(looks_like_join_code("abc def ghj"));
+        assert!(!looks_like_join_code("https://example.com/?link=abc"));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_encrypted_links_keep_their_key() {
+        let link_id = format!("test-link-{}", uuid::Uuid::new_v4());
+        e2e::create_connection_secret(&link_id).unwrap();
+
+        // A friend may already have the key from the link, so no code and no dropped key
+        assert!(create_code(&link_id, "player").await.is_err());
+        assert!(e2e::has_connection_key(&link_id));
+        assert!(code_for_link(&link_id).is_none());
+
+        e2e::remove_connection_secret(&link_id);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_generated_codes_use_plain_symbols() {
+        for _ in 0..50 {
+            let code = generate().unwrap();
+            assert!(parse(&code).is_ok());
+            assert!(code.replace('-', "").bytes().all(|symbol| ALPHABET.contains(&symbol)));
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use leptos::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::api_retry::RetryPolicy;
+use crate::browser::console_log;
+use crate::connect_component::{get_stored_player_id, Connection, ConnectionStatus};
+use crate::connection_utils;
+use crate::invite_policy;
+use crate::join_code;
+use crate::profile::{self, Friend, ProfileCard};
+use crate::qr_scan::{self, ScannedInvite};
+
+// What someone pasted or typed to join a friend
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinTarget {
+    // An invite link or a bare link id
+    Link(ScannedInvite),
+    // A join code, in canonical form
+    Code(String),
+}
+
+// An invite looked up before joining it
+#[derive(Debug, Clone)]
+struct InvitePreview {
+    invite: ScannedInvite,
+    connection: Connection,
+    inviter: Option<Friend>,
+}
+
+fn is_link_id(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// Work out whether the input is an invite link, a join code or a bare link id
+pub fn parse_join_input(input: &str) -> Result<JoinTarget, String> {
+    let text = input.trim();
+    if text.is_empty() {
+        return Err("Paste an invite link, or type a join code.".to_string());
+    }
+
+    if text.contains("link=") || text.contains("://") {
+        return qr_scan::invite_from_text(text)
+            .map(JoinTarget::Link)
+            .map_err(|_| "That link isn't a Friends Connect invite.".to_string());
+    }
+
+    // Link ids are longer than a code, so anything code-sized is read as one
+    if join_code::looks_like_join_code(text) {
+        return join_code::parse(text).map(JoinTarget::Code);
+    }
+
+    if is_link_id(text) {
+        return Ok(JoinTarget::Link(ScannedInvite { link_id: text.to_string(), secret: None }));
+    }
+
+    Err("That doesn't look like an invite link or join code.".to_string())
+}
+
+// Find the connection behind an invite, and who sent it
+async fn preview_invite(target: JoinTarget) -> Result<InvitePreview, String> {
+    let invite = match target {
+        JoinTarget::Link(invite) => invite,
+        JoinTarget::Code(code) => ScannedInvite { link_id: join_code::resolve_code(&code).await?, secret: None },
+    };
+    qr_scan::check_invite(&invite)?;
+
+    let connection = connection_utils::get_connection_by_link_id(&invite.link_id, &RetryPolicy::user_action())
+        .await
+        .map_err(|e| match e.as_string() {
+            Some(message) if message == "Connection not found" => "No invite with that link. Check it and try again.".to_string(),
+            Some(message) => message,
+            None => format!("{:?}", e),
+        })?;
+    let inviter = profile::inviter(&invite.link_id).await;
+
+    Ok(InvitePreview { invite, connection, inviter })
+}
+
+// Why an invite can't be joined, if it can't
+fn join_blocker(connection: &Connection) -> Option<&'static str> {
+    let me = get_stored_player_id().unwrap_or_default();
+    if connection.players.contains(&me) {
+        return Some("You're already on this connection.");
+    }
+    match connection.status {
+        ConnectionStatus::Expired => Some("This invite has expired. Ask your friend for a new one."),
+        _ if connection.is_group() && connection.players.len() as u32 >= connection.max_players => {
+            Some("This group is full. Ask your friend to make room or start a new one.")
+        },
+        _ if !connection.is_group() && connection.players.len() as u32 >= connection.max_players => {
+            Some("This invite has already been used. Ask your friend for a new one.")
+        },
+        // A reusable link stays open after the first friend joins
+        _ if invite_policy::remaining_uses(connection) == Some(0) => {
+            Some("This invite has already been used. Ask your friend for a new one.")
+        },
+        _ => None,
+    }
+}
+
+// Join by pasting an invite link or link id, or typing a join code. The invite
+// is looked up and shown first, then joined without reloading the page.
+#[component]
+pub fn JoinFriend(
+    // Called with the link id once the invite is in the address bar
+    on_join: Callback<String>,
+) -> impl IntoView {
+    let (input, set_input) = signal(String::new());
+    let (busy, set_busy) = signal(false);
+    let (error, set_error) = signal(String::new());
+    let (preview, set_preview) = signal(None::<InvitePreview>);
+
+    // Typos in a join code are caught as soon as the whole code is typed
+    let typo = move || {
+        let typed = input.get();
+        if !join_code::looks_like_join_code(&typed) || typed.contains("link=") {
+            return None;
+        }
+        join_code::parse(&typed).err()
+    };
+
+    let handle_look_up = move || {
+        if busy.get_untracked() {
+            return;
+        }
+        let target = match parse_join_input(&input.get_untracked()) {
+            Ok(target) => target,
+            Err(e) => {
+                set_error.set(e);
+                return;
+            }
+        };
+        set_busy.set(true);
+        set_error.set(String::new());
+        set_preview.set(None);
+        spawn_local(async move {
+            match preview_invite(target).await {
+                Ok(found) => set_preview.set(Some(found)),
+                Err(e) => set_error.set(e),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let handle_join = move |_| {
+        let Some(found) = preview.get_untracked() else {
+            return;
+        };
+        let invite = found.invite;
+        if let Err(e) = qr_scan::accept_invite(&invite) {
+            set_error.set(e);
+            return;
+        }
+        console_log(&format!("Joining link {} from the join panel", invite.link_id));
+        set_input.set(String::new());
+        set_preview.set(None);
+        on_join.run(invite.link_id);
+    };
+
+    view! {
+        <div class="bg-gray-800 border border-gray-700 rounded p-4 mb-4" data-test-id="join-friend-panel">
+            <div class="font-medium">"Join a friend"</div>
+            <div class="text-sm text-gray-400">
+                "Paste the invite link your friend sent, or type their join code, like ABC-DEF-GHJ."
+            </div>
+            <div class="flex gap-2 mt-3">
+                <input
+                    type="text"
+                    class="w-full px-4 py-2 rounded bg-gray-900 border border-gray-700 text-gray-100"
+                    placeholder="Invite link or join code"
+                    autocomplete="off"
+                    spellcheck="false"
+                    prop:value=input
+                    prop:disabled=busy
+                    on:input=move |ev| {
+                        set_input.set(event_target_value(&ev));
+                        set_error.set(String::new());
+                        set_preview.set(None);
+                    }
+                    on:keydown=move |ev: web_sys::KeyboardEvent| {
+                        if ev.key() == "Enter" {
+                            handle_look_up();
+                        }
+                    }
+                />
+                <button
+                    class="px-4 py-2 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-gray-200 whitespace-nowrap"
+                    prop:disabled=move || busy.get() || input.get().trim().is_empty() || typo().is_some()
+                    on:click=move |_| handle_look_up()
+                >
+                    {move || if busy.get() { "Looking up…" } else { "Look up" }}
+                </button>
+            </div>
+            {move || typo().or_else(|| Some(error.get()).filter(|e| !e.is_empty())).map(|message| view! {
+                <div class="mt-2 text-sm text-red-400" data-test-id="join-friend-error">{message}</div>
+            })}
+            {move || preview.get().map(|found| {
+                let blocker = join_blocker(&found.connection);
+                view! {
+                    <div class="mt-3 p-3 rounded bg-gray-900 border border-gray-700" data-test-id="join-preview">
+                        <div class="text-xs text-gray-400 mb-2">"Invited by"</div>
+                        {match found.inviter {
+                            Some(friend) => view! {
+                                <ProfileCard player_id=friend.player_id profile=friend.profile.unwrap_or_default() />
+                            }.into_any(),
+                            None => view! {
+                                <div class="text-sm text-gray-300">"A friend who hasn't set up a profile"</div>
+                            }.into_any(),
+                        }}
+                        {(found.invite.secret.is_none()).then(|| view! {
+                            <div class="mt-2 text-xs text-gray-400">
+                                "This invite has no encryption key, so chat on it will be marked \"Not encrypted\"."
+                            </div>
+                        })}
+                        {match blocker {
+                            Some(reason) => view! {
+                                <div class="mt-2 text-sm text-yellow-300">{reason}</div>
+                            }.into_any(),
+                            None => view! {
+                                <button
+                                    class="mt-3 px-4 py-2 bg-indigo-600 hover:bg-indigo-700 rounded text-gray-100"
+                                    on:click=handle_join
+                                >
+                                    "Join"
+                                </button>
+                            }.into_any(),
+                        }}
+                    </div>
+                }
+            })}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_component::test_connection;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_parse_full_and_relative_links() {
+        let target = parse_join_input(" http://64.181.233.1/?link=abc123#key=s3cr3t ").unwrap();
+        assert_eq!(
+            target,
+            JoinTarget::Link(ScannedInvite { link_id: "abc123".to_string(), secret: Some("s3cr3t".to_string()) })
+        );
+        assert!(matches!(parse_join_input("?link=relative"), Ok(JoinTarget::Link(invite)) if invite.link_id == "relative"));
+        assert!(parse_join_input("https://example.com/?other=1").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_bare_link_id() {
+        assert_eq!(
+            parse_join_input("a1b2c3d4-e5f6-7890"),
+            Ok(JoinTarget::Link(ScannedInvite { link_id: "a1b2c3d4-e5f6-7890".to_string(), secret: None }))
+        );
+        assert!(parse_join_input("not a link").is_err());
+        assert!(parse_join_input("   ").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_join_code() {
+        let code = join_code::encode(0x12_3456_789a);
+        assert_eq!(parse_join_input(&code.to_lowercase()), Ok(JoinTarget::Code(code.clone())));
+
+        let mut typo = code.into_bytes();
+        typo[0] = if typo[0] == b'1' { b'2' } else { b'1' };
+        assert!(parse_join_input(&String::from_utf8(typo).unwrap()).unwrap_err().contains("typo"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_join_blocker() {
+        let mut connection = Connection {
+            players: vec!["someone-else".to_string()],
+            ..test_connection("c1", ConnectionStatus::Pending)
+        };
+        assert_eq!(join_blocker(&connection), None);
+
+        connection.players.push("friend".to_string());
+        assert!(join_blocker(&connection).unwrap().contains("already been used"));
+
+        // A group link can be used again until the group fills up
+        connection.max_uses = Some(5);
+        connection.max_players = 5;
+        connection.status = ConnectionStatus::Active;
+        assert_eq!(join_blocker(&connection), None);
+
+        connection.max_players = 2;
+        assert!(join_blocker(&connection).unwrap().contains("already been used"));
+        connection.max_players = 3;
+        connection.players.push("another".to_string());
+        assert!(join_blocker(&connection).unwrap().contains("full"));
+
+        connection.status = ConnectionStatus::Expired;
+        assert!(join_blocker(&connection).unwrap().contains("expired"));
+    }
+}
@@ -0,0 +1,98 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{window, CryptoKey, IdbDatabase, IdbObjectStore, IdbRequest, IdbTransactionMode};
+
+// IndexedDB can hold a CryptoKey itself, so a key generated as non-extractable
+// survives reloads without its private half ever being readable by scripts.
+// localStorage only holds strings, which would mean exporting the key.
+const DB_NAME: &str = "friends-connect";
+const DB_VERSION: u32 = 1;
+const STORE: &str = "keys";
+
+// Settle a promise with the request's result once it succeeds or fails
+fn finished(request: &IdbRequest) -> js_sys::Promise {
+    let request = request.clone();
+    js_sys::Promise::new(&mut |resolve, reject| {
+        let succeeded = request.clone();
+        let on_success = Closure::once_into_js(move || {
+            let _ = resolve.call1(&JsValue::NULL, &succeeded.result().unwrap_or(JsValue::UNDEFINED));
+        });
+        let failed = request.clone();
+        let on_error = Closure::once_into_js(move || {
+            let error = failed.error().ok().flatten().map(JsValue::from)
+                .unwrap_or_else(|| JsValue::from_str("IndexedDB request failed"));
+            let _ = reject.call1(&JsValue::NULL, &error);
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    })
+}
+
+async fn open() -> Result<IdbDatabase, JsValue> {
+    let factory = window()
+        .ok_or_else(|| JsValue::from_str("No window found"))?
+        .indexed_db()?
+        .ok_or_else(|| JsValue::from_str("IndexedDB not available"))?;
+    let request = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+
+    let upgrading = request.clone();
+    let on_upgrade = Closure::once_into_js(move || {
+        if let Ok(db) = upgrading.result().and_then(|db| db.dyn_into::<IdbDatabase>()) {
+            let _ = db.create_object_store(STORE);
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+
+    JsFuture::from(finished(&request)).await?.dyn_into()
+}
+
+fn store(db: &IdbDatabase, mode: IdbTransactionMode) -> Result<IdbObjectStore, JsValue> {
+    db.transaction_with_str_and_mode(STORE, mode)?.object_store(STORE)
+}
+
+pub async fn save_key(name: &str, key: &CryptoKey) -> Result<(), JsValue> {
+    let db = open().await?;
+    let request = store(&db, IdbTransactionMode::Readwrite)?.put_with_key(key, &JsValue::from_str(name))?;
+    JsFuture::from(finished(&request)).await?;
+    Ok(())
+}
+
+pub async fn load_key(name: &str) -> Result<Option<CryptoKey>, JsValue> {
+    let db = open().await?;
+    let request = store(&db, IdbTransactionMode::Readonly)?.get(&JsValue::from_str(name))?;
+    let found = JsFuture::from(finished(&request)).await?;
+    Ok(found.dyn_into::<CryptoKey>().ok())
+}
+
+pub async fn delete_key(name: &str) -> Result<(), JsValue> {
+    let db = open().await?;
+    let request = store(&db, IdbTransactionMode::Readwrite)?.delete(&JsValue::from_str(name))?;
+    JsFuture::from(finished(&request)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webcrypto;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    async fn test_keys_round_trip_without_being_extractable() {
+        let key = webcrypto::await_key(webcrypto::subtle().unwrap().generate_key_with_object(
+            &webcrypto::params(&[("name", JsValue::from_str("AES-GCM")), ("length", JsValue::from(256))]).unwrap(),
+            false,
+            &webcrypto::usages(&["encrypt"]),
+        )).await.unwrap();
+
+        save_key("key-store-test", &key).await.unwrap();
+        let loaded = load_key("key-store-test").await.unwrap().expect("key should be stored");
+        assert!(!loaded.extractable());
+
+        delete_key("key-store-test").await.unwrap();
+        assert!(load_key("key-store-test").await.unwrap().is_none());
+    }
+}
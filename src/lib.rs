@@ -6,6 +6,41 @@ use connect_component::FriendsConnect;
 pub mod connection_modal;
 pub mod connection_item;
 pub mod connection_utils;
+pub mod api_retry;
+pub mod browser;
+pub mod outbox;
+pub mod pwa;
+pub mod notifications;
+pub mod webcrypto;
+pub mod identity;
+pub mod key_store;
+pub mod identity_settings;
+pub mod e2e;
+pub mod chat;
+pub mod verification;
+pub mod verify_friend;
+pub mod profile;
+pub mod profile_settings;
+pub mod identicon;
+pub mod qr;
+pub mod qr_decode;
+pub mod qr_scan;
+pub mod invite_share;
+pub mod join_code;
+pub mod join_friend;
+pub mod invite_policy;
+pub mod expiry;
+pub mod approval;
+pub mod group;
+pub mod blocklist;
+pub mod rename;
+pub mod notes;
+pub mod connection_list;
+pub mod tags;
+pub mod archive;
+pub mod undo;
+pub mod toast;
+use pwa::UpdatePrompt;
 
 #[cfg(test)]
 mod connection_utils_test;
@@ -13,6 +48,7 @@ mod connection_utils_test;
 #[component]
 pub fn App() -> impl IntoView {
     view! { 
+        <UpdatePrompt />
         <FriendsConnect />
     }
 }
@@ -0,0 +1,78 @@
+use leptos::prelude::*;
+
+use crate::connection_utils;
+
+// Saved-connection field holding the private note about a connection
+const NOTE_FIELD: &str = "note";
+// Longest note we keep
+pub const MAX_NOTE_CHARS: usize = 1000;
+
+pub fn load_note(connection_id: &str) -> String {
+    connection_utils::get_saved_connection(connection_id)
+        .and_then(|record| record.get(NOTE_FIELD).and_then(|v| v.as_str()).map(|note| note.to_string()))
+        .unwrap_or_default()
+}
+
+// Store a note, or clear it when it's blank. Notes never leave this device.
+pub fn save_note(connection_id: &str, note: &str) {
+    let note: String = note.trim().chars().take(MAX_NOTE_CHARS).collect();
+    connection_utils::update_saved_connection(connection_id, |record| {
+        if note.is_empty() {
+            record.remove(NOTE_FIELD);
+        } else {
+            record.insert(NOTE_FIELD.to_string(), serde_json::Value::String(note));
+        }
+    });
+}
+
+// A private note about a connection, saved whenever the text area loses focus
+#[component]
+pub fn ConnectionNote(connection_id: String) -> impl IntoView {
+    let (note, set_note) = signal(load_note(&connection_id));
+    let (saved, set_saved) = signal(false);
+    let connection_id = StoredValue::new(connection_id);
+
+    view! {
+        <div data-test-id="connection-note">
+            <div class="text-sm font-medium text-gray-200 mb-1">"Private note"</div>
+            <textarea
+                class="w-full px-3 py-2 rounded bg-gray-900 border border-gray-700 text-sm text-gray-100"
+                rows="2"
+                placeholder="Only you can see this"
+                maxlength=MAX_NOTE_CHARS.to_string()
+                prop:value=note
+                on:input=move |ev| {
+                    set_note.set(event_target_value(&ev));
+                    set_saved.set(false);
+                }
+                on:blur=move |_| {
+                    save_note(&connection_id.get_value(), &note.get_untracked());
+                    set_saved.set(true);
+                }
+            ></textarea>
+            {move || saved.get().then(|| view! { <div class="text-xs text-gray-500">"Saved"</div> })}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_component::{test_connection, ConnectionStatus};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_note_round_trips_through_saved_connection() {
+        let connection = test_connection(&format!("notes-test-{}", uuid::Uuid::new_v4()), ConnectionStatus::Active);
+        connection_utils::save_connection_to_local_storage(&connection, "Friend").unwrap();
+        assert_eq!(load_note(&connection.id), "");
+
+        save_note(&connection.id, "  Met at the climbing gym  ");
+        assert_eq!(load_note(&connection.id), "Met at the climbing gym");
+
+        save_note(&connection.id, "   ");
+        assert_eq!(load_note(&connection.id), "");
+    }
+}
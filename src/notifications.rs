@@ -0,0 +1,250 @@
+use leptos::prelude::*;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{window, Notification, NotificationOptions, NotificationPermission};
+
+use crate::browser::{console_log, storage};
+use crate::connect_component::Connection;
+
+// localStorage keys for the notification settings
+const ENABLED_KEY: &str = "notifications-enabled";
+const MUTED_KEY: &str = "muted-connections";
+
+// Base document title; unread events are shown as "(3) Connect with Friends"
+pub const APP_TITLE: &str = "Connect with Friends";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotificationKind {
+    FriendJoined,
+    NewMessage,
+    // Someone asked to join one of our invites that needs approval
+    JoinRequest,
+    // Our request to join was accepted or declined
+    RequestAnswered,
+    // One of our invites is about to lapse unanswered
+    InviteExpiring,
+    Other,
+}
+
+// A server notification, matched up with the saved connection it is about
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationEvent {
+    pub kind: NotificationKind,
+    pub connection_id: Option<String>,
+    pub text: String,
+}
+
+// Bumps the unread badge in the tab title. Provided by the app so parts of it
+// other than the notification poll, such as an open chat, can count events too.
+#[derive(Clone, Copy)]
+pub struct UnreadBadge(pub WriteSignal<usize>);
+
+impl UnreadBadge {
+    pub fn bump(&self) {
+        self.0.update(|count| *count += 1);
+    }
+}
+
+// Whether the user is looking at another tab or window
+pub fn tab_hidden() -> bool {
+    window()
+        .and_then(|w| w.document())
+        .map(|d| d.hidden())
+        .unwrap_or(false)
+}
+
+// The server sends plain strings; work out what they are about from their
+// wording and from whichever connection or link id they mention
+pub fn classify_notification(text: &str, connections: &[Connection]) -> NotificationEvent {
+    let lower = text.to_lowercase();
+    let kind = if lower.contains("message") {
+        NotificationKind::NewMessage
+    } else if lower.contains("requested") || lower.contains("wants to join") {
+        NotificationKind::JoinRequest
+    } else if lower.contains("accepted") || lower.contains("declined") {
+        NotificationKind::RequestAnswered
+    } else if lower.contains("joined") || lower.contains("connected") {
+        NotificationKind::FriendJoined
+    } else {
+        NotificationKind::Other
+    };
+
+    let connection_id = connections
+        .iter()
+        .find(|c| text.contains(&c.id) || (!c.link_id.is_empty() && text.contains(&c.link_id)))
+        .map(|c| c.id.clone());
+
+    NotificationEvent {
+        kind,
+        connection_id,
+        text: text.to_string(),
+    }
+}
+
+// Document title with an unread badge
+pub fn title_with_badge(unread: usize) -> String {
+    if unread == 0 {
+        APP_TITLE.to_string()
+    } else {
+        format!("({}) {}", unread, APP_TITLE)
+    }
+}
+
+// The Notification API is missing on some mobile browsers and in insecure contexts
+pub fn is_supported() -> bool {
+    window()
+        .map(|w| js_sys::Reflect::has(&w, &JsValue::from_str("Notification")).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+pub fn permission_granted() -> bool {
+    is_supported() && Notification::permission() == NotificationPermission::Granted
+}
+
+pub fn permission_denied() -> bool {
+    is_supported() && Notification::permission() == NotificationPermission::Denied
+}
+
+// Ask the browser for permission; resolves to whether it was granted
+pub async fn request_permission() -> Result<bool, JsValue> {
+    if !is_supported() {
+        return Ok(false);
+    }
+
+    let result = JsFuture::from(Notification::request_permission()?).await?;
+    Ok(result.as_string().as_deref() == Some("granted"))
+}
+
+// Whether the user opted in to desktop notifications
+pub fn notifications_enabled() -> bool {
+    storage()
+        .and_then(|s| s.get_item(ENABLED_KEY).ok().flatten())
+        .map(|v| v == "true")
+        .unwrap_or(false)
+}
+
+pub fn set_notifications_enabled(enabled: bool) {
+    if let Some(storage) = storage() {
+        let _ = storage.set_item(ENABLED_KEY, if enabled { "true" } else { "false" });
+    }
+}
+
+// Connections the user doesn't want desktop notifications for
+pub fn load_muted_connections() -> Vec<String> {
+    storage()
+        .and_then(|s| s.get_item(MUTED_KEY).ok().flatten())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn is_muted(connection_id: &str) -> bool {
+    load_muted_connections().iter().any(|id| id == connection_id)
+}
+
+pub fn set_muted(connection_id: &str, muted: bool) {
+    let mut muted_ids = load_muted_connections();
+    muted_ids.retain(|id| id != connection_id);
+    if muted {
+        muted_ids.push(connection_id.to_string());
+    }
+
+    if let (Some(storage), Ok(json)) = (storage(), serde_json::to_string(&muted_ids)) {
+        let _ = storage.set_item(MUTED_KEY, &json);
+    }
+}
+
+// Whether an event should become a desktop notification
+pub fn should_notify(event: &NotificationEvent) -> bool {
+    if !notifications_enabled() || !permission_granted() {
+        return false;
+    }
+
+    match &event.connection_id {
+        Some(id) => !is_muted(id),
+        None => true,
+    }
+}
+
+// Show a desktop notification; clicking it focuses the tab and runs `on_click`
+pub fn show_notification(title: &str, event: &NotificationEvent, on_click: impl Fn() + 'static) {
+    let options = NotificationOptions::new();
+    options.set_body(&event.text);
+    options.set_icon("icon.svg");
+    // Collapse repeated events for the same connection into one notification
+    if let Some(id) = &event.connection_id {
+        options.set_tag(&format!("connection-{}", id));
+    }
+
+    let notification = match Notification::new_with_options(title, &options) {
+        Ok(n) => n,
+        Err(e) => {
+            console_log(&format!("Failed to show notification: {:?}", e));
+            return;
+        }
+    };
+
+    let clicked = notification.clone();
+    let handler = Closure::<dyn Fn()>::new(move || {
+        if let Some(window) = web_sys::window() {
+            let _ = window.focus();
+        }
+        clicked.close();
+        on_click();
+    });
+    notification.set_onclick(Some(handler.as_ref().unchecked_ref()));
+    handler.forget();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_component::{test_connection, ConnectionStatus};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn connection(id: &str, link_id: &str) -> Connection {
+        Connection { link_id: link_id.to_string(), ..test_connection(id, ConnectionStatus::Pending) }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_classify_notification_matches_connection() {
+        let connections = vec![connection("conn-1", "link-1"), connection("conn-2", "link-2")];
+
+        let joined = classify_notification("Player joined connection conn-2", &connections);
+        assert_eq!(joined.kind, NotificationKind::FriendJoined);
+        assert_eq!(joined.connection_id.as_deref(), Some("conn-2"));
+
+        let message = classify_notification("New message in link-1", &connections);
+        assert_eq!(message.kind, NotificationKind::NewMessage);
+        assert_eq!(message.connection_id.as_deref(), Some("conn-1"));
+
+        let request = classify_notification("Player requested to join connection conn-1", &connections);
+        assert_eq!(request.kind, NotificationKind::JoinRequest);
+        assert_eq!(request.connection_id.as_deref(), Some("conn-1"));
+
+        let answered = classify_notification("Your request for link-2 was accepted", &connections);
+        assert_eq!(answered.kind, NotificationKind::RequestAnswered);
+        assert_eq!(answered.connection_id.as_deref(), Some("conn-2"));
+
+        let other = classify_notification("Server maintenance tonight", &connections);
+        assert_eq!(other.kind, NotificationKind::Other);
+        assert_eq!(other.connection_id, None);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_title_with_badge() {
+        assert_eq!(title_with_badge(0), "Connect with Friends");
+        assert_eq!(title_with_badge(3), "(3) Connect with Friends");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_mute_round_trip() {
+        set_muted("conn-mute-test", true);
+        assert!(is_muted("conn-mute-test"));
+
+        set_muted("conn-mute-test", false);
+        assert!(!is_muted("conn-mute-test"));
+    }
+}
@@ -0,0 +1,272 @@
+use std::cell::Cell;
+
+use serde::{Serialize, Deserialize};
+use web_sys::window;
+
+use crate::api_retry::{ApiError, RetryPolicy};
+use crate::browser::console_log;
+use crate::connect_component::{Connection, ConnectionStatus};
+use crate::connection_utils;
+use crate::approval;
+
+// localStorage key holding the queued actions, oldest first
+const OUTBOX_KEY: &str = "outbox";
+
+// Something the user did that still has to reach the server
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OutboxAction {
+    SendMessage { connection_id: String, content: String },
+    Join { link_id: String, friendly_name: String },
+    Leave { connection_id: String },
+    Rename { connection_id: String, name: String },
+}
+
+impl OutboxAction {
+    // The saved connection this action belongs to, if it already exists locally
+    pub fn connection_id(&self) -> Option<&str> {
+        match self {
+            OutboxAction::SendMessage { connection_id, .. }
+            | OutboxAction::Leave { connection_id }
+            | OutboxAction::Rename { connection_id, .. } => Some(connection_id),
+            OutboxAction::Join { .. } => None,
+        }
+    }
+
+    // Short human readable description for pending/error messages
+    pub fn describe(&self) -> String {
+        match self {
+            OutboxAction::SendMessage { .. } => "send message".to_string(),
+            OutboxAction::Join { friendly_name, .. } => format!("join {}", friendly_name),
+            OutboxAction::Leave { .. } => "leave connection".to_string(),
+            OutboxAction::Rename { name, .. } => format!("rename to {}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OutboxEntry {
+    // Client-generated id, sent along with the request so the server can deduplicate replays
+    pub id: String,
+    pub player_id: String,
+    pub action: OutboxAction,
+    pub queued_at: i64,
+}
+
+// What happened to an entry when the outbox was replayed
+#[derive(Debug, Clone)]
+pub enum ReplayOutcome {
+    // The server accepted it; joins hand back the connection they created
+    Delivered { entry: OutboxEntry, connection: Option<Connection> },
+    // The server refused it, so it was dropped from the outbox
+    Rejected { entry: OutboxEntry, error: String },
+}
+
+thread_local! {
+    // Guards against two replays (e.g. "online" event and the timer) running at once
+    static REPLAYING: Cell<bool> = const { Cell::new(false) };
+}
+
+// Whether the browser thinks it has network access
+pub fn is_online() -> bool {
+    window()
+        .map(|w| w.navigator().on_line())
+        .unwrap_or(true)
+}
+
+// Load queued actions from local storage, oldest first
+pub fn load_outbox() -> Vec<OutboxEntry> {
+    let storage = match window().and_then(|w| w.local_storage().ok().flatten()) {
+        Some(s) => s,
+        None => return Vec::new(),
+    };
+
+    match storage.get_item(OUTBOX_KEY) {
+        Ok(Some(json)) => serde_json::from_str(&json).unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+fn store_outbox(entries: &[OutboxEntry]) -> Option<()> {
+    let storage = window()?.local_storage().ok()??;
+
+    if entries.is_empty() {
+        storage.remove_item(OUTBOX_KEY).ok()?;
+    } else {
+        let json = serde_json::to_string(entries).ok()?;
+        storage.set_item(OUTBOX_KEY, &json).ok()?;
+    }
+
+    Some(())
+}
+
+// Queue an action to be sent once the backend is reachable again
+pub fn enqueue(player_id: &str, action: OutboxAction) -> OutboxEntry {
+    let entry = OutboxEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        player_id: player_id.to_string(),
+        action,
+        queued_at: js_sys::Date::now() as i64,
+    };
+
+    console_log(&format!("Queued \"{}\" in outbox ({})", entry.action.describe(), entry.id));
+
+    let mut entries = load_outbox();
+    entries.push(entry.clone());
+    let _ = store_outbox(&entries);
+
+    entry
+}
+
+// Drop a single entry, e.g. once it has been delivered
+pub fn remove_from_outbox(entry_id: &str) {
+    let mut entries = load_outbox();
+    entries.retain(|e| e.id != entry_id);
+    let _ = store_outbox(&entries);
+}
+
+// Queued actions that belong to a saved connection
+pub fn pending_for_connection(entries: &[OutboxEntry], connection_id: &str) -> usize {
+    entries
+        .iter()
+        .filter(|e| e.action.connection_id() == Some(connection_id))
+        .count()
+}
+
+// Send one entry, reusing its id so the server can recognise a duplicate
+async fn deliver(entry: &OutboxEntry, policy: &RetryPolicy) -> Result<Option<Connection>, ApiError> {
+    match &entry.action {
+        OutboxAction::SendMessage { connection_id, content } => {
+            connection_utils::send_message_with_id(connection_id, &entry.player_id, content, &entry.id, policy).await?;
+            Ok(None)
+        },
+        OutboxAction::Join { link_id, friendly_name } => {
+            let mut connection = connection_utils::join_connection_with_id(link_id, &entry.player_id, &entry.id, policy).await?;
+
+            if connection.status != ConnectionStatus::Requested && connection.players.len() >= 2 {
+                connection.status = ConnectionStatus::Active;
+            }
+
+            // Save it the same way a live join does
+            if let Some(window) = window() {
+                if let Ok(Some(storage)) = window.local_storage() {
+                    let _ = storage.set_item(&format!("conn-name-{}", connection.id), friendly_name);
+                }
+            }
+            let _ = connection_utils::save_connection_to_local_storage(&connection, friendly_name);
+            if connection.status == ConnectionStatus::Requested {
+                approval::set_awaiting(&connection.id, true);
+            }
+
+            Ok(Some(connection))
+        },
+        OutboxAction::Leave { connection_id } => {
+            connection_utils::leave_connection(connection_id, &entry.player_id, &entry.id, policy).await?;
+            Ok(None)
+        },
+        OutboxAction::Rename { connection_id, name } => {
+            connection_utils::rename_connection(connection_id, &entry.player_id, name, &entry.id, policy).await?;
+            Ok(None)
+        },
+    }
+}
+
+// Replay queued actions in the order they were made.
+//
+// Stops at the first failure that could go away on its own (no network, rate
+// limiting, a server error) and leaves that entry and everything after it
+// queued, so a later message can never overtake an earlier one. Entries the
+// server rejects outright are dropped and reported.
+pub async fn replay_outbox() -> Vec<ReplayOutcome> {
+    if REPLAYING.with(|r| r.replace(true)) {
+        return Vec::new();
+    }
+
+    let policy = RetryPolicy::polling();
+    let mut outcomes = Vec::new();
+
+    for entry in load_outbox() {
+        match deliver(&entry, &policy).await {
+            Ok(connection) => {
+                console_log(&format!("Delivered queued \"{}\"", entry.action.describe()));
+                remove_from_outbox(&entry.id);
+                outcomes.push(ReplayOutcome::Delivered { entry, connection });
+            },
+            Err(e) if e.is_retryable() => {
+                console_log(&format!("Backend not ready ({}), keeping the rest of the outbox", e.message()));
+                break;
+            },
+            Err(e) => {
+                let error = e.message();
+                console_log(&format!("Dropping queued \"{}\": {}", entry.action.describe(), error));
+                remove_from_outbox(&entry.id);
+                outcomes.push(ReplayOutcome::Rejected { entry, error });
+            }
+        }
+    }
+
+    REPLAYING.with(|r| r.set(false));
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn clear_outbox() {
+        let storage = window().unwrap().local_storage().unwrap().unwrap();
+        storage.remove_item(OUTBOX_KEY).unwrap();
+    }
+
+    #[wasm_bindgen_test]
+    fn test_outbox_keeps_insertion_order() {
+        clear_outbox();
+
+        let first = enqueue("player-1", OutboxAction::SendMessage {
+            connection_id: "conn-1".to_string(),
+            content: "hello".to_string(),
+        });
+        let second = enqueue("player-1", OutboxAction::Leave {
+            connection_id: "conn-1".to_string(),
+        });
+
+        let entries = load_outbox();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], first);
+        assert_eq!(entries[1], second);
+        assert_ne!(first.id, second.id, "Every entry needs its own client id");
+
+        remove_from_outbox(&first.id);
+        assert_eq!(load_outbox(), vec![second]);
+
+        clear_outbox();
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pending_for_connection_ignores_joins_and_other_connections() {
+        clear_outbox();
+
+        enqueue("player-1", OutboxAction::Rename {
+            connection_id: "conn-1".to_string(),
+            name: "Sam".to_string(),
+        });
+        enqueue("player-1", OutboxAction::SendMessage {
+            connection_id: "conn-2".to_string(),
+            content: "hi".to_string(),
+        });
+        enqueue("player-1", OutboxAction::Join {
+            link_id: "link-1".to_string(),
+            friendly_name: "Alex".to_string(),
+        });
+
+        let entries = load_outbox();
+        assert_eq!(pending_for_connection(&entries, "conn-1"), 1);
+        assert_eq!(pending_for_connection(&entries, "conn-2"), 1);
+        assert_eq!(pending_for_connection(&entries, "conn-3"), 0);
+
+        clear_outbox();
+    }
+}
@@ -0,0 +1,247 @@
+use base64::Engine;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::api_retry::RetryPolicy;
+use crate::browser::{console_log, storage};
+use crate::connect_component::{get_stored_player_id, Connection};
+use crate::connection_utils;
+use crate::identicon;
+
+// localStorage key for our own profile
+const PROFILE_KEY: &str = "player-profile";
+// Saved connection fields caching who the friend is and their profile
+const FRIEND_ID_FIELD: &str = "friend_player_id";
+const FRIEND_PROFILE_FIELD: &str = "friend_profile";
+
+// Limits, also applied to profiles we receive from friends
+pub const MAX_DISPLAY_NAME_CHARS: usize = 40;
+pub const MAX_STATUS_CHARS: usize = 80;
+pub const MAX_AVATAR_BYTES: usize = 48 * 1024;
+
+// Image types accepted as avatars
+const AVATAR_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+// What we share about ourselves with friends
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerProfile {
+    pub display_name: String,
+    // A small image as a `data:` URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<String>,
+    #[serde(default)]
+    pub status: String,
+}
+
+// The other player on a connection
+#[derive(Debug, Clone, PartialEq)]
+pub struct Friend {
+    pub player_id: String,
+    pub profile: Option<PlayerProfile>,
+}
+
+impl PlayerProfile {
+    pub fn is_empty(&self) -> bool {
+        self.display_name.is_empty() && self.avatar.is_none() && self.status.is_empty()
+    }
+}
+
+// Only inline images of a known type are allowed, so a friend's avatar can't
+// point us at a tracking URL
+fn is_valid_avatar(avatar: &str) -> bool {
+    avatar.len() <= MAX_AVATAR_BYTES * 4 / 3 + 64
+        && AVATAR_TYPES
+            .iter()
+            .any(|mime| avatar.starts_with(&format!("data:{};base64,", mime)))
+}
+
+// Trim a profile and check it against the limits
+pub fn normalize_profile(profile: PlayerProfile) -> Result<PlayerProfile, String> {
+    let display_name = profile.display_name.trim().to_string();
+    let status = profile.status.trim().to_string();
+
+    if display_name.chars().count() > MAX_DISPLAY_NAME_CHARS {
+        return Err(format!("Display name can be at most {} characters.", MAX_DISPLAY_NAME_CHARS));
+    }
+    if status.chars().count() > MAX_STATUS_CHARS {
+        return Err(format!("Status can be at most {} characters.", MAX_STATUS_CHARS));
+    }
+    if profile.avatar.as_deref().is_some_and(|avatar| !is_valid_avatar(avatar)) {
+        return Err("Avatar must be a PNG, JPEG, GIF or WebP image under 48 KB.".to_string());
+    }
+
+    Ok(PlayerProfile { display_name, avatar: profile.avatar, status })
+}
+
+// Turn an uploaded image into an avatar data URL
+pub fn avatar_data_url(mime_type: &str, bytes: &[u8]) -> Result<String, String> {
+    if !AVATAR_TYPES.contains(&mime_type) {
+        return Err("Avatar must be a PNG, JPEG, GIF or WebP image.".to_string());
+    }
+    if bytes.len() > MAX_AVATAR_BYTES {
+        return Err("Avatar must be under 48 KB.".to_string());
+    }
+
+    Ok(format!(
+        "data:{};base64,{}",
+        mime_type,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+pub fn load_profile() -> Option<PlayerProfile> {
+    let json = storage()?.get_item(PROFILE_KEY).ok()??;
+    serde_json::from_str::<PlayerProfile>(&json)
+        .ok()
+        .filter(|profile| !profile.is_empty())
+}
+
+pub fn save_profile(profile: PlayerProfile) -> Result<PlayerProfile, String> {
+    let profile = normalize_profile(profile)?;
+    let storage = storage().ok_or_else(|| "localStorage not available".to_string())?;
+    let json = serde_json::to_string(&profile).map_err(|e| e.to_string())?;
+    storage.set_item(PROFILE_KEY, &json).map_err(|e| format!("{:?}", e))?;
+    Ok(profile)
+}
+
+// The friend's profile as last seen, cached with the saved connection
+pub fn friend_profile(connection_id: &str) -> Option<PlayerProfile> {
+    connection_utils::get_saved_connection(connection_id)?
+        .get(FRIEND_PROFILE_FIELD)
+        .cloned()
+        .and_then(|value| serde_json::from_value(value).ok())
+}
+
+fn store_friend(connection_id: &str, friend: &Friend) {
+    let profile = friend.profile.as_ref().and_then(|profile| serde_json::to_value(profile).ok());
+    connection_utils::update_saved_connection(connection_id, |record| {
+        record.insert(FRIEND_ID_FIELD.to_string(), serde_json::Value::String(friend.player_id.clone()));
+        match profile {
+            Some(profile) => record.insert(FRIEND_PROFILE_FIELD.to_string(), profile),
+            None => record.remove(FRIEND_PROFILE_FIELD),
+        };
+    });
+}
+
+// The friend on a connection as last seen, cached with the saved connection
+pub fn cached_friend(connection_id: &str) -> Option<Friend> {
+    let record = connection_utils::get_saved_connection(connection_id)?;
+    let player_id = record.get(FRIEND_ID_FIELD)?.as_str()?.to_string();
+    Some(Friend { player_id, profile: friend_profile(connection_id) })
+}
+
+// Find the first player on a connection other than us, with their profile
+async fn fetch_other_player(link_id: &str) -> Option<Friend> {
+    let me = get_stored_player_id().unwrap_or_default();
+    let policy = RetryPolicy::polling();
+
+    let connection = connection_utils::get_connection_by_link_id(link_id, &policy).await.ok()?;
+    let player_id = connection.players.into_iter().find(|player| *player != me)?;
+
+    let profile = match connection_utils::get_player_profile(&player_id, &policy).await {
+        Ok(profile) => profile.and_then(|profile| normalize_profile(profile).ok()),
+        Err(e) => {
+            console_log(&format!("Couldn't load profile for {}: {:?}", player_id, e));
+            None
+        }
+    };
+
+    Some(Friend { player_id, profile })
+}
+
+// Refresh what we know about the friend on a connection
+pub async fn refresh_friend(connection: &Connection) -> Option<Friend> {
+    let friend = fetch_other_player(&connection.link_id).await?;
+    store_friend(&connection.id, &friend);
+    Some(friend)
+}
+
+// Whoever sent us an invite link
+pub async fn inviter(link_id: &str) -> Option<Friend> {
+    fetch_other_player(link_id).await
+}
+
+// A player's own avatar if they have one, otherwise their identicon
+#[component]
+pub fn PlayerAvatar(
+    #[prop(into)] player_id: String,
+    // Their uploaded avatar, if any
+    #[prop(default = None)] image: Option<String>,
+    // Tailwind size classes
+    #[prop(default = "h-8 w-8")] size: &'static str,
+) -> impl IntoView {
+    let src = image.unwrap_or_else(|| identicon::identicon_data_url(&player_id));
+    view! {
+        <img class=format!("{} rounded-full object-cover", size) src=src alt="" data-test-id="player-avatar" />
+    }
+}
+
+// A player's avatar, display name and status line
+#[component]
+pub fn ProfileCard(#[prop(into)] player_id: String, #[prop(into)] profile: PlayerProfile) -> impl IntoView {
+    view! {
+        <div class="flex items-center gap-3" data-test-id="profile-card">
+            <PlayerAvatar player_id=player_id image=profile.avatar.clone() size="h-10 w-10" />
+            <div>
+                <div class="font-medium text-gray-100">{profile.display_name.clone()}</div>
+                {(!profile.status.is_empty()).then(|| view! {
+                    <div class="text-sm text-gray-400">{profile.status.clone()}</div>
+                })}
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_normalize_profile_trims_and_limits() {
+        let profile = normalize_profile(PlayerProfile {
+            display_name: "  Sam  ".to_string(),
+            avatar: None,
+            status: " around today ".to_string(),
+        }).unwrap();
+        assert_eq!(profile.display_name, "Sam");
+        assert_eq!(profile.status, "around today");
+
+        let too_long = PlayerProfile {
+            display_name: "x".repeat(MAX_DISPLAY_NAME_CHARS + 1),
+            ..PlayerProfile::default()
+        };
+        assert!(normalize_profile(too_long).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_avatar_must_be_inline_image() {
+        let avatar = avatar_data_url("image/png", &[1, 2, 3]).unwrap();
+        assert!(avatar.starts_with("data:image/png;base64,"));
+        assert!(normalize_profile(PlayerProfile { avatar: Some(avatar), ..PlayerProfile::default() }).is_ok());
+
+        let remote = PlayerProfile {
+            avatar: Some("https://example.com/pixel.png".to_string()),
+            ..PlayerProfile::default()
+        };
+        assert!(normalize_profile(remote).is_err());
+
+        assert!(avatar_data_url("image/svg+xml", &[1]).is_err());
+        assert!(avatar_data_url("image/png", &vec![0; MAX_AVATAR_BYTES + 1]).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_profile_round_trips_through_storage() {
+        let saved = save_profile(PlayerProfile {
+            display_name: "Robin".to_string(),
+            avatar: None,
+            status: "hi".to_string(),
+        }).unwrap();
+        assert_eq!(load_profile(), Some(saved));
+
+        save_profile(PlayerProfile::default()).unwrap();
+        assert_eq!(load_profile(), None);
+    }
+}
@@ -0,0 +1,148 @@
+use leptos::prelude::*;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::HtmlInputElement;
+
+use crate::api_retry::RetryPolicy;
+use crate::connect_component::get_stored_player_id;
+use crate::connection_utils;
+use crate::toast;
+use crate::profile::{self, PlayerProfile, ProfileCard};
+
+// Settings section for the profile we share with friends
+#[component]
+pub fn ProfileSettings() -> impl IntoView {
+    let saved = profile::load_profile().unwrap_or_default();
+    let (display_name, set_display_name) = signal(saved.display_name);
+    let (status_line, set_status_line) = signal(saved.status);
+    let (avatar, set_avatar) = signal(saved.avatar);
+    let (message, set_message) = signal(String::new());
+    let (busy, set_busy) = signal(false);
+    let toasts = toast::use_toasts();
+    let avatar_input = NodeRef::<leptos::html::Input>::new();
+
+    let handle_avatar_change = move |_| {
+        let Some(file) = avatar_input.get()
+            .and_then(|input: HtmlInputElement| input.files())
+            .and_then(|files| files.get(0))
+        else {
+            return;
+        };
+
+        spawn_local(async move {
+            let bytes = match JsFuture::from(file.array_buffer()).await {
+                Ok(buffer) => js_sys::Uint8Array::new(&buffer).to_vec(),
+                Err(e) => {
+                    set_message.set(format!("Couldn't read the image: {:?}", e));
+                    return;
+                }
+            };
+
+            match profile::avatar_data_url(&file.type_(), &bytes) {
+                Ok(data_url) => {
+                    set_avatar.set(Some(data_url));
+                    set_message.set(String::new());
+                },
+                Err(e) => set_message.set(e),
+            }
+        });
+    };
+
+    let handle_save = move |_| {
+        let edited = PlayerProfile {
+            display_name: display_name.get(),
+            avatar: avatar.get(),
+            status: status_line.get(),
+        };
+
+        let saved = match profile::save_profile(edited) {
+            Ok(saved) => saved,
+            Err(e) => {
+                set_message.set(e);
+                return;
+            }
+        };
+        set_display_name.set(saved.display_name.clone());
+        set_status_line.set(saved.status.clone());
+        set_message.set(String::new());
+
+        let Some(player_id) = get_stored_player_id() else {
+            toasts.success("Profile saved on this device.");
+            return;
+        };
+
+        set_busy.set(true);
+        spawn_local(async move {
+            match connection_utils::update_player_profile(&player_id, &saved, &RetryPolicy::user_action()).await {
+                Ok(()) => toasts.success("Profile saved and shared with your friends."),
+                Err(e) => {
+                    let error_msg = e.as_string().unwrap_or_else(|| format!("{:?}", e));
+                    toasts.warning(format!(
+                        "Profile saved on this device. Friends will see it when you next create or join a connection ({}).",
+                        error_msg
+                    ));
+                }
+            }
+            set_busy.set(false);
+        });
+    };
+
+    view! {
+        <div class="mt-4 pt-4 border-t border-gray-700" data-test-id="profile-settings">
+            <div class="font-medium">"Your profile"</div>
+            <div class="text-sm text-gray-400">"Friends see this on their list. The names you give them stay private to you."</div>
+            <div class="mt-3">
+                {move || {
+                    let preview = PlayerProfile {
+                        display_name: display_name.get(),
+                        avatar: avatar.get(),
+                        status: status_line.get(),
+                    };
+                    view! { <ProfileCard player_id=get_stored_player_id().unwrap_or_default() profile=preview /> }
+                }}
+            </div>
+            <input
+                type="text"
+                class="w-full mt-3 px-4 py-2 rounded bg-gray-900 border border-gray-700 text-gray-100"
+                placeholder="Display name"
+                maxlength=profile::MAX_DISPLAY_NAME_CHARS.to_string()
+                prop:value=display_name
+                on:input=move |ev| set_display_name.set(event_target_value(&ev))
+            />
+            <input
+                type="text"
+                class="w-full mt-2 px-4 py-2 rounded bg-gray-900 border border-gray-700 text-gray-100"
+                placeholder="Status (optional)"
+                maxlength=profile::MAX_STATUS_CHARS.to_string()
+                prop:value=status_line
+                on:input=move |ev| set_status_line.set(event_target_value(&ev))
+            />
+            <input
+                type="file"
+                accept="image/png,image/jpeg,image/gif,image/webp"
+                class="w-full mt-2 text-sm text-gray-300"
+                node_ref=avatar_input
+                on:change=handle_avatar_change
+            />
+            <div class="flex justify-end gap-2 mt-2">
+                {move || avatar.get().is_some().then(|| view! {
+                    <button
+                        class="px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200"
+                        on:click=move |_| set_avatar.set(None)
+                    >
+                        "Remove avatar"
+                    </button>
+                })}
+                <button
+                    class="px-3 py-1 bg-indigo-600 hover:bg-indigo-700 rounded text-sm text-gray-100"
+                    prop:disabled=busy
+                    on:click=handle_save
+                >
+                    "Save profile"
+                </button>
+            </div>
+            {move || (!message.get().is_empty()).then(|| view! {
+                <div class="mt-2 text-sm text-gray-300">{message.get()}</div>
+            })}
+        </div>
+    }
+}
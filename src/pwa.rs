@@ -0,0 +1,158 @@
+use std::cell::Cell;
+
+use leptos::prelude::*;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{window, ServiceWorker, ServiceWorkerRegistration, ServiceWorkerState};
+
+use crate::browser::console_log;
+
+// Service worker script copied next to index.html by Trunk
+const SERVICE_WORKER_URL: &str = "./sw.js";
+
+thread_local! {
+    // Set once the user has asked the waiting worker to take over. A first
+    // visit also changes controller (the new worker claims the page), and that
+    // must not reload the page.
+    static UPDATE_REQUESTED: Cell<bool> = const { Cell::new(false) };
+}
+
+// navigator.serviceWorker only exists in secure contexts (HTTPS or localhost)
+pub fn is_supported() -> bool {
+    window()
+        .map(|w| js_sys::Reflect::has(&w.navigator(), &JsValue::from_str("serviceWorker")).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+// Register the service worker and call `on_update_ready` once a new build has
+// been downloaded and is waiting to take over.
+pub async fn register_service_worker(on_update_ready: impl Fn() + Clone + 'static) -> Result<(), JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("No window found"))?;
+    if !is_supported() {
+        return Err(JsValue::from_str("Service workers need HTTPS or localhost"));
+    }
+    let container = window.navigator().service_worker();
+
+    let registration: ServiceWorkerRegistration =
+        JsFuture::from(container.register(SERVICE_WORKER_URL)).await?.dyn_into()?;
+    console_log(&format!("Service worker registered for scope {}", registration.scope()));
+
+    // A previous visit may already have left an update waiting
+    if registration.waiting().is_some() && container.controller().is_some() {
+        on_update_ready();
+    }
+
+    let watched_registration = registration.clone();
+    let on_update_found = Closure::<dyn Fn()>::new(move || {
+        let Some(installing) = watched_registration.installing() else {
+            return;
+        };
+
+        let worker = installing.clone();
+        let on_update_ready = on_update_ready.clone();
+        let on_state_change = Closure::<dyn Fn()>::new(move || {
+            // "installed" with an existing controller means an update, not the first install
+            let has_controller = web_sys::window()
+                .map(|w| w.navigator().service_worker().controller().is_some())
+                .unwrap_or(false);
+            if worker.state() == ServiceWorkerState::Installed && has_controller {
+                console_log("A new version of the app is ready");
+                on_update_ready();
+            }
+        });
+        installing.set_onstatechange(Some(on_state_change.as_ref().unchecked_ref()));
+        on_state_change.forget();
+    });
+    registration.set_onupdatefound(Some(on_update_found.as_ref().unchecked_ref()));
+    on_update_found.forget();
+
+    // Reload once the new worker we asked for has taken control
+    let on_controller_change = Closure::<dyn Fn()>::new(move || {
+        if !UPDATE_REQUESTED.with(|requested| requested.get()) {
+            return;
+        }
+        if let Some(window) = web_sys::window() {
+            let _ = window.location().reload();
+        }
+    });
+    container.set_oncontrollerchange(Some(on_controller_change.as_ref().unchecked_ref()));
+    on_controller_change.forget();
+
+    Ok(())
+}
+
+// Ask the waiting service worker to take over; the page reloads when it does
+pub async fn apply_update() -> Result<(), JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("No window found"))?;
+    if !is_supported() {
+        return window.location().reload();
+    }
+    let container = window.navigator().service_worker();
+
+    let registration = JsFuture::from(container.get_registration()).await?;
+    if registration.is_undefined() {
+        // Nothing to hand over to, so just load the new build directly
+        return window.location().reload();
+    }
+
+    let registration: ServiceWorkerRegistration = registration.dyn_into()?;
+    match registration.waiting() {
+        Some(waiting) => {
+            UPDATE_REQUESTED.with(|requested| requested.set(true));
+            post_skip_waiting(&waiting)
+        },
+        None => window.location().reload(),
+    }
+}
+
+fn post_skip_waiting(worker: &ServiceWorker) -> Result<(), JsValue> {
+    let message = js_sys::Object::new();
+    js_sys::Reflect::set(&message, &JsValue::from_str("type"), &JsValue::from_str("SKIP_WAITING"))?;
+    worker.post_message(&message)
+}
+
+// Registers the service worker and offers a reload when a new deployment is ready
+#[component]
+pub fn UpdatePrompt() -> impl IntoView {
+    let (update_ready, set_update_ready) = signal(false);
+
+    Effect::new(move |_| {
+        spawn_local(async move {
+            if let Err(e) = register_service_worker(move || set_update_ready.set(true)).await {
+                console_log(&format!("Service worker not available: {:?}", e));
+            }
+        });
+    });
+
+    view! {
+        {move || update_ready.get().then(|| view! {
+            <div
+                class="max-w-md mx-auto mt-4 p-3 flex justify-between items-center bg-indigo-900 text-indigo-100 rounded"
+                data-test-id="update-prompt"
+            >
+                <span>"A new version is available."</span>
+                <div class="flex gap-2">
+                    <button
+                        class="px-3 py-1 bg-gray-700 hover:bg-gray-600 rounded text-sm text-gray-200"
+                        on:click=move |_| set_update_ready.set(false)
+                    >
+                        "Later"
+                    </button>
+                    <button
+                        class="px-3 py-1 bg-indigo-600 hover:bg-indigo-700 rounded text-sm text-gray-100"
+                        on:click=move |_| {
+                            spawn_local(async move {
+                                if let Err(e) = apply_update().await {
+                                    console_log(&format!("Failed to apply update: {:?}", e));
+                                }
+                            });
+                        }
+                    >
+                        "Reload"
+                    </button>
+                </div>
+            </div>
+        })}
+    }
+}
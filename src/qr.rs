@@ -0,0 +1,143 @@
+use leptos::prelude::*;
+use qrcode::render::svg;
+use qrcode::{Color, EcLevel, QrCode};
+
+use crate::identity_settings::{download_bytes, download_text};
+use crate::toast;
+
+// Blank modules around the code; scanners need at least four
+const QUIET_ZONE: usize = 4;
+// Pixels per module in the downloaded PNG
+const PNG_SCALE: usize = 8;
+
+// Medium error correction keeps invite links (with their key fragment) small
+// enough to scan from a phone screen
+fn invite_code(url: &str) -> Result<QrCode, String> {
+    QrCode::with_error_correction_level(url.as_bytes(), EcLevel::M).map_err(|e| e.to_string())
+}
+
+// The QR code for a URL as an SVG document
+pub fn qr_svg(url: &str) -> Result<String, String> {
+    let code = invite_code(url)?;
+    Ok(code
+        .render::<svg::Color>()
+        .min_dimensions(200, 200)
+        .dark_color(svg::Color("#000000"))
+        .light_color(svg::Color("#ffffff"))
+        .build())
+}
+
+// The QR code for a URL as a greyscale PNG
+pub fn qr_png(url: &str) -> Result<Vec<u8>, String> {
+    let code = invite_code(url)?;
+    let modules = code.width();
+    let colors = code.to_colors();
+    let side = (modules + 2 * QUIET_ZONE) * PNG_SCALE;
+
+    let mut pixels = vec![255u8; side * side];
+    for (index, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let x = (index % modules + QUIET_ZONE) * PNG_SCALE;
+        let y = (index / modules + QUIET_ZONE) * PNG_SCALE;
+        for row in y..y + PNG_SCALE {
+            pixels[row * side + x..row * side + x + PNG_SCALE].fill(0);
+        }
+    }
+
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, side as u32, side as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(&pixels).map_err(|e| e.to_string())?;
+    writer.finish().map_err(|e| e.to_string())?;
+
+    Ok(bytes)
+}
+
+// A scannable invite link, with downloads for sharing it elsewhere
+#[component]
+pub fn InviteQr(#[prop(into)] url: Signal<String>) -> impl IntoView {
+    let toasts = toast::use_toasts();
+
+    let download_svg = move |_| {
+        let result = qr_svg(&url.get())
+            .and_then(|svg| download_text("friends-connect-invite.svg", &svg, "image/svg+xml").map_err(|e| format!("{:?}", e)));
+        if let Err(e) = result {
+            toasts.error(format!("Couldn't download the QR code: {}", e));
+        }
+    };
+
+    let download_png = move |_| {
+        let result = qr_png(&url.get())
+            .and_then(|png| download_bytes("friends-connect-invite.png", &png, "image/png").map_err(|e| format!("{:?}", e)));
+        if let Err(e) = result {
+            toasts.error(format!("Couldn't download the QR code: {}", e));
+        }
+    };
+
+    view! {
+        <div class="mt-3 flex flex-col items-center" data-test-id="invite-qr">
+            {move || match qr_svg(&url.get()) {
+                Ok(svg) => view! {
+                    <div class="w-48 h-48 bg-white p-2 rounded" inner_html=svg></div>
+                }.into_any(),
+                Err(e) => view! {
+                    <div class="text-sm text-red-400">{format!("Couldn't draw a QR code: {}", e)}</div>
+                }.into_any(),
+            }}
+            <div class="flex gap-2 mt-2">
+                <button
+                    class="px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200"
+                    on:click=download_png
+                >
+                    "Download PNG"
+                </button>
+                <button
+                    class="px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200"
+                    on:click=download_svg
+                >
+                    "Download SVG"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    const INVITE: &str = "http://64.181.233.1/?link=abc123#key=0123456789abcdefghijklmnopqrstuvwxyzABCDEFG";
+
+    #[wasm_bindgen_test]
+    fn test_qr_svg_renders_invite() {
+        let svg = qr_svg(INVITE).unwrap();
+        assert!(svg.contains("<svg"));
+        assert!(svg.contains("#000000"));
+        assert_eq!(svg, qr_svg(INVITE).unwrap());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_qr_png_has_quiet_zone_and_scale() {
+        let bytes = qr_png(INVITE).unwrap();
+        let decoder = png::Decoder::new(bytes.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut pixels).unwrap();
+
+        let modules = invite_code(INVITE).unwrap().width();
+        assert_eq!(frame.width as usize, (modules + 2 * QUIET_ZONE) * PNG_SCALE);
+        assert_eq!(frame.width, frame.height);
+
+        // The quiet zone is white and the top-left finder pattern starts dark
+        assert_eq!(pixels[0], 255);
+        let finder = QUIET_ZONE * PNG_SCALE;
+        assert_eq!(pixels[finder * frame.width as usize + finder], 0);
+    }
+}
@@ -0,0 +1,1256 @@
+use qrcode::canvas::is_functional;
+use qrcode::Version;
+
+// A small QR decoder for reading invite codes from screenshots and photos of
+// screens. It finds the three finder patterns, samples the module grid
+// (correcting perspective with the bottom-right alignment pattern when there
+// is one), then undoes the mask, corrects errors and parses the segments.
+
+// Error correction codewords per block, by version and level (L, M, Q, H).
+// ISO/IEC 18004, table 9.
+const EC_CODEWORDS_PER_BLOCK: [[usize; 4]; 40] = [
+    [7, 10, 13, 17], [10, 16, 22, 28], [15, 26, 18, 22], [20, 18, 26, 16], [26, 24, 18, 22],
+    [18, 16, 24, 28], [20, 18, 18, 26], [24, 22, 22, 26], [30, 22, 20, 24], [18, 26, 24, 28],
+    [20, 30, 28, 24], [24, 22, 26, 28], [26, 22, 24, 22], [30, 24, 20, 24], [22, 24, 30, 24],
+    [24, 28, 24, 30], [28, 28, 28, 28], [30, 26, 28, 28], [28, 26, 26, 26], [28, 26, 30, 28],
+    [28, 26, 28, 30], [28, 28, 30, 24], [30, 28, 30, 30], [30, 28, 30, 30], [26, 28, 30, 30],
+    [28, 28, 28, 30], [30, 28, 30, 30], [30, 28, 30, 30], [30, 28, 30, 30], [30, 28, 30, 30],
+    [30, 28, 30, 30], [30, 28, 30, 30], [30, 28, 30, 30], [30, 28, 30, 30], [30, 28, 30, 30],
+    [30, 28, 30, 30], [30, 28, 30, 30], [30, 28, 30, 30], [30, 28, 30, 30], [30, 28, 30, 30],
+];
+
+// Data codewords per block as (size, count) for the two block groups, by
+// version and level (L, M, Q, H). ISO/IEC 18004, table 9.
+type BlockGroups = (usize, usize, usize, usize);
+const DATA_BLOCKS: [[BlockGroups; 4]; 40] = [
+    [(19, 1, 0, 0), (16, 1, 0, 0), (13, 1, 0, 0), (9, 1, 0, 0)],
+    [(34, 1, 0, 0), (28, 1, 0, 0), (22, 1, 0, 0), (16, 1, 0, 0)],
+    [(55, 1, 0, 0), (44, 1, 0, 0), (17, 2, 0, 0), (13, 2, 0, 0)],
+    [(80, 1, 0, 0), (32, 2, 0, 0), (24, 2, 0, 0), (9, 4, 0, 0)],
+    [(108, 1, 0, 0), (43, 2, 0, 0), (15, 2, 16, 2), (11, 2, 12, 2)],
+    [(68, 2, 0, 0), (27, 4, 0, 0), (19, 4, 0, 0), (15, 4, 0, 0)],
+    [(78, 2, 0, 0), (31, 4, 0, 0), (14, 2, 15, 4), (13, 4, 14, 1)],
+    [(97, 2, 0, 0), (38, 2, 39, 2), (18, 4, 19, 2), (14, 4, 15, 2)],
+    [(116, 2, 0, 0), (36, 3, 37, 2), (16, 4, 17, 4), (12, 4, 13, 4)],
+    [(68, 2, 69, 2), (43, 4, 44, 1), (19, 6, 20, 2), (15, 6, 16, 2)],
+    [(81, 4, 0, 0), (50, 1, 51, 4), (22, 4, 23, 4), (12, 3, 13, 8)],
+    [(92, 2, 93, 2), (36, 6, 37, 2), (20, 4, 21, 6), (14, 7, 15, 4)],
+    [(107, 4, 0, 0), (37, 8, 38, 1), (20, 8, 21, 4), (11, 12, 12, 4)],
+    [(115, 3, 116, 1), (40, 4, 41, 5), (16, 11, 17, 5), (12, 11, 13, 5)],
+    [(87, 5, 88, 1), (41, 5, 42, 5), (24, 5, 25, 7), (12, 11, 13, 7)],
+    [(98, 5, 99, 1), (45, 7, 46, 3), (19, 15, 20, 2), (15, 3, 16, 13)],
+    [(107, 1, 108, 5), (46, 10, 47, 1), (22, 1, 23, 15), (14, 2, 15, 17)],
+    [(120, 5, 121, 1), (43, 9, 44, 4), (22, 17, 23, 1), (14, 2, 15, 19)],
+    [(113, 3, 114, 4), (44, 3, 45, 11), (21, 17, 22, 4), (13, 9, 14, 16)],
+    [(107, 3, 108, 5), (41, 3, 42, 13), (24, 15, 25, 5), (15, 15, 16, 10)],
+    [(116, 4, 117, 4), (42, 17, 0, 0), (22, 17, 23, 6), (16, 19, 17, 6)],
+    [(111, 2, 112, 7), (46, 17, 0, 0), (24, 7, 25, 16), (13, 34, 0, 0)],
+    [(121, 4, 122, 5), (47, 4, 48, 14), (24, 11, 25, 14), (15, 16, 16, 14)],
+    [(117, 6, 118, 4), (45, 6, 46, 14), (24, 11, 25, 16), (16, 30, 17, 2)],
+    [(106, 8, 107, 4), (47, 8, 48, 13), (24, 7, 25, 22), (15, 22, 16, 13)],
+    [(114, 10, 115, 2), (46, 19, 47, 4), (22, 28, 23, 6), (16, 33, 17, 4)],
+    [(122, 8, 123, 4), (45, 22, 46, 3), (23, 8, 24, 26), (15, 12, 16, 28)],
+    [(117, 3, 118, 10), (45, 3, 46, 23), (24, 4, 25, 31), (15, 11, 16, 31)],
+    [(116, 7, 117, 7), (45, 21, 46, 7), (23, 1, 24, 37), (15, 19, 16, 26)],
+    [(115, 5, 116, 10), (47, 19, 48, 10), (24, 15, 25, 25), (15, 23, 16, 25)],
+    [(115, 13, 116, 3), (46, 2, 47, 29), (24, 42, 25, 1), (15, 23, 16, 28)],
+    [(115, 17, 0, 0), (46, 10, 47, 23), (24, 10, 25, 35), (15, 19, 16, 35)],
+    [(115, 17, 116, 1), (46, 14, 47, 21), (24, 29, 25, 19), (15, 11, 16, 46)],
+    [(115, 13, 116, 6), (46, 14, 47, 23), (24, 44, 25, 7), (16, 59, 17, 1)],
+    [(121, 12, 122, 7), (47, 12, 48, 26), (24, 39, 25, 14), (15, 22, 16, 41)],
+    [(121, 6, 122, 14), (47, 6, 48, 34), (24, 46, 25, 10), (15, 2, 16, 64)],
+    [(122, 17, 123, 4), (46, 29, 47, 14), (24, 49, 25, 10), (15, 24, 16, 46)],
+    [(122, 4, 123, 18), (46, 13, 47, 32), (24, 48, 25, 14), (15, 42, 16, 32)],
+    [(117, 20, 118, 4), (47, 40, 48, 7), (24, 43, 25, 22), (15, 10, 16, 67)],
+    [(118, 19, 119, 6), (47, 18, 48, 31), (24, 34, 25, 34), (15, 20, 16, 61)],
+];
+
+const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+// Neighbourhoods with less variance than this (a standard deviation of 24
+// grey levels) are flat paper or screen, where sensor noise shouldn't count
+const FLAT_VARIANCE: f64 = 576.0;
+// How many finder pattern candidates to pair up
+const MAX_CANDIDATES: usize = 40;
+// How many finder pattern triples to try before giving up
+const MAX_TRIPLES: usize = 6;
+
+// Decode the first QR code found in an RGBA image (as from canvas `getImageData`)
+pub fn decode_rgba(rgba: &[u8], width: usize, height: usize) -> Result<String, String> {
+    if width == 0 || height == 0 || rgba.len() < width * height * 4 {
+        return Err("The image is empty.".to_string());
+    }
+
+    let luma: Vec<u8> = rgba
+        .chunks_exact(4)
+        .take(width * height)
+        .map(|px| {
+            // Transparent pixels count as white, like the page behind a PNG
+            let alpha = px[3] as u32;
+            let grey = (px[0] as u32 * 299 + px[1] as u32 * 587 + px[2] as u32 * 114) / 1000;
+            ((grey * alpha + 255 * (255 - alpha)) / 255) as u8
+        })
+        .collect();
+
+    decode_luma(&luma, width, height)
+}
+
+// Decode the first QR code found in a greyscale image
+pub fn decode_luma(luma: &[u8], width: usize, height: usize) -> Result<String, String> {
+    if width == 0 || height == 0 || luma.len() < width * height {
+        return Err("The image is empty.".to_string());
+    }
+
+    let image = BitImage::binarize(luma, width, height);
+    let triples = image.finder_triples();
+    if triples.is_empty() {
+        return Err("No QR code found in the image.".to_string());
+    }
+
+    let mut last_error = String::new();
+    for triple in triples.iter().take(MAX_TRIPLES) {
+        match image.decode_triple(triple) {
+            Ok(text) => return Ok(text),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(format!("Found a QR code but couldn't read it: {}", last_error))
+}
+
+//------------------------------------------------------------------------------
+// Binarization
+
+struct BitImage {
+    width: usize,
+    height: usize,
+    dark: Vec<bool>,
+}
+
+// Otsu's method: the threshold that best separates the histogram in two
+fn otsu_threshold(luma: &[u8]) -> u8 {
+    let mut histogram = [0u64; 256];
+    for value in luma {
+        histogram[*value as usize] += 1;
+    }
+
+    let total = luma.len() as f64;
+    let sum_all: f64 = histogram.iter().enumerate().map(|(v, n)| v as f64 * *n as f64).sum();
+    let (mut weight_below, mut sum_below) = (0.0, 0.0);
+    let (mut best, mut best_variance) = (128u8, -1.0);
+
+    for (value, count) in histogram.iter().enumerate() {
+        weight_below += *count as f64;
+        if weight_below == 0.0 {
+            continue;
+        }
+        let weight_above = total - weight_below;
+        if weight_above == 0.0 {
+            break;
+        }
+        sum_below += value as f64 * *count as f64;
+        let mean_below = sum_below / weight_below;
+        let mean_above = (sum_all - sum_below) / weight_above;
+        let variance = weight_below * weight_above * (mean_below - mean_above).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            // Pixels at or below `value` are dark
+            best = value as u8;
+        }
+    }
+    best
+}
+
+impl BitImage {
+    // Threshold each pixel against its neighbourhood so uneven lighting in
+    // photos doesn't wash out half the code; flat areas use a global threshold
+    fn binarize(luma: &[u8], width: usize, height: usize) -> Self {
+        let global = otsu_threshold(&luma[..width * height]);
+
+        let stride = width + 1;
+        let mut sums = vec![0u64; stride * (height + 1)];
+        let mut squares = vec![0u64; stride * (height + 1)];
+        for y in 0..height {
+            let (mut row_sum, mut row_squares) = (0u64, 0u64);
+            for x in 0..width {
+                let value = luma[y * width + x] as u64;
+                row_sum += value;
+                row_squares += value * value;
+                sums[(y + 1) * stride + x + 1] = sums[y * stride + x + 1] + row_sum;
+                squares[(y + 1) * stride + x + 1] = squares[y * stride + x + 1] + row_squares;
+            }
+        }
+
+        let radius = (width.min(height) / 12).max(8);
+        let mut dark = vec![false; width * height];
+        for y in 0..height {
+            let (top, bottom) = (y.saturating_sub(radius), (y + radius + 1).min(height));
+            for x in 0..width {
+                let (left, right) = (x.saturating_sub(radius), (x + radius + 1).min(width));
+                let area = ((bottom - top) * (right - left)) as f64;
+                let window = |table: &[u64]| {
+                    (table[bottom * stride + right] + table[top * stride + left]) as f64
+                        - (table[top * stride + right] + table[bottom * stride + left]) as f64
+                };
+                let mean = window(&sums) / area;
+                let variance = window(&squares) / area - mean * mean;
+
+                let value = luma[y * width + x];
+                dark[y * width + x] = if variance < FLAT_VARIANCE {
+                    value <= global
+                } else {
+                    (value as f64) < mean
+                };
+            }
+        }
+
+        BitImage { width, height, dark }
+    }
+
+    fn is_dark(&self, x: i64, y: i64) -> bool {
+        x >= 0
+            && y >= 0
+            && (x as usize) < self.width
+            && (y as usize) < self.height
+            && self.dark[y as usize * self.width + x as usize]
+    }
+
+    fn sample(&self, point: (f64, f64)) -> bool {
+        self.is_dark(point.0.floor() as i64, point.1.floor() as i64)
+    }
+}
+
+//------------------------------------------------------------------------------
+// Finder patterns
+
+#[derive(Debug, Clone, Copy)]
+struct FinderCandidate {
+    x: f64,
+    y: f64,
+    module_size: f64,
+    hits: usize,
+}
+
+// Whether five run lengths look like a finder pattern's 1:1:3:1:1
+fn is_finder_ratio(counts: &[usize; 5]) -> bool {
+    let total: usize = counts.iter().sum();
+    if total < 7 {
+        return false;
+    }
+    let module = total as f64 / 7.0;
+    let tolerance = module * 0.7;
+    [1.0, 1.0, 3.0, 1.0, 1.0]
+        .iter()
+        .zip(counts)
+        .all(|(ratio, count)| (module * ratio - *count as f64).abs() < tolerance * ratio)
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+impl BitImage {
+    // Measure the pattern through (x, y) along (dx, dy); returns the centre's
+    // offset from (x, y) along that direction and the pattern's total length
+    fn cross_check(&self, x: f64, y: f64, dx: i64, dy: i64, max_total: usize) -> Option<(f64, usize)> {
+        let (x0, y0) = (x.floor() as i64, y.floor() as i64);
+        if !self.is_dark(x0, y0) {
+            return None;
+        }
+
+        let run = |mut step: i64, direction: i64, want_dark: bool, limit: usize| {
+            let mut count = 0;
+            while count <= limit && self.is_dark(x0 + dx * step, y0 + dy * step) == want_dark {
+                count += 1;
+                step += direction;
+            }
+            (count, step)
+        };
+
+        let (centre_back, step) = run(0, -1, true, max_total);
+        let (light_back, step) = run(step, -1, false, max_total);
+        let (dark_back, _) = run(step, -1, true, max_total);
+        let (centre_fwd, step) = run(1, 1, true, max_total);
+        let (light_fwd, step) = run(step, 1, false, max_total);
+        let (dark_fwd, _) = run(step, 1, true, max_total);
+        let counts = [dark_back, light_back, centre_back + centre_fwd, light_fwd, dark_fwd];
+
+        let total: usize = counts.iter().sum();
+        if total > max_total || !is_finder_ratio(&counts) {
+            return None;
+        }
+
+        // The centre run covers offsets -(centre_back - 1) ..= centre_fwd
+        let offset = (centre_fwd as f64 - centre_back as f64 + 1.0) / 2.0;
+        Some((offset, total))
+    }
+
+    // Centroid and area of the dark blob containing (x, y), or None if it
+    // reaches further than `reach` pixels, i.e. it isn't an isolated blob
+    fn blob_centroid(&self, x: f64, y: f64, reach: f64) -> Option<(f64, f64, f64)> {
+        let (x0, y0) = (x.floor() as i64, y.floor() as i64);
+        if !self.is_dark(x0, y0) {
+            return None;
+        }
+        let reach = reach.ceil() as i64;
+
+        let mut seen = std::collections::HashSet::from([(x0, y0)]);
+        let mut pending = vec![(x0, y0)];
+        let (mut sum_x, mut sum_y) = (0.0, 0.0);
+        while let Some((x, y)) = pending.pop() {
+            if (x - x0).abs() > reach || (y - y0).abs() > reach {
+                return None;
+            }
+            sum_x += x as f64 + 0.5;
+            sum_y += y as f64 + 0.5;
+            for next in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if self.is_dark(next.0, next.1) && seen.insert(next) {
+                    pending.push(next);
+                }
+            }
+        }
+
+        let area = seen.len() as f64;
+        Some((sum_x / area, sum_y / area, area))
+    }
+
+    // Chord midpoints miss the centre of a rotated finder, but the 3×3 dark
+    // centre is symmetric, so its centroid is exact
+    fn refine_centre(&self, candidate: &mut FinderCandidate) {
+        if let Some((x, y, area)) = self.blob_centroid(candidate.x, candidate.y, candidate.module_size * 4.0) {
+            candidate.x = x;
+            candidate.y = y;
+            candidate.module_size = (area / 9.0).sqrt();
+        }
+    }
+
+    // Walk out from a candidate's centre in eight directions expecting dark,
+    // light, dark, then the light separator, in 1.5 : 1 : 1 proportions.
+    // Concentric squares keep those proportions along any ray, so this works
+    // for rotated codes too.
+    fn has_separator(&self, candidate: &FinderCandidate) -> bool {
+        let (x0, y0) = (candidate.x.floor() as i64, candidate.y.floor() as i64);
+        let limit = (candidate.module_size * 8.0).ceil() as usize;
+        let directions = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (-1, -1), (1, -1), (-1, 1)];
+
+        let passing = directions
+            .iter()
+            .filter(|(dx, dy)| {
+                let mut runs = [0usize; 4];
+                let mut step = 0i64;
+                for (index, run) in runs.iter_mut().enumerate() {
+                    let want_dark = index % 2 == 0;
+                    while *run < limit && self.is_dark(x0 + dx * step, y0 + dy * step) == want_dark {
+                        *run += 1;
+                        step += 1;
+                    }
+                }
+
+                let unit = (runs[0] + runs[1] + runs[2]) as f64 / 3.5;
+                let tolerance = (unit * 0.6).max(1.5);
+                (runs[0] as f64 - unit * 1.5).abs() < tolerance
+                    && (runs[1] as f64 - unit).abs() < tolerance
+                    && (runs[2] as f64 - unit).abs() < tolerance
+                    && runs[3] as f64 >= unit * 0.5
+            })
+            .count();
+
+        passing >= 7
+    }
+
+    fn finder_candidates(&self) -> Vec<FinderCandidate> {
+        let mut candidates: Vec<FinderCandidate> = Vec::new();
+        let step = (self.height / 600).max(1);
+
+        for y in (0..self.height).step_by(step) {
+            // Run lengths along the row as (starts dark, start, length)
+            let mut runs: Vec<(bool, usize, usize)> = Vec::new();
+            for x in 0..self.width {
+                let dark = self.dark[y * self.width + x];
+                match runs.last_mut() {
+                    Some(run) if run.0 == dark => run.2 += 1,
+                    _ => runs.push((dark, x, 1)),
+                }
+            }
+
+            for window in runs.windows(5) {
+                if !window[0].0 {
+                    continue;
+                }
+                let counts = [window[0].2, window[1].2, window[2].2, window[3].2, window[4].2];
+                if !is_finder_ratio(&counts) {
+                    continue;
+                }
+
+                let total: usize = counts.iter().sum();
+                let row_x = window[2].1 as f64 + window[2].2 as f64 / 2.0;
+                let Some((dy, vertical_total)) = self.cross_check(row_x, y as f64 + 0.5, 0, 1, total * 2) else {
+                    continue;
+                };
+                let centre_y = (y as f64 + 0.5 + dy).floor() + 0.5;
+                let Some((dx, horizontal_total)) = self.cross_check(row_x, centre_y, 1, 0, total * 2) else {
+                    continue;
+                };
+                let centre_x = row_x.floor() + 0.5 + dx;
+                let module_size = (vertical_total + horizontal_total) as f64 / 14.0;
+
+                match candidates.iter_mut().find(|c| {
+                    distance((c.x, c.y), (centre_x, centre_y)) <= c.module_size.max(module_size) * 2.0
+                        && (c.module_size - module_size).abs() <= c.module_size.max(1.0)
+                }) {
+                    Some(existing) => {
+                        let hits = existing.hits as f64;
+                        existing.x = (existing.x * hits + centre_x) / (hits + 1.0);
+                        existing.y = (existing.y * hits + centre_y) / (hits + 1.0);
+                        existing.module_size = (existing.module_size * hits + module_size) / (hits + 1.0);
+                        existing.hits += 1;
+                    },
+                    None => candidates.push(FinderCandidate { x: centre_x, y: centre_y, module_size, hits: 1 }),
+                }
+            }
+        }
+
+        candidates.iter_mut().for_each(|c| self.refine_centre(c));
+        // Repetitive data can form finder-like shapes; real ones are ringed by light modules
+        candidates.retain(|c| self.has_separator(c));
+        candidates.sort_by_key(|c| std::cmp::Reverse(c.hits));
+        candidates.truncate(MAX_CANDIDATES);
+        candidates
+    }
+
+    // Triples of finder patterns that could be one code's corners, best first,
+    // ordered as (top-left, top-right, bottom-left)
+    fn finder_triples(&self) -> Vec<[FinderCandidate; 3]> {
+        let candidates = self.finder_candidates();
+        let mut triples: Vec<(f64, [FinderCandidate; 3])> = Vec::new();
+
+        for i in 0..candidates.len() {
+            for j in i + 1..candidates.len() {
+                for k in j + 1..candidates.len() {
+                    let group = [candidates[i], candidates[j], candidates[k]];
+                    let sizes = group.iter().map(|c| c.module_size);
+                    let (smallest, largest) = sizes.fold((f64::MAX, 0.0f64), |(lo, hi), s| (lo.min(s), hi.max(s)));
+                    if largest > smallest * 1.6 {
+                        continue;
+                    }
+
+                    // The corner opposite the longest side is the top-left
+                    let side = |a: usize, b: usize| distance((group[a].x, group[a].y), (group[b].x, group[b].y));
+                    let sides = [side(1, 2), side(0, 2), side(0, 1)];
+                    let corner = (0..3).max_by(|a, b| sides[*a].total_cmp(&sides[*b])).unwrap_or(0);
+                    let (a, b) = ((corner + 1) % 3, (corner + 2) % 3);
+                    let (leg_a, leg_b, hypotenuse) = (sides[b], sides[a], sides[corner]);
+
+                    let module = (smallest + largest) / 2.0;
+                    if leg_a.min(leg_b) < module * 10.0 {
+                        continue;
+                    }
+                    let score = (leg_a - leg_b).abs() / leg_a.max(leg_b)
+                        + (hypotenuse - (leg_a * leg_a + leg_b * leg_b).sqrt()).abs() / hypotenuse;
+                    if score > 0.5 {
+                        continue;
+                    }
+
+                    // In image coordinates (y down) top-right → bottom-left turns clockwise
+                    let top_left = group[corner];
+                    let (mut top_right, mut bottom_left) = (group[a], group[b]);
+                    let cross = (top_right.x - top_left.x) * (bottom_left.y - top_left.y)
+                        - (top_right.y - top_left.y) * (bottom_left.x - top_left.x);
+                    if cross < 0.0 {
+                        std::mem::swap(&mut top_right, &mut bottom_left);
+                    }
+                    triples.push((score, [top_left, top_right, bottom_left]));
+                }
+            }
+        }
+
+        triples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        triples.into_iter().map(|(_, triple)| triple).collect()
+    }
+}
+
+//------------------------------------------------------------------------------
+// Sampling
+
+// A point in module coordinates and where it was found in the image
+type Correspondence = ((f64, f64), (f64, f64));
+
+// Maps module coordinates (x right, y down, in modules) to image pixels
+#[derive(Debug, Clone)]
+enum Transform {
+    Affine { origin: (f64, f64), x_axis: (f64, f64), y_axis: (f64, f64) },
+    Perspective([f64; 8]),
+}
+
+impl Transform {
+    fn map(&self, u: f64, v: f64) -> (f64, f64) {
+        match self {
+            Transform::Affine { origin, x_axis, y_axis } => (
+                origin.0 + u * x_axis.0 + v * y_axis.0,
+                origin.1 + u * x_axis.1 + v * y_axis.1,
+            ),
+            Transform::Perspective(h) => {
+                let w = h[6] * u + h[7] * v + 1.0;
+                ((h[0] * u + h[1] * v + h[2]) / w, (h[3] * u + h[4] * v + h[5]) / w)
+            }
+        }
+    }
+
+    // Homography through four module → pixel correspondences
+    fn perspective(pairs: &[Correspondence; 4]) -> Option<Transform> {
+        let mut rows = [[0.0f64; 9]; 8];
+        for (index, ((u, v), (x, y))) in pairs.iter().enumerate() {
+            rows[index * 2] = [*u, *v, 1.0, 0.0, 0.0, 0.0, -u * x, -v * x, *x];
+            rows[index * 2 + 1] = [0.0, 0.0, 0.0, *u, *v, 1.0, -u * y, -v * y, *y];
+        }
+
+        // Gaussian elimination with partial pivoting
+        for column in 0..8 {
+            let pivot = (column..8).max_by(|a, b| rows[*a][column].abs().total_cmp(&rows[*b][column].abs()))?;
+            if rows[pivot][column].abs() < 1e-9 {
+                return None;
+            }
+            rows.swap(column, pivot);
+            let pivot_row = rows[column];
+            for (index, row) in rows.iter_mut().enumerate() {
+                if index != column {
+                    let factor = row[column] / pivot_row[column];
+                    for (value, pivot_value) in row.iter_mut().zip(pivot_row).skip(column) {
+                        *value -= factor * pivot_value;
+                    }
+                }
+            }
+        }
+
+        let mut h = [0.0; 8];
+        for (index, value) in h.iter_mut().enumerate() {
+            *value = rows[index][8] / rows[index][index];
+        }
+        Some(Transform::Perspective(h))
+    }
+}
+
+// A sampled module grid, dark = true
+#[derive(Debug, Clone)]
+struct Grid {
+    size: usize,
+    modules: Vec<bool>,
+}
+
+impl Grid {
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.modules[y * self.size + x]
+    }
+
+    // Mirror across the main diagonal, for codes seen from behind (e.g. a selfie camera)
+    fn transposed(&self) -> Grid {
+        let mut modules = vec![false; self.modules.len()];
+        for y in 0..self.size {
+            for x in 0..self.size {
+                modules[x * self.size + y] = self.get(x, y);
+            }
+        }
+        Grid { size: self.size, modules }
+    }
+}
+
+impl BitImage {
+    fn sample_grid(&self, transform: &Transform, size: usize) -> Grid {
+        let mut modules = Vec::with_capacity(size * size);
+        for y in 0..size {
+            for x in 0..size {
+                modules.push(self.sample(transform.map(x as f64 + 0.5, y as f64 + 0.5)));
+            }
+        }
+        Grid { size, modules }
+    }
+
+    // Look for the bottom-right alignment pattern near where the finder
+    // patterns alone predict it
+    fn find_alignment(&self, affine: &Transform, size: usize, module_size: f64) -> Option<(f64, f64)> {
+        let Transform::Affine { x_axis, y_axis, .. } = affine else {
+            return None;
+        };
+        let centre = size as f64 - 6.5;
+        let predicted = affine.map(centre, centre);
+        // Perspective pulls the far corner further from the affine guess on bigger codes
+        let radius = (module_size * (size as f64 / 12.0).max(4.0)).ceil() as i64;
+        let step = ((module_size / 4.0).floor() as i64).max(1);
+
+        let mut best: Option<(usize, f64, (f64, f64))> = None;
+        for dy in (-radius..=radius).step_by(step as usize) {
+            for dx in (-radius..=radius).step_by(step as usize) {
+                let point = (predicted.0 + dx as f64, predicted.1 + dy as f64);
+                let mut score = 0;
+                for j in -2i32..=2 {
+                    for i in -2i32..=2 {
+                        let expect_dark = i.abs().max(j.abs()) != 1;
+                        let sample = (
+                            point.0 + i as f64 * x_axis.0 + j as f64 * y_axis.0,
+                            point.1 + i as f64 * x_axis.1 + j as f64 * y_axis.1,
+                        );
+                        if self.sample(sample) == expect_dark {
+                            score += 1;
+                        }
+                    }
+                }
+                let off_by = distance(point, predicted);
+                if best.is_none_or(|(best_score, best_off, _)| score > best_score || (score == best_score && off_by < best_off)) {
+                    best = Some((score, off_by, point));
+                }
+            }
+        }
+
+        // Allow a few wrong samples for noise and perspective, then centre on the middle dot
+        let (_, _, point) = best.filter(|(score, _, _)| *score >= 20)?;
+        Some(match self.blob_centroid(point.0, point.1, module_size * 1.5) {
+            Some((x, y, _)) => (x, y),
+            None => point,
+        })
+    }
+
+    // A finder pattern's module size measured along the line towards another
+    // finder, from edge to edge of its outer ring (7 modules)
+    fn module_size_along(&self, from: &FinderCandidate, to: &FinderCandidate) -> f64 {
+        let length = distance((from.x, from.y), (to.x, to.y));
+        let direction = ((to.x - from.x) / length, (to.y - from.y) / length);
+        let limit = (from.module_size * 8.0).ceil() as usize;
+
+        let mut width = 0.0;
+        for sign in [1.0, -1.0] {
+            // Dark centre, light ring, then the outer dark ring
+            let mut transitions = 0;
+            let mut dark = true;
+            let mut step = 0;
+            while step < limit && transitions < 3 {
+                step += 1;
+                let point = (
+                    from.x + sign * step as f64 * direction.0,
+                    from.y + sign * step as f64 * direction.1,
+                );
+                if self.sample(point) != dark {
+                    dark = !dark;
+                    transitions += 1;
+                }
+            }
+            if transitions < 3 {
+                return from.module_size;
+            }
+            width += step as f64 - 0.5;
+        }
+        width / 7.0
+    }
+
+    fn decode_triple(&self, triple: &[FinderCandidate; 3]) -> Result<String, String> {
+        let [top_left, top_right, bottom_left] = triple;
+        let module_size = (top_left.module_size + top_right.module_size + bottom_left.module_size) / 3.0;
+
+        // Count modules between finder centres, measuring the finders along
+        // the code's own axes so rotation and perspective don't skew the estimate
+        let modules_between = |from: &FinderCandidate, to: &FinderCandidate| {
+            let modules = (self.module_size_along(from, to) + self.module_size_along(to, from)) / 2.0;
+            distance((from.x, from.y), (to.x, to.y)) / modules
+        };
+        let across = (modules_between(top_left, top_right) + modules_between(top_left, bottom_left)) / 2.0;
+
+        // Finder centres sit 3.5 modules in from the edges; sizes are 17 + 4 × version
+        let estimate = across.round() as i64 + 7;
+        let nearest = 17 + ((estimate - 17) as f64 / 4.0).round() as i64 * 4;
+        let mut sizes: Vec<usize> = [nearest, nearest - 4, nearest + 4, nearest - 8, nearest + 8]
+            .iter()
+            .filter(|size| (21..=177).contains(*size))
+            .map(|size| *size as usize)
+            .collect();
+        sizes.dedup();
+
+        let mut last_error = "no plausible size".to_string();
+        for size in sizes {
+            let span = size as f64 - 7.0;
+            let affine = Transform::Affine {
+                origin: (
+                    top_left.x - 3.5 * (top_right.x - top_left.x) / span - 3.5 * (bottom_left.x - top_left.x) / span,
+                    top_left.y - 3.5 * (top_right.y - top_left.y) / span - 3.5 * (bottom_left.y - top_left.y) / span,
+                ),
+                x_axis: ((top_right.x - top_left.x) / span, (top_right.y - top_left.y) / span),
+                y_axis: ((bottom_left.x - top_left.x) / span, (bottom_left.y - top_left.y) / span),
+            };
+
+            let mut transforms = Vec::new();
+            if size > 21 {
+                if let Some(alignment) = self.find_alignment(&affine, size, module_size) {
+                    let corner = size as f64 - 3.5;
+                    let pairs = [
+                        ((3.5, 3.5), (top_left.x, top_left.y)),
+                        ((corner, 3.5), (top_right.x, top_right.y)),
+                        ((3.5, corner), (bottom_left.x, bottom_left.y)),
+                        ((size as f64 - 6.5, size as f64 - 6.5), alignment),
+                    ];
+                    transforms.extend(Transform::perspective(&pairs));
+                }
+            }
+            transforms.push(affine);
+
+            for transform in &transforms {
+                let grid = self.sample_grid(transform, size);
+                for candidate in [grid.transposed(), grid] {
+                    match decode_grid(&candidate) {
+                        Ok(text) => return Ok(text),
+                        Err(e) => last_error = e,
+                    }
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
+
+//------------------------------------------------------------------------------
+// Format and version information
+
+// 15-bit format codeword for 5 data bits: BCH(15, 5), then the fixed mask
+fn format_codeword(data: u32) -> u32 {
+    let mut remainder = data << 10;
+    for bit in (10..15).rev() {
+        if remainder & (1 << bit) != 0 {
+            remainder ^= 0x537 << (bit - 10);
+        }
+    }
+    ((data << 10) | remainder) ^ 0x5412
+}
+
+// 18-bit version codeword: BCH(18, 6)
+fn version_codeword(version: u32) -> u32 {
+    let mut remainder = version << 12;
+    for bit in (12..18).rev() {
+        if remainder & (1 << bit) != 0 {
+            remainder ^= 0x1f25 << (bit - 12);
+        }
+    }
+    (version << 12) | remainder
+}
+
+fn read_bits(grid: &Grid, coords: &[(usize, usize)]) -> u32 {
+    coords.iter().fold(0, |bits, (x, y)| (bits << 1) | grid.get(*x, *y) as u32)
+}
+
+// Error correction level index (L, M, Q, H) and mask pattern
+fn read_format(grid: &Grid) -> Option<(usize, u8)> {
+    let size = grid.size;
+    let main: Vec<(usize, usize)> = [(0, 8), (1, 8), (2, 8), (3, 8), (4, 8), (5, 8), (7, 8), (8, 8), (8, 7)]
+        .into_iter()
+        .chain([5, 4, 3, 2, 1, 0].map(|y| (8, y)))
+        .collect();
+    let side: Vec<(usize, usize)> = (1..=7)
+        .map(|i| (8, size - i))
+        .chain((1..=8).rev().map(|i| (size - i, 8)))
+        .collect();
+    let copies = [read_bits(grid, &main), read_bits(grid, &side)];
+
+    let (distance, data) = (0..32u32)
+        .flat_map(|data| copies.map(|bits| ((bits ^ format_codeword(data)).count_ones(), data)))
+        .min()?;
+    if distance > 3 {
+        return None;
+    }
+
+    // Levels are stored as M=00, L=01, H=10, Q=11
+    let level = match data >> 3 {
+        0b01 => 0,
+        0b00 => 1,
+        0b11 => 2,
+        _ => 3,
+    };
+    Some((level, (data & 0b111) as u8))
+}
+
+fn read_version(grid: &Grid) -> Option<usize> {
+    let size = grid.size;
+    let bottom_left: Vec<(usize, usize)> = (0..6).rev()
+        .flat_map(|x| [(x, size - 9), (x, size - 10), (x, size - 11)])
+        .collect();
+    let top_right: Vec<(usize, usize)> = (0..6).rev()
+        .flat_map(|y| [(size - 9, y), (size - 10, y), (size - 11, y)])
+        .collect();
+    let copies = [read_bits(grid, &bottom_left), read_bits(grid, &top_right)];
+
+    let (distance, version) = (7..=40u32)
+        .flat_map(|version| copies.map(|bits| ((bits ^ version_codeword(version)).count_ones(), version)))
+        .min()?;
+    (distance <= 3).then_some(version as usize)
+}
+
+fn mask_bit(mask: u8, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (y / 2 + x / 3).is_multiple_of(2),
+        5 => (x * y) % 2 + (x * y) % 3 == 0,
+        6 => ((x * y) % 2 + (x * y) % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + (x * y) % 3).is_multiple_of(2),
+    }
+}
+
+//------------------------------------------------------------------------------
+// Codewords
+
+fn decode_grid(grid: &Grid) -> Result<String, String> {
+    let size = grid.size;
+    let version = (size - 17) / 4;
+    if !(1..=40).contains(&version) || size != 17 + version * 4 {
+        return Err("unsupported size".to_string());
+    }
+    if version >= 7 && read_version(grid) != Some(version) {
+        return Err("version information doesn't match".to_string());
+    }
+    let (level, mask) = read_format(grid).ok_or_else(|| "unreadable format information".to_string())?;
+
+    let qr_version = Version::Normal(version as i16);
+    let is_reserved = |x: usize, y: usize| {
+        let version_area = version >= 7
+            && ((x >= size - 11 && x < size - 8 && y < 6) || (y >= size - 11 && y < size - 8 && x < 6));
+        version_area || is_functional(qr_version, size as i16, x as i16, y as i16)
+    };
+
+    // Read the data modules in the standard zigzag, two columns at a time from the right
+    let mut bits = Vec::with_capacity(size * size);
+    let mut right = size as i64 - 1;
+    let mut upwards = true;
+    while right > 0 {
+        if right == 6 {
+            // Skip the vertical timing pattern
+            right -= 1;
+        }
+        for step in 0..size {
+            let y = if upwards { size - 1 - step } else { step };
+            for x in [right as usize, right as usize - 1] {
+                if !is_reserved(x, y) {
+                    bits.push(grid.get(x, y) ^ mask_bit(mask, x, y));
+                }
+            }
+        }
+        upwards = !upwards;
+        right -= 2;
+    }
+
+    let codewords: Vec<u8> = bits
+        .chunks_exact(8)
+        .map(|byte| byte.iter().fold(0u8, |acc, bit| (acc << 1) | *bit as u8))
+        .collect();
+
+    let data = correct_codewords(&codewords, version, level)?;
+    parse_segments(&data, version)
+}
+
+// Undo the block interleaving and correct each block
+fn correct_codewords(codewords: &[u8], version: usize, level: usize) -> Result<Vec<u8>, String> {
+    let ec_len = EC_CODEWORDS_PER_BLOCK[version - 1][level];
+    let (size_1, count_1, size_2, count_2) = DATA_BLOCKS[version - 1][level];
+    let block_sizes: Vec<usize> = std::iter::repeat_n(size_1, count_1)
+        .chain(std::iter::repeat_n(size_2, count_2))
+        .collect();
+
+    let total: usize = block_sizes.iter().map(|size| size + ec_len).sum();
+    if codewords.len() < total {
+        return Err("too few codewords".to_string());
+    }
+
+    let mut blocks: Vec<Vec<u8>> = block_sizes.iter().map(|size| Vec::with_capacity(size + ec_len)).collect();
+    let mut next = codewords.iter();
+    let longest = block_sizes.iter().copied().max().unwrap_or(0);
+    for index in 0..longest {
+        for (block, size) in blocks.iter_mut().zip(&block_sizes) {
+            if index < *size {
+                block.extend(next.next());
+            }
+        }
+    }
+    for _ in 0..ec_len {
+        for block in blocks.iter_mut() {
+            block.extend(next.next());
+        }
+    }
+
+    let field = Galois::new();
+    let mut data = Vec::new();
+    for (mut block, size) in blocks.into_iter().zip(block_sizes) {
+        field.correct(&mut block, ec_len)?;
+        data.extend_from_slice(&block[..size]);
+    }
+    Ok(data)
+}
+
+//------------------------------------------------------------------------------
+// Reed–Solomon over GF(256) with the QR polynomial x⁸ + x⁴ + x³ + x² + 1
+
+struct Galois {
+    exp: [u8; 510],
+    log: [u8; 256],
+}
+
+impl Galois {
+    fn new() -> Self {
+        let mut exp = [0u8; 510];
+        let mut log = [0u8; 256];
+        let mut value = 1u16;
+        for power in 0..255 {
+            // Doubled up so products can index with the sum of two logs
+            exp[power] = value as u8;
+            exp[power + 255] = value as u8;
+            log[value as usize] = power as u8;
+            value <<= 1;
+            if value & 0x100 != 0 {
+                value ^= 0x11d;
+            }
+        }
+        Galois { exp, log }
+    }
+
+    fn mul(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            0
+        } else {
+            self.exp[self.log[a as usize] as usize + self.log[b as usize] as usize]
+        }
+    }
+
+    fn div(&self, a: u8, b: u8) -> u8 {
+        if a == 0 {
+            0
+        } else {
+            self.exp[self.log[a as usize] as usize + 255 - self.log[b as usize] as usize]
+        }
+    }
+
+    fn pow_alpha(&self, power: usize) -> u8 {
+        self.exp[power % 255]
+    }
+
+    // Evaluate a polynomial stored lowest degree first
+    fn eval(&self, poly: &[u8], x: u8) -> u8 {
+        poly.iter().rev().fold(0, |acc, coefficient| self.mul(acc, x) ^ coefficient)
+    }
+
+    fn syndromes(&self, block: &[u8], ec_len: usize) -> Vec<u8> {
+        (0..ec_len)
+            .map(|i| {
+                let root = self.pow_alpha(i);
+                block.iter().fold(0, |acc, codeword| self.mul(acc, root) ^ codeword)
+            })
+            .collect()
+    }
+
+    // Fix up to ec_len / 2 wrong codewords in place
+    fn correct(&self, block: &mut [u8], ec_len: usize) -> Result<(), String> {
+        let syndromes = self.syndromes(block, ec_len);
+        if syndromes.iter().all(|s| *s == 0) {
+            return Ok(());
+        }
+
+        // Berlekamp–Massey for the error locator polynomial
+        let mut locator = vec![1u8];
+        let mut previous = vec![1u8];
+        let (mut errors, mut shift, mut last_discrepancy) = (0usize, 1usize, 1u8);
+        for k in 0..ec_len {
+            let discrepancy = (1..=errors)
+                .filter(|i| *i < locator.len())
+                .fold(syndromes[k], |acc, i| acc ^ self.mul(locator[i], syndromes[k - i]));
+            if discrepancy == 0 {
+                shift += 1;
+                continue;
+            }
+
+            let scale = self.div(discrepancy, last_discrepancy);
+            let before = locator.clone();
+            if locator.len() < previous.len() + shift {
+                locator.resize(previous.len() + shift, 0);
+            }
+            for (i, coefficient) in previous.iter().enumerate() {
+                locator[i + shift] ^= self.mul(scale, *coefficient);
+            }
+
+            if 2 * errors <= k {
+                errors = k + 1 - errors;
+                previous = before;
+                last_discrepancy = discrepancy;
+                shift = 1;
+            } else {
+                shift += 1;
+            }
+        }
+        while locator.len() > 1 && locator.last() == Some(&0) {
+            locator.pop();
+        }
+        let degree = locator.len() - 1;
+        if degree == 0 || degree * 2 > ec_len {
+            return Err("too many errors".to_string());
+        }
+
+        // Chien search: an error at power j (index n-1-j) makes Λ(α^-j) vanish
+        let n = block.len();
+        let positions: Vec<usize> = (0..n)
+            .filter(|j| self.eval(&locator, self.pow_alpha(255 - j % 255)) == 0)
+            .collect();
+        if positions.len() != degree {
+            return Err("too many errors".to_string());
+        }
+
+        // Forney: e = X · Ω(X⁻¹) / Λ'(X⁻¹) for first consecutive root α⁰
+        let mut evaluator = vec![0u8; ec_len];
+        for (i, syndrome) in syndromes.iter().enumerate() {
+            for (j, coefficient) in locator.iter().enumerate() {
+                if i + j < ec_len {
+                    evaluator[i + j] ^= self.mul(*syndrome, *coefficient);
+                }
+            }
+        }
+        let derivative: Vec<u8> = locator
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, coefficient)| if i % 2 == 1 { *coefficient } else { 0 })
+            .collect();
+
+        for j in positions {
+            let x = self.pow_alpha(j);
+            let x_inverse = self.pow_alpha(255 - j % 255);
+            let denominator = self.eval(&derivative, x_inverse);
+            if denominator == 0 {
+                return Err("uncorrectable block".to_string());
+            }
+            let magnitude = self.mul(x, self.div(self.eval(&evaluator, x_inverse), denominator));
+            block[n - 1 - j] ^= magnitude;
+        }
+
+        if self.syndromes(block, ec_len).iter().any(|s| *s != 0) {
+            return Err("uncorrectable block".to_string());
+        }
+        Ok(())
+    }
+}
+
+//------------------------------------------------------------------------------
+// Segments
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn remaining(&self) -> usize {
+        self.bytes.len() * 8 - self.position
+    }
+
+    fn read(&mut self, count: usize) -> Option<u32> {
+        if count > self.remaining() {
+            return None;
+        }
+        let mut value = 0u32;
+        for _ in 0..count {
+            let byte = self.bytes[self.position / 8];
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.position += 1;
+        }
+        Some(value)
+    }
+}
+
+// Bits in the character count for numeric, alphanumeric and byte segments
+fn count_bits(version: usize) -> [usize; 3] {
+    match version {
+        1..=9 => [10, 9, 8],
+        10..=26 => [12, 11, 16],
+        _ => [14, 13, 16],
+    }
+}
+
+fn parse_segments(data: &[u8], version: usize) -> Result<String, String> {
+    let truncated = || "truncated data".to_string();
+    let [numeric_bits, alphanumeric_bits, byte_bits] = count_bits(version);
+    let mut reader = BitReader { bytes: data, position: 0 };
+    let mut output = Vec::new();
+
+    while reader.remaining() >= 4 {
+        match reader.read(4).ok_or_else(truncated)? {
+            0b0000 => break,
+            0b0001 => {
+                let mut count = reader.read(numeric_bits).ok_or_else(truncated)? as usize;
+                while count > 0 {
+                    let (bits, digits) = match count {
+                        1 => (4, 1),
+                        2 => (7, 2),
+                        _ => (10, 3),
+                    };
+                    let value = reader.read(bits).ok_or_else(truncated)?;
+                    output.extend(format!("{:0width$}", value, width = digits).bytes());
+                    count -= digits;
+                }
+            },
+            0b0010 => {
+                let mut count = reader.read(alphanumeric_bits).ok_or_else(truncated)? as usize;
+                while count >= 2 {
+                    let value = reader.read(11).ok_or_else(truncated)? as usize;
+                    if value >= 45 * 45 {
+                        return Err("invalid alphanumeric data".to_string());
+                    }
+                    output.push(ALPHANUMERIC[value / 45]);
+                    output.push(ALPHANUMERIC[value % 45]);
+                    count -= 2;
+                }
+                if count == 1 {
+                    let value = reader.read(6).ok_or_else(truncated)? as usize;
+                    output.push(*ALPHANUMERIC.get(value).ok_or_else(|| "invalid alphanumeric data".to_string())?);
+                }
+            },
+            0b0100 => {
+                let count = reader.read(byte_bits).ok_or_else(truncated)?;
+                for _ in 0..count {
+                    output.push(reader.read(8).ok_or_else(truncated)? as u8);
+                }
+            },
+            0b0111 => {
+                // ECI designator; we always decode as UTF-8
+                let first = reader.read(8).ok_or_else(truncated)?;
+                let extra = if first & 0x80 == 0 { 0 } else if first & 0x40 == 0 { 8 } else { 16 };
+                reader.read(extra).ok_or_else(truncated)?;
+            },
+            0b0011 => {
+                // Structured append header: symbol index, count and parity
+                reader.read(16).ok_or_else(truncated)?;
+            },
+            0b0101 => {},
+            0b1001 => {
+                reader.read(8).ok_or_else(truncated)?;
+            },
+            _ => return Err("unsupported QR data mode".to_string()),
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::{Color, EcLevel, QrCode};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    const INVITE: &str = "http://64.181.233.1/?link=abc123#key=0123456789abcdefghijklmnopqrstuvwxyzABCDEFG";
+
+    // Render a code as a greyscale image with a quiet zone, `scale` pixels per module
+    fn render(code: &QrCode, scale: usize) -> (Vec<u8>, usize) {
+        let modules = code.width();
+        let side = (modules + 8) * scale;
+        let mut pixels = vec![255u8; side * side];
+        for (index, color) in code.to_colors().iter().enumerate() {
+            if *color == Color::Dark {
+                let (x, y) = ((index % modules + 4) * scale, (index / modules + 4) * scale);
+                for row in y..y + scale {
+                    pixels[row * side + x..row * side + x + scale].fill(0);
+                }
+            }
+        }
+        (pixels, side)
+    }
+
+    #[wasm_bindgen_test]
+    fn test_format_and_version_codewords_match_the_spec() {
+        // Level M, mask 5 from ISO/IEC 18004 annex C, and version 7's codeword
+        assert_eq!(format_codeword(0b00101), 0b100000011001110);
+        assert_eq!(version_codeword(7), 0x07c94);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_reed_solomon_corrects_errors() {
+        let field = Galois::new();
+        let data = b" [\x0bx\xd1r\xdcMC@\xec\x11\xec\x11\xec\x11";
+        let ec = b"\xc4#'w\xeb\xd7\xe7\xe2]\x17";
+        let mut block: Vec<u8> = data.iter().chain(ec.iter()).copied().collect();
+
+        block[0] ^= 0x55;
+        block[7] ^= 0x01;
+        block[20] ^= 0xff;
+        field.correct(&mut block, ec.len()).unwrap();
+        assert_eq!(&block[..data.len()], data);
+
+        // Six errors are beyond what ten EC codewords can fix
+        for index in [1, 3, 5, 9, 11, 13] {
+            block[index] ^= 0x22;
+        }
+        assert!(field.correct(&mut block, ec.len()).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_decodes_generated_invite() {
+        let code = QrCode::with_error_correction_level(INVITE.as_bytes(), EcLevel::M).unwrap();
+        let (pixels, side) = render(&code, 6);
+        assert_eq!(decode_luma(&pixels, side, side).unwrap(), INVITE);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_decodes_every_level_and_segment_mode() {
+        for (text, level) in [
+            ("http://example.com/?link=XYZ", EcLevel::L),
+            ("HELLO WORLD 123", EcLevel::Q),
+            ("01234567890123456789", EcLevel::H),
+            (INVITE, EcLevel::L),
+        ] {
+            let code = QrCode::with_error_correction_level(text.as_bytes(), level).unwrap();
+            let (pixels, side) = render(&code, 4);
+            assert_eq!(decode_luma(&pixels, side, side).unwrap(), text);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_decodes_large_versions() {
+        let text = "x".repeat(400);
+        let code = QrCode::new(text.as_bytes()).unwrap();
+        assert!(code.width() > 45, "Should exercise version information");
+        let (pixels, side) = render(&code, 3);
+        assert_eq!(decode_luma(&pixels, side, side).unwrap(), text);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_decodes_rotated_and_damaged_codes() {
+        let code = QrCode::with_error_correction_level(INVITE.as_bytes(), EcLevel::M).unwrap();
+        let (pixels, side) = render(&code, 5);
+
+        // Rotate 90° clockwise
+        let mut rotated = vec![255u8; side * side];
+        for y in 0..side {
+            for x in 0..side {
+                rotated[x * side + (side - 1 - y)] = pixels[y * side + x];
+            }
+        }
+        assert_eq!(decode_luma(&rotated, side, side).unwrap(), INVITE);
+
+        // Scribble over a few data modules near the middle
+        let mut damaged = pixels.clone();
+        let middle = side / 2;
+        for y in middle..middle + 10 {
+            for x in middle..middle + 10 {
+                damaged[y * side + x] = 255 - damaged[y * side + x];
+            }
+        }
+        assert_eq!(decode_luma(&damaged, side, side).unwrap(), INVITE);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_rgba_with_transparency_and_no_code() {
+        let code = QrCode::new(b"?link=abc").unwrap();
+        let (pixels, side) = render(&code, 4);
+        // Dark modules opaque black, light ones fully transparent
+        let rgba: Vec<u8> = pixels
+            .iter()
+            .flat_map(|value| if *value == 0 { [0, 0, 0, 255] } else { [0, 0, 0, 0] })
+            .collect();
+        assert_eq!(decode_rgba(&rgba, side, side).unwrap(), "?link=abc");
+
+        let blank = vec![255u8; 100 * 100];
+        assert!(decode_luma(&blank, 100, 100).is_err());
+    }
+}
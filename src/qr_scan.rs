@@ -0,0 +1,218 @@
+use leptos::ev;
+use leptos::prelude::*;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{window, Blob, CanvasRenderingContext2d, ClipboardEvent, HtmlCanvasElement, HtmlInputElement, ImageBitmap};
+
+use crate::browser::console_log;
+use crate::connection_utils;
+use crate::e2e;
+use crate::qr_decode;
+
+// Photos are scaled down to this many pixels on their longest side before
+// decoding; plenty for a QR code and keeps decoding quick on phones
+const MAX_SCAN_SIDE: f64 = 1000.0;
+
+// An invite read from a QR code
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScannedInvite {
+    pub link_id: String,
+    // The end-to-end key from the `#key=` fragment, if the link carried one
+    pub secret: Option<String>,
+}
+
+// Pull the link id (and key) out of the text in a QR code
+pub fn invite_from_text(text: &str) -> Result<ScannedInvite, String> {
+    let not_invite = || "That QR code isn't a Friends Connect invite.".to_string();
+
+    // Resolve against the current page so a bare "?link=..." works too
+    let base = window()
+        .and_then(|window| window.location().href().ok())
+        .unwrap_or_else(|| "http://localhost/".to_string());
+    let url = web_sys::Url::new_with_base(text.trim(), &base).map_err(|_| not_invite())?;
+
+    let link_id = connection_utils::extract_link_id_from_search(&url.search())
+        .filter(|link_id| !link_id.is_empty())
+        .ok_or_else(not_invite)?;
+
+    Ok(ScannedInvite { link_id, secret: e2e::extract_secret_from_fragment(&url.hash()) })
+}
+
+// Whether an invite can be joined from this browser. A link without a key is
+// fine (its chat is marked "Not encrypted"), but a key that's there and broken
+// is refused rather than quietly dropped.
+pub fn check_invite(invite: &ScannedInvite) -> Result<(), String> {
+    if invite.link_id.trim().is_empty() {
+        return Err("That invite has no link id.".to_string());
+    }
+    match &invite.secret {
+        Some(secret) if !e2e::is_valid_secret(secret) => {
+            Err("That invite's encryption key is damaged. Ask your friend to send the link again.".to_string())
+        },
+        _ => Ok(()),
+    }
+}
+
+// Get a checked invite ready for the join flow: keep its key and put the link
+// id in the address bar, as if the invite link was opened. Used by every way
+// of joining so they all accept the same invites.
+pub fn accept_invite(invite: &ScannedInvite) -> Result<(), String> {
+    check_invite(invite)?;
+    if let Some(secret) = &invite.secret {
+        e2e::store_connection_secret(&invite.link_id, secret).map_err(|e| format!("{:?}", e))?;
+    }
+    connection_utils::show_link_in_url(&invite.link_id).map_err(|e| format!("{:?}", e))
+}
+
+// Draw an image onto a canvas and read back its pixels as RGBA
+async fn image_pixels(image: &Blob) -> Result<(Vec<u8>, usize, usize), JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("No window"))?;
+    let bitmap: ImageBitmap = JsFuture::from(window.create_image_bitmap_with_blob(image)?)
+        .await?
+        .dyn_into()?;
+
+    let (width, height) = (bitmap.width() as f64, bitmap.height() as f64);
+    if width == 0.0 || height == 0.0 {
+        return Err(JsValue::from_str("The image is empty."));
+    }
+    let scale = (MAX_SCAN_SIDE / width.max(height)).min(1.0);
+    let (width, height) = ((width * scale).round().max(1.0), (height * scale).round().max(1.0));
+
+    let document = window.document().ok_or_else(|| JsValue::from_str("No document"))?;
+    let canvas: HtmlCanvasElement = document.create_element("canvas")?.dyn_into()?;
+    canvas.set_width(width as u32);
+    canvas.set_height(height as u32);
+    let context: CanvasRenderingContext2d = canvas
+        .get_context("2d")?
+        .ok_or_else(|| JsValue::from_str("Canvas not available"))?
+        .dyn_into()?;
+
+    context.draw_image_with_image_bitmap_and_dw_and_dh(&bitmap, 0.0, 0.0, width, height)?;
+    bitmap.close();
+    let data = context.get_image_data(0.0, 0.0, width, height)?;
+
+    Ok((data.data().0, width as usize, height as usize))
+}
+
+// Decode the invite in an image and get it ready for the join flow
+async fn join_from_image(image: Blob) -> Result<String, String> {
+    let (pixels, width, height) = image_pixels(&image)
+        .await
+        .map_err(|e| format!("Couldn't read the image: {}", e.as_string().unwrap_or_else(|| format!("{:?}", e))))?;
+    let text = qr_decode::decode_rgba(&pixels, width, height)?;
+    let invite = invite_from_text(&text)?;
+    console_log(&format!("Scanned invite for link {}", invite.link_id));
+    accept_invite(&invite)?;
+
+    Ok(invite.link_id)
+}
+
+// Join by uploading or pasting a picture of an invite QR code
+#[component]
+pub fn QrJoin(
+    // Called with the link id once the invite is in the address bar
+    on_join: Callback<String>,
+) -> impl IntoView {
+    let (message, set_message) = signal(String::new());
+    let (busy, set_busy) = signal(false);
+    let image_input = NodeRef::<leptos::html::Input>::new();
+
+    let scan = move |image: Blob| {
+        set_busy.set(true);
+        set_message.set("Reading QR code…".to_string());
+        spawn_local(async move {
+            match join_from_image(image).await {
+                Ok(link_id) => {
+                    set_message.set(String::new());
+                    on_join.run(link_id);
+                },
+                Err(e) => set_message.set(e),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let handle_image_change = move |_| {
+        let file = image_input.get()
+            .and_then(|input: HtmlInputElement| input.files())
+            .and_then(|files| files.get(0));
+        if let Some(file) = file {
+            scan(file.into());
+        }
+    };
+
+    // Screenshots can be pasted anywhere while the panel is open
+    let paste_handle = window_event_listener(ev::paste, move |event| {
+        let image = event
+            .dyn_ref::<ClipboardEvent>()
+            .and_then(|event| event.clipboard_data())
+            .and_then(|data| data.files())
+            .and_then(|files| (0..files.length()).filter_map(|index| files.get(index)).find(|file| file.type_().starts_with("image/")));
+        if let Some(image) = image {
+            event.prevent_default();
+            scan(image.into());
+        }
+    });
+    on_cleanup(move || paste_handle.remove());
+
+    view! {
+        <div class="bg-gray-800 border border-gray-700 rounded p-4 mb-4" data-test-id="qr-join-panel">
+            <div class="font-medium">"Join from QR image"</div>
+            <div class="text-sm text-gray-400">
+                "Choose a screenshot or photo of an invite QR code, or paste one with Ctrl+V."
+            </div>
+            <input
+                type="file"
+                accept="image/*"
+                class="w-full mt-3 text-sm text-gray-300"
+                prop:disabled=busy
+                node_ref=image_input
+                on:change=handle_image_change
+            />
+            {move || (!message.get().is_empty()).then(|| view! {
+                <div class="mt-2 text-sm text-gray-300" data-test-id="qr-join-message">{message.get()}</div>
+            })}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_invite_from_full_link() {
+        let invite = invite_from_text("http://64.181.233.1/?link=abc123#key=s3cr3t").unwrap();
+        assert_eq!(invite.link_id, "abc123");
+        assert_eq!(invite.secret.as_deref(), Some("s3cr3t"));
+
+        let without_key = invite_from_text(" https://example.com/app/?link=xyz ").unwrap();
+        assert_eq!(without_key, ScannedInvite { link_id: "xyz".to_string(), secret: None });
+    }
+
+    #[wasm_bindgen_test]
+    fn test_invite_from_relative_link() {
+        assert_eq!(invite_from_text("?link=relative").unwrap().link_id, "relative");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_rejects_codes_that_arent_invites() {
+        assert!(invite_from_text("hello there").is_err());
+        assert!(invite_from_text("https://example.com/?other=1").is_err());
+        assert!(invite_from_text("https://example.com/?link=").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_check_invite() {
+        let key = "A".repeat(43);
+        let keyless = ScannedInvite { link_id: "abc123".to_string(), secret: None };
+        assert_eq!(check_invite(&keyless), Ok(()));
+        assert_eq!(check_invite(&ScannedInvite { secret: Some(key), ..keyless.clone() }), Ok(()));
+
+        assert!(check_invite(&ScannedInvite { secret: Some("s3cr3t".to_string()), ..keyless.clone() }).is_err());
+        assert!(check_invite(&ScannedInvite { link_id: " ".to_string(), secret: None }).is_err());
+    }
+}
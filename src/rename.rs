@@ -0,0 +1,64 @@
+
+use crate::api_retry::RetryPolicy;
+use crate::browser::console_log;
+use crate::connect_component::get_stored_player_id;
+use crate::connection_utils;
+use crate::outbox::{self, OutboxAction};
+
+// Longest friendly name we keep for a connection
+pub const MAX_NAME_CHARS: usize = 60;
+
+// A friendly name as it will be stored, or why it can't be used
+pub fn clean_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Please enter a name for your connection.".to_string());
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(format!("Names can be at most {} characters.", MAX_NAME_CHARS));
+    }
+    Ok(name.to_string())
+}
+
+// Rename a connection here straight away, then tell the server. Offline, the
+// rename waits in the outbox; the local name is kept either way.
+pub async fn rename(connection_id: &str, name: &str) -> Result<String, String> {
+    let name = clean_name(name)?;
+    connection_utils::rename_saved_connection(connection_id, &name);
+    console_log(&format!("Renamed {} to {}", connection_id, name));
+
+    let Some(player_id) = get_stored_player_id() else {
+        return Ok(name);
+    };
+    let client_id = uuid::Uuid::new_v4().to_string();
+    match connection_utils::rename_connection(connection_id, &player_id, &name, &client_id, &RetryPolicy::user_action()).await {
+        Ok(()) => Ok(name),
+        Err(e) if e.is_network() || !outbox::is_online() => {
+            outbox::enqueue(&player_id, OutboxAction::Rename {
+                connection_id: connection_id.to_string(),
+                name: name.clone(),
+            });
+            Ok(name)
+        },
+        Err(e) => Err(format!(
+            "Renamed on this device, but the server didn't get it ({}).",
+            e.message()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_clean_name() {
+        assert_eq!(clean_name("  Sam  "), Ok("Sam".to_string()));
+        assert!(clean_name("   ").is_err());
+        assert!(clean_name(&"x".repeat(MAX_NAME_CHARS + 1)).is_err());
+        assert!(clean_name(&"é".repeat(MAX_NAME_CHARS)).is_ok());
+    }
+}
@@ -0,0 +1,212 @@
+use leptos::prelude::*;
+
+use crate::connection_utils;
+
+// Saved-connection fields for how a connection is organised
+const TAGS_FIELD: &str = "tags";
+const PINNED_FIELD: &str = "pinned";
+// Longest tag we keep, and how many one connection can have
+pub const MAX_TAG_CHARS: usize = 24;
+pub const MAX_TAGS: usize = 8;
+
+// A tag as it's stored: trimmed, lower case, inner spaces collapsed
+pub fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    if tag.is_empty() {
+        return Err("Type a tag first.".to_string());
+    }
+    if tag.chars().count() > MAX_TAG_CHARS {
+        return Err(format!("Tags can be at most {} characters.", MAX_TAG_CHARS));
+    }
+    Ok(tag)
+}
+
+pub fn load_tags(connection_id: &str) -> Vec<String> {
+    connection_utils::get_saved_connection(connection_id)
+        .and_then(|record| record.get(TAGS_FIELD).cloned())
+        .and_then(|tags| serde_json::from_value(tags).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_tags(connection_id: &str, tags: &[String]) {
+    connection_utils::update_saved_connection(connection_id, |record| {
+        if tags.is_empty() {
+            record.remove(TAGS_FIELD);
+        } else {
+            record.insert(TAGS_FIELD.to_string(), serde_json::json!(tags));
+        }
+    });
+}
+
+pub fn is_pinned(connection_id: &str) -> bool {
+    connection_utils::get_saved_connection(connection_id)
+        .and_then(|record| record.get(PINNED_FIELD).and_then(|v| v.as_bool()))
+        .unwrap_or(false)
+}
+
+pub fn set_pinned(connection_id: &str, pinned: bool) {
+    connection_utils::update_saved_connection(connection_id, |record| {
+        if pinned {
+            record.insert(PINNED_FIELD.to_string(), serde_json::Value::Bool(true));
+        } else {
+            record.remove(PINNED_FIELD);
+        }
+    });
+}
+
+// Every tag in use on a saved connection, sorted, for filter chips and suggestions
+pub fn known_tags() -> Vec<String> {
+    let mut tags: Vec<String> = connection_utils::load_saved_connections()
+        .iter()
+        .filter_map(|record| record.get(TAGS_FIELD).cloned())
+        .filter_map(|tags| serde_json::from_value::<Vec<String>>(tags).ok())
+        .flatten()
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+// Tags on a connection, with a way to add and remove them. Suggests tags
+// already used on other connections.
+#[component]
+pub fn TagEditor(
+    connection_id: String,
+    // Called with the new tags after every change
+    on_change: Callback<Vec<String>>,
+) -> impl IntoView {
+    let (tags, set_tags) = signal(load_tags(&connection_id));
+    let (draft, set_draft) = signal(String::new());
+    let (error, set_error) = signal(String::new());
+    let connection_id = StoredValue::new(connection_id);
+
+    let store = move |updated: Vec<String>| {
+        save_tags(&connection_id.get_value(), &updated);
+        set_tags.set(updated.clone());
+        on_change.run(updated);
+    };
+
+    let add_tag = move || {
+        let tag = match normalize_tag(&draft.get_untracked()) {
+            Ok(tag) => tag,
+            Err(e) => {
+                set_error.set(e);
+                return;
+            }
+        };
+        let mut updated = tags.get_untracked();
+        if !updated.contains(&tag) {
+            if updated.len() >= MAX_TAGS {
+                set_error.set(format!("A connection can have at most {} tags.", MAX_TAGS));
+                return;
+            }
+            updated.push(tag);
+            store(updated);
+        }
+        set_draft.set(String::new());
+        set_error.set(String::new());
+    };
+
+    view! {
+        <div data-test-id="tag-editor">
+            <div class="text-sm font-medium text-gray-200 mb-1">"Tags"</div>
+            <div class="flex flex-wrap gap-1 mb-2">
+                {move || tags.get().into_iter().map(|tag| {
+                    let removed = tag.clone();
+                    view! {
+                        <span class="flex items-center gap-1 px-2 py-0.5 rounded-full bg-gray-900 border border-gray-700 text-xs text-gray-300">
+                            {tag}
+                            <button
+                                class="text-gray-500 hover:text-gray-200"
+                                title="Remove tag"
+                                on:click=move |_| {
+                                    let mut updated = tags.get_untracked();
+                                    updated.retain(|t| *t != removed);
+                                    store(updated);
+                                }
+                            >
+                                "×"
+                            </button>
+                        </span>
+                    }
+                }).collect_view()}
+            </div>
+            <div class="flex gap-2">
+                <input
+                    type="text"
+                    class="w-full px-3 py-1 rounded bg-gray-900 border border-gray-700 text-sm text-gray-100"
+                    placeholder="Add a tag, e.g. game night"
+                    maxlength=MAX_TAG_CHARS.to_string()
+                    list="known-tags"
+                    prop:value=draft
+                    on:input=move |ev| {
+                        set_draft.set(event_target_value(&ev));
+                        set_error.set(String::new());
+                    }
+                    on:keydown=move |ev: web_sys::KeyboardEvent| {
+                        if ev.key() == "Enter" {
+                            add_tag();
+                        }
+                    }
+                />
+                <button
+                    class="px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200"
+                    on:click=move |_| add_tag()
+                >
+                    "Add"
+                </button>
+            </div>
+            <datalist id="known-tags">
+                {known_tags().into_iter().map(|tag| view! { <option value=tag></option> }).collect_view()}
+            </datalist>
+            {move || (!error.get().is_empty()).then(|| view! {
+                <div class="mt-1 text-sm text-red-400">{error.get()}</div>
+            })}
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_component::{test_connection, Connection, ConnectionStatus};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn saved_connection() -> Connection {
+        let connection = test_connection(&format!("tags-test-{}", uuid::Uuid::new_v4()), ConnectionStatus::Active);
+        connection_utils::save_connection_to_local_storage(&connection, "Friend").unwrap();
+        connection
+    }
+
+    #[wasm_bindgen_test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag("  Game   Night "), Ok("game night".to_string()));
+        assert!(normalize_tag("   ").is_err());
+        assert!(normalize_tag(&"x".repeat(MAX_TAG_CHARS + 1)).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_tags_and_pin_round_trip_through_saved_connection() {
+        let connection = saved_connection();
+        assert!(load_tags(&connection.id).is_empty());
+        assert!(!is_pinned(&connection.id));
+
+        save_tags(&connection.id, &["work".to_string(), "climbing".to_string()]);
+        set_pinned(&connection.id, true);
+        assert_eq!(load_tags(&connection.id), vec!["work", "climbing"]);
+        assert!(is_pinned(&connection.id));
+
+        let other = saved_connection();
+        save_tags(&other.id, &["work".to_string()]);
+        let known = known_tags();
+        assert!(known.contains(&"climbing".to_string()) && known.contains(&"work".to_string()));
+        assert_eq!(known.iter().filter(|tag| *tag == "work").count(), 1);
+
+        save_tags(&connection.id, &[]);
+        set_pinned(&connection.id, false);
+        assert!(load_tags(&connection.id).is_empty());
+        assert!(!is_pinned(&connection.id));
+    }
+}
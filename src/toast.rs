@@ -0,0 +1,322 @@
+use std::time::Duration;
+
+use leptos::prelude::*;
+
+// Most toasts shown at once; the oldest without a button make way for new
+// ones. Toasts with a button can briefly push the count past this.
+pub const MAX_TOASTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    // How long a toast stays up unless it's dismissed first. Problems stay
+    // longer so there's time to read them.
+    pub fn duration(self) -> Duration {
+        Duration::from_secs(match self {
+            Severity::Info | Severity::Success => 4,
+            Severity::Warning => 8,
+            Severity::Error => 12,
+        })
+    }
+
+    fn classes(self) -> &'static str {
+        match self {
+            Severity::Info => "bg-gray-800 border-gray-600 text-gray-200",
+            Severity::Success => "bg-green-900 border-green-700 text-green-100",
+            Severity::Warning => "bg-yellow-900 border-yellow-700 text-yellow-100",
+            Severity::Error => "bg-red-900 border-red-700 text-red-100",
+        }
+    }
+}
+
+// A button on a toast, such as "Undo"
+#[derive(Clone)]
+pub struct ToastAction {
+    pub label: String,
+    pub on_click: Callback<()>,
+}
+
+#[derive(Clone)]
+pub struct Toast {
+    pub id: u32,
+    pub severity: Severity,
+    pub message: String,
+    pub action: Option<ToastAction>,
+}
+
+// The queue of toasts on screen, shared through context so any component can
+// report how something went
+#[derive(Clone, Copy)]
+pub struct Toasts {
+    queue: RwSignal<Vec<Toast>>,
+    next_id: StoredValue<u32>,
+}
+
+impl Default for Toasts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Toasts {
+    pub fn new() -> Toasts {
+        Toasts {
+            queue: RwSignal::new(Vec::new()),
+            next_id: StoredValue::new(0),
+        }
+    }
+
+    // Toasts currently on screen, oldest first
+    pub fn shown(&self) -> Vec<Toast> {
+        self.queue.get()
+    }
+
+    pub fn info(&self, message: impl Into<String>) {
+        self.show(Severity::Info, message);
+    }
+
+    pub fn success(&self, message: impl Into<String>) {
+        self.show(Severity::Success, message);
+    }
+
+    pub fn warning(&self, message: impl Into<String>) {
+        self.show(Severity::Warning, message);
+    }
+
+    pub fn error(&self, message: impl Into<String>) {
+        self.show(Severity::Error, message);
+    }
+
+    pub fn show(&self, severity: Severity, message: impl Into<String>) -> u32 {
+        self.push(severity, message.into(), None, severity.duration())
+    }
+
+    // A toast with a button, kept up for `lasting`. The toast closes once the
+    // button is used.
+    pub fn show_with_action(
+        &self,
+        severity: Severity,
+        message: impl Into<String>,
+        label: impl Into<String>,
+        on_click: Callback<()>,
+        lasting: Duration,
+    ) -> u32 {
+        let action = ToastAction { label: label.into(), on_click };
+        self.push(severity, message.into(), Some(action), lasting)
+    }
+
+    // Take a toast down; false if it had already gone
+    pub fn dismiss(&self, id: u32) -> bool {
+        let mut found = false;
+        self.queue.update(|queue| {
+            let before = queue.len();
+            queue.retain(|toast| toast.id != id);
+            found = queue.len() != before;
+        });
+        found
+    }
+
+    fn push(&self, severity: Severity, message: String, action: Option<ToastAction>, lasting: Duration) -> u32 {
+        // The same note twice in a row is shown once
+        if action.is_none() {
+            let repeated = self.queue.with_untracked(|queue| {
+                queue
+                    .iter()
+                    .find(|toast| toast.action.is_none() && toast.severity == severity && toast.message == message)
+                    .map(|toast| toast.id)
+            });
+            if let Some(id) = repeated {
+                return id;
+            }
+        }
+
+        let id = self.next_id.get_value();
+        self.next_id.set_value(id.wrapping_add(1));
+        self.queue.update(|queue| {
+            queue.push(Toast { id, severity, message, action });
+            // A toast with a button, such as Undo, may be the only way to take
+            // something back, so only plain ones are pushed out
+            while queue.len() > MAX_TOASTS {
+                let older = &queue[..queue.len() - 1];
+                let Some(index) = older.iter().position(|toast| toast.action.is_none()) else {
+                    break;
+                };
+                queue.remove(index);
+            }
+        });
+
+        let toasts = *self;
+        set_timeout(
+            move || {
+                toasts.dismiss(id);
+            },
+            lasting,
+        );
+        id
+    }
+}
+
+// Share a toast queue with everything below the current component
+pub fn provide_toasts() -> Toasts {
+    let toasts = Toasts::new();
+    provide_context(toasts);
+    toasts
+}
+
+// The nearest toast queue. A component outside `provide_toasts` would show its
+// toasts nowhere, so that's treated as a bug rather than papered over.
+pub fn use_toasts() -> Toasts {
+    expect_context::<Toasts>()
+}
+
+// Where the toasts appear. The region is always in the page so screen readers
+// announce toasts as they arrive; errors interrupt, everything else waits.
+#[component]
+pub fn ToastRegion() -> impl IntoView {
+    let toasts = use_toasts();
+
+    view! {
+        <div
+            class="sticky bottom-4 flex flex-col gap-2"
+            class:mt-4=move || !toasts.queue.with(|queue| queue.is_empty())
+            role="status"
+            aria-live="polite"
+            data-test-id="toasts"
+        >
+            <For
+                each=move || toasts.shown()
+                key=|toast| toast.id
+                let:toast
+            >
+                {
+                    let id = toast.id;
+                    let error = toast.severity == Severity::Error;
+                    view! {
+                        <div
+                            class=format!("flex justify-between items-center gap-4 p-3 rounded border shadow-lg text-sm {}", toast.severity.classes())
+                            role=error.then_some("alert")
+                            data-test-id="toast"
+                        >
+                            <span>{toast.message.clone()}</span>
+                            <div class="flex gap-2 items-center">
+                                {toast.action.clone().map(|action| view! {
+                                    <button
+                                        class="px-3 py-1 bg-indigo-600 hover:bg-indigo-700 rounded text-sm text-gray-100"
+                                        on:click=move |_| {
+                                            toasts.dismiss(id);
+                                            action.on_click.run(());
+                                        }
+                                    >
+                                        {action.label.clone()}
+                                    </button>
+                                })}
+                                <button
+                                    class="text-gray-400 hover:text-gray-100"
+                                    title="Dismiss"
+                                    aria-label="Dismiss"
+                                    on:click=move |_| {
+                                        toasts.dismiss(id);
+                                    }
+                                >
+                                    "×"
+                                </button>
+                            </div>
+                        </div>
+                    }
+                }
+            </For>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_show_and_dismiss() {
+        let toasts = Toasts::new();
+        let first = toasts.show(Severity::Info, "Saved");
+        let second = toasts.show(Severity::Error, "Couldn't send");
+        assert_ne!(first, second);
+        assert_eq!(toasts.shown().len(), 2);
+        assert_eq!(toasts.shown()[1].severity, Severity::Error);
+
+        assert!(toasts.dismiss(first));
+        assert!(!toasts.dismiss(first));
+        assert_eq!(toasts.shown().len(), 1);
+        assert_eq!(toasts.shown()[0].message, "Couldn't send");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_repeats_are_shown_once_and_queue_is_capped() {
+        let toasts = Toasts::new();
+        let first = toasts.show(Severity::Warning, "You're offline");
+        assert_eq!(toasts.show(Severity::Warning, "You're offline"), first);
+        assert_eq!(toasts.shown().len(), 1);
+
+        for n in 0..MAX_TOASTS {
+            toasts.info(format!("Note {}", n));
+        }
+        let shown = toasts.shown();
+        assert_eq!(shown.len(), MAX_TOASTS);
+        // The oldest made way
+        assert!(shown.iter().all(|toast| toast.id != first));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_actions_are_kept_apart() {
+        let toasts = Toasts::new();
+        let plain = toasts.show(Severity::Info, "Deleted Sam");
+        let with_undo = toasts.show_with_action(
+            Severity::Info,
+            "Deleted Sam",
+            "Undo",
+            Callback::new(|_| {}),
+            Duration::from_secs(6),
+        );
+        assert_ne!(plain, with_undo);
+        let shown = toasts.shown();
+        assert_eq!(shown[1].action.as_ref().map(|a| a.label.as_str()), Some("Undo"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_toasts_with_actions_are_not_pushed_out() {
+        let toasts = Toasts::new();
+        let undo = toasts.show_with_action(
+            Severity::Info,
+            "Left Sam",
+            "Undo",
+            Callback::new(|_| {}),
+            Duration::from_secs(6),
+        );
+        for n in 0..MAX_TOASTS + 2 {
+            toasts.info(format!("Note {}", n));
+        }
+        let shown = toasts.shown();
+        assert_eq!(shown.len(), MAX_TOASTS);
+        assert!(shown.iter().any(|toast| toast.id == undo));
+
+        // Even when every toast has a button, the new one still shows
+        let toasts = Toasts::new();
+        for n in 0..MAX_TOASTS + 1 {
+            toasts.show_with_action(Severity::Info, format!("Deleted {}", n), "Undo", Callback::new(|_| {}), Duration::from_secs(6));
+        }
+        assert_eq!(toasts.shown().len(), MAX_TOASTS + 1);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_problems_stay_up_longer() {
+        assert!(Severity::Error.duration() > Severity::Warning.duration());
+        assert!(Severity::Warning.duration() > Severity::Info.duration());
+    }
+}
@@ -0,0 +1,279 @@
+use serde::{Deserialize, Serialize};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::window;
+
+use crate::api_retry::RetryPolicy;
+use crate::archive;
+use crate::browser::{console_log, storage};
+use crate::connect_component::{get_connection_name, get_stored_player_id, Connection};
+use crate::connection_utils;
+use crate::outbox::{self, OutboxAction};
+
+// How long a destructive action can be undone before it's carried out
+pub const UNDO_SECS: u64 = 6;
+// localStorage key for deletes and leaves still inside their undo window, so
+// they're carried out even if the page closes before the window does
+const PENDING_KEY: &str = "pending-undo";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UndoAction {
+    // Forget connections on this device
+    Delete,
+    // Forget a connection and give up our seat on the server
+    Leave,
+    // Hide connections in the archive
+    Archive,
+}
+
+// How a connection was stored before an action touched it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    connection_id: String,
+    position: Option<(usize, serde_json::Value)>,
+    name: Option<String>,
+}
+
+// An action that has taken effect on screen but can still be taken back
+#[derive(Debug, Clone)]
+pub struct PendingUndo {
+    pub id: u32,
+    pub action: UndoAction,
+    pub connections: Vec<Connection>,
+    snapshots: Vec<Snapshot>,
+    pub message: String,
+    // Which stored `PendingCommit` belongs to this action
+    key: String,
+}
+
+// A delete or leave waiting out its undo window, as stored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PendingCommit {
+    key: String,
+    action: UndoAction,
+    connection_ids: Vec<String>,
+}
+
+fn load_pending() -> Vec<PendingCommit> {
+    storage()
+        .and_then(|s| s.get_item(PENDING_KEY).ok().flatten())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn store_pending(pending: &[PendingCommit]) {
+    let Some(storage) = storage() else {
+        return;
+    };
+    if pending.is_empty() {
+        let _ = storage.remove_item(PENDING_KEY);
+    } else if let Ok(json) = serde_json::to_string(pending) {
+        let _ = storage.set_item(PENDING_KEY, &json);
+    }
+}
+
+// The action was undone or carried out, so it no longer needs finishing later
+fn settle(key: &str) {
+    let mut pending = load_pending();
+    pending.retain(|commit| commit.key != key);
+    store_pending(&pending);
+}
+
+// Forget the connections on this device and, for a leave, give up our seats
+fn carry_out(action: UndoAction, connection_ids: Vec<String>) {
+    if action == UndoAction::Archive {
+        return;
+    }
+    for connection_id in &connection_ids {
+        connection_utils::forget_connection(connection_id);
+    }
+    if action == UndoAction::Leave {
+        let Some(player_id) = get_stored_player_id() else {
+            return;
+        };
+        for connection_id in connection_ids {
+            let player_id = player_id.clone();
+            spawn_local(async move {
+                let client_id = uuid::Uuid::new_v4().to_string();
+                match connection_utils::leave_connection(&connection_id, &player_id, &client_id, &RetryPolicy::user_action()).await {
+                    Ok(()) => {},
+                    Err(e) if e.is_network() || !outbox::is_online() => {
+                        outbox::enqueue(&player_id, OutboxAction::Leave { connection_id });
+                    },
+                    Err(e) => console_log(&format!("Couldn't leave {}: {:?}", connection_id, e)),
+                }
+            });
+        }
+    }
+}
+
+// Carry out deletes and leaves whose undo window was cut short by the page
+// closing or reloading. On screen they had already happened.
+pub fn finish_interrupted() {
+    let interrupted = load_pending();
+    if interrupted.is_empty() {
+        return;
+    }
+    store_pending(&[]);
+    for commit in interrupted {
+        console_log(&format!("Finishing an interrupted {:?} of {} connection(s)", commit.action, commit.connection_ids.len()));
+        carry_out(commit.action, commit.connection_ids);
+    }
+}
+
+pub fn snapshot(connection_id: &str) -> Snapshot {
+    let position = connection_utils::load_saved_connections()
+        .into_iter()
+        .enumerate()
+        .find(|(_, record)| record.get("id").and_then(|v| v.as_str()) == Some(connection_id));
+    let name = window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .and_then(|storage| storage.get_item(&format!("conn-name-{}", connection_id)).ok().flatten());
+    Snapshot { connection_id: connection_id.to_string(), position, name }
+}
+
+// Put a connection back exactly as the snapshot found it
+pub fn restore(snapshot: &Snapshot) {
+    if let Some(name) = &snapshot.name {
+        connection_utils::rename_saved_connection(&snapshot.connection_id, name);
+    }
+    if let Some((index, record)) = &snapshot.position {
+        connection_utils::restore_saved_connection(*index, record.clone());
+    }
+}
+
+fn describe(action: UndoAction, connections: &[Connection]) -> String {
+    let verb = match action {
+        UndoAction::Delete => "Deleted",
+        UndoAction::Leave => "Left",
+        UndoAction::Archive => "Archived",
+    };
+    match connections {
+        [one] => format!("{} {}", verb, get_connection_name(&one.id).unwrap_or_else(|| "connection".to_string())),
+        many => format!("{} {} connections", verb, many.len()),
+    }
+}
+
+impl PendingUndo {
+    // Remember how the connections are stored now, then apply whatever part of
+    // the action can't wait. Archiving is only a flag, so it happens at once;
+    // deleting and leaving wait for `commit`, and are written down until then
+    // so `finish_interrupted` can carry them out if the page goes away first.
+    pub fn start(id: u32, action: UndoAction, connections: Vec<Connection>) -> PendingUndo {
+        let snapshots = connections.iter().map(|c| snapshot(&c.id)).collect();
+        let message = describe(action, &connections);
+        let key = uuid::Uuid::new_v4().to_string();
+        if action == UndoAction::Archive {
+            for connection in &connections {
+                archive::set_archived(&connection.id, true);
+            }
+        } else {
+            let mut pending = load_pending();
+            pending.push(PendingCommit {
+                key: key.clone(),
+                action,
+                connection_ids: connections.iter().map(|c| c.id.clone()).collect(),
+            });
+            store_pending(&pending);
+        }
+        PendingUndo { id, action, connections, snapshots, message, key }
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.connections.iter().map(|c| c.id.clone()).collect()
+    }
+
+    // Take the action back
+    pub fn undo(&self) {
+        settle(&self.key);
+        for snapshot in &self.snapshots {
+            restore(snapshot);
+        }
+        console_log(&format!("Undid: {}", self.message));
+    }
+
+    // The undo window has closed; carry the action out for good
+    pub fn commit(self) {
+        settle(&self.key);
+        let ids = self.ids();
+        carry_out(self.action, ids);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_component::{test_connection, ConnectionStatus};
+    use crate::{e2e, notifications, tags};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn saved_connection(name: &str) -> Connection {
+        let connection = test_connection(&format!("undo-test-{}", uuid::Uuid::new_v4()), ConnectionStatus::Expired);
+        connection_utils::save_connection_to_local_storage(&connection, name).unwrap();
+        connection_utils::rename_saved_connection(&connection.id, name);
+        connection
+    }
+
+    #[wasm_bindgen_test]
+    fn test_undo_restores_the_exact_record() {
+        let connection = saved_connection("Sam");
+        connection_utils::update_saved_connection(&connection.id, |record| {
+            record.insert("note".to_string(), serde_json::json!("keep me"));
+        });
+        let before = connection_utils::get_saved_connection(&connection.id);
+
+        let pending = PendingUndo::start(1, UndoAction::Delete, vec![connection.clone()]);
+        assert_eq!(pending.message, "Deleted Sam");
+        // Nothing is removed until the undo window closes
+        assert!(connection_utils::get_saved_connection(&connection.id).is_some());
+
+        pending.undo();
+        assert_eq!(connection_utils::get_saved_connection(&connection.id), before);
+        assert_eq!(get_connection_name(&connection.id).as_deref(), Some("Sam"));
+
+        // Undone actions aren't carried out later either
+        finish_interrupted();
+        assert_eq!(connection_utils::get_saved_connection(&connection.id), before);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_interrupted_delete_is_finished_on_next_start() {
+        let connection = saved_connection("Robin");
+        // The page goes away inside the undo window, so neither undo nor commit runs
+        let pending = PendingUndo::start(4, UndoAction::Delete, vec![connection.clone()]);
+        drop(pending);
+        assert!(connection_utils::get_saved_connection(&connection.id).is_some());
+
+        finish_interrupted();
+        assert!(connection_utils::get_saved_connection(&connection.id).is_none());
+        assert!(load_pending().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_archive_applies_at_once_and_undoes() {
+        let connection = saved_connection("Kim");
+        let pending = PendingUndo::start(2, UndoAction::Archive, vec![connection.clone()]);
+        assert!(archive::is_archived(&connection.id));
+
+        pending.undo();
+        assert!(!archive::is_archived(&connection.id));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_commit_deletes() {
+        let connection = saved_connection("Alex");
+        e2e::create_connection_secret(&connection.link_id).unwrap();
+        notifications::set_muted(&connection.id, true);
+        tags::set_pinned(&connection.id, true);
+
+        PendingUndo::start(3, UndoAction::Delete, vec![connection.clone()]).commit();
+        assert!(connection_utils::get_saved_connection(&connection.id).is_none());
+        assert!(get_connection_name(&connection.id).is_none());
+        // Nothing else about it is left behind
+        assert!(!e2e::has_connection_key(&connection.link_id));
+        assert!(!notifications::is_muted(&connection.id));
+        assert!(!tags::is_pinned(&connection.id));
+        assert!(load_pending().is_empty());
+    }
+}
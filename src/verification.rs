@@ -0,0 +1,183 @@
+use wasm_bindgen::prelude::*;
+
+use crate::connection_utils;
+use crate::webcrypto;
+
+// Safety numbers let two friends check, out of band, that they hold each
+// other's real public keys and the server hasn't swapped one in the middle.
+
+// Domain separation for the safety number hash; bump if the format changes
+const SAFETY_NUMBER_CONTEXT: &[u8] = b"friends-connect-safety-v1";
+
+// 64 easy-to-name emoji; each one encodes 6 bits of the hash
+const EMOJI: [&str; 64] = [
+    "🐶", "🐱", "🐭", "🐹", "🐰", "🦊", "🐻", "🐼",
+    "🐨", "🐯", "🦁", "🐮", "🐷", "🐸", "🐵", "🐔",
+    "🐧", "🐦", "🦆", "🦉", "🐴", "🦄", "🐝", "🐛",
+    "🦋", "🐌", "🐞", "🐢", "🐍", "🐙", "🦀", "🐬",
+    "🐳", "🦈", "🐘", "🦒", "🦓", "🐪", "🌵", "🌲",
+    "🌻", "🍄", "🌙", "⭐", "🔥", "🌈", "❄", "☂",
+    "🍎", "🍌", "🍇", "🍓", "🍒", "🍍", "🥕", "🌽",
+    "🍕", "🎈", "🎁", "🎸", "🚀", "⚓", "🔑", "⏰",
+];
+
+// Number of emoji shown; 8 × 6 bits = 48 bits of the hash
+const EMOJI_COUNT: usize = 8;
+// Digit groups shown alongside, for reading out over the phone
+const DIGIT_GROUPS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SafetyNumber {
+    pub emoji: Vec<&'static str>,
+    pub digits: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationState {
+    // We haven't compared safety numbers yet
+    Unverified,
+    // The friend's current key is the one we verified
+    Verified,
+    // The friend's key differs from the one we saw before
+    KeyChanged,
+}
+
+// Turn a hash into the emoji and digit groups shown to the user
+pub fn safety_number_from_digest(digest: &[u8]) -> SafetyNumber {
+    // Read the first 48 bits six at a time
+    let bits = digest[..6].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+    let emoji = (0..EMOJI_COUNT)
+        .map(|i| EMOJI[((bits >> (42 - 6 * i)) & 0x3f) as usize])
+        .collect();
+
+    let digits = (0..DIGIT_GROUPS)
+        .map(|i| {
+            let start = 6 + i * 4;
+            let chunk = u32::from_be_bytes([digest[start], digest[start + 1], digest[start + 2], digest[start + 3]]);
+            format!("{:05}", chunk % 100_000)
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    SafetyNumber { emoji, digits }
+}
+
+// Safety number for two public keys (base64url, raw P-256). The keys are
+// sorted first so both friends see the same result.
+pub async fn compute_safety_number(key_a: &str, key_b: &str) -> Result<SafetyNumber, JsValue> {
+    let mut keys = [webcrypto::base64url_decode(key_a)?, webcrypto::base64url_decode(key_b)?];
+    keys.sort();
+
+    let mut input = SAFETY_NUMBER_CONTEXT.to_vec();
+    for key in &keys {
+        input.extend_from_slice(key);
+    }
+
+    let digest = webcrypto::sha256(&input).await?;
+    Ok(safety_number_from_digest(&digest))
+}
+
+// Verification fields stored in the saved connection record
+const FRIEND_KEY_FIELD: &str = "friend_public_key";
+const VERIFIED_KEY_FIELD: &str = "verified_public_key";
+const KEY_CHANGED_FIELD: &str = "friend_key_changed";
+
+fn string_field(record: &serde_json::Map<String, serde_json::Value>, field: &str) -> Option<String> {
+    record.get(field).and_then(|v| v.as_str()).map(|v| v.to_string())
+}
+
+pub fn verification_state(connection_id: &str) -> VerificationState {
+    let Some(record) = connection_utils::get_saved_connection(connection_id) else {
+        return VerificationState::Unverified;
+    };
+
+    if record.get(KEY_CHANGED_FIELD).and_then(|v| v.as_bool()).unwrap_or(false) {
+        return VerificationState::KeyChanged;
+    }
+
+    match (string_field(&record, FRIEND_KEY_FIELD), string_field(&record, VERIFIED_KEY_FIELD)) {
+        (Some(current), Some(verified)) if current == verified => VerificationState::Verified,
+        _ => VerificationState::Unverified,
+    }
+}
+
+// Remember the key we just fetched for our friend. A different key from last
+// time drops any verification and flags the change until it is re-verified.
+pub fn record_friend_key(connection_id: &str, friend_player_id: &str, public_key: &str) -> VerificationState {
+    connection_utils::update_saved_connection(connection_id, |record| {
+        let previous = string_field(record, FRIEND_KEY_FIELD);
+        if previous.as_deref().is_some_and(|key| key != public_key) {
+            record.insert(KEY_CHANGED_FIELD.to_string(), serde_json::Value::Bool(true));
+            record.remove(VERIFIED_KEY_FIELD);
+        }
+        record.insert("friend_player_id".to_string(), serde_json::Value::String(friend_player_id.to_string()));
+        record.insert(FRIEND_KEY_FIELD.to_string(), serde_json::Value::String(public_key.to_string()));
+    });
+
+    verification_state(connection_id)
+}
+
+// The user compared safety numbers and they matched
+pub fn mark_verified(connection_id: &str, public_key: &str) {
+    connection_utils::update_saved_connection(connection_id, |record| {
+        record.insert(FRIEND_KEY_FIELD.to_string(), serde_json::Value::String(public_key.to_string()));
+        record.insert(VERIFIED_KEY_FIELD.to_string(), serde_json::Value::String(public_key.to_string()));
+        record.remove(KEY_CHANGED_FIELD);
+    });
+}
+
+pub fn clear_verification(connection_id: &str) {
+    connection_utils::update_saved_connection(connection_id, |record| {
+        record.remove(VERIFIED_KEY_FIELD);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connect_component::{test_connection, ConnectionStatus};
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_safety_number_from_digest_is_stable() {
+        let digest = [0u8; 32];
+        let number = safety_number_from_digest(&digest);
+        assert_eq!(number.emoji, vec!["🐶"; EMOJI_COUNT]);
+        assert_eq!(number.digits, "00000 00000 00000 00000");
+
+        let digest: Vec<u8> = (0u8..32).collect();
+        assert_eq!(safety_number_from_digest(&digest), safety_number_from_digest(&digest));
+        assert_ne!(safety_number_from_digest(&digest).emoji, number.emoji);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_safety_number_is_symmetric() {
+        let alice = webcrypto::base64url_encode(&[1u8; 65]);
+        let bob = webcrypto::base64url_encode(&[2u8; 65]);
+        let mallory = webcrypto::base64url_encode(&[3u8; 65]);
+
+        let from_alice = compute_safety_number(&alice, &bob).await.unwrap();
+        let from_bob = compute_safety_number(&bob, &alice).await.unwrap();
+        assert_eq!(from_alice, from_bob);
+
+        let intercepted = compute_safety_number(&alice, &mallory).await.unwrap();
+        assert_ne!(from_alice, intercepted);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_key_change_drops_verification() {
+        let connection = test_connection(&format!("verify-test-{}", uuid::Uuid::new_v4()), ConnectionStatus::Active);
+        connection_utils::save_connection_to_local_storage(&connection, "Friend").unwrap();
+
+        assert_eq!(record_friend_key(&connection.id, "friend", "key-1"), VerificationState::Unverified);
+        mark_verified(&connection.id, "key-1");
+        assert_eq!(record_friend_key(&connection.id, "friend", "key-1"), VerificationState::Verified);
+
+        assert_eq!(record_friend_key(&connection.id, "friend", "key-2"), VerificationState::KeyChanged);
+
+        mark_verified(&connection.id, "key-2");
+        assert_eq!(verification_state(&connection.id), VerificationState::Verified);
+    }
+}
@@ -0,0 +1,175 @@
+use leptos::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+
+use crate::api_retry::RetryPolicy;
+use crate::browser::console_log;
+use crate::connect_component::Connection;
+use crate::connection_utils;
+use crate::identity;
+use crate::verification::{self, SafetyNumber, VerificationState};
+use crate::webcrypto;
+
+// The friend's public key, once fetched and checked
+#[derive(Debug, Clone, PartialEq)]
+struct FriendKey {
+    player_id: String,
+    public_key: String,
+    // Whether their player id is derived from this key (older ids aren't)
+    id_matches_key: bool,
+}
+
+// Find the other player and fetch their key from the server. Only one-to-one
+// connections have a single other player to verify.
+async fn fetch_friend_key(connection: &Connection, me: &str) -> Result<FriendKey, String> {
+    if connection.is_group() {
+        return Err("Safety numbers can only be compared on one-to-one connections.".to_string());
+    }
+    let policy = RetryPolicy::user_action();
+    let current = connection_utils::get_connection_by_link_id(&connection.link_id, &policy)
+        .await
+        .map_err(|e| e.as_string().unwrap_or_else(|| format!("{:?}", e)))?;
+
+    let friend_id = current
+        .players
+        .into_iter()
+        .find(|player| player != me)
+        .ok_or_else(|| "Your friend hasn't joined yet.".to_string())?;
+
+    let public_key = connection_utils::get_player_public_key(&friend_id, &policy)
+        .await
+        .map_err(|e| e.as_string().unwrap_or_else(|| format!("{:?}", e)))?;
+
+    let raw_key = webcrypto::base64url_decode(&public_key)
+        .map_err(|_| "Your friend's public key is malformed.".to_string())?;
+    let hash = webcrypto::sha256(&raw_key)
+        .await
+        .map_err(|e| format!("{:?}", e))?;
+
+    Ok(FriendKey {
+        id_matches_key: identity::player_id_from_public_key_hash(&hash) == friend_id,
+        player_id: friend_id,
+        public_key,
+    })
+}
+
+// Compare safety numbers with the friend on a connection
+#[component]
+pub fn VerifyFriend(#[prop(into)] connection: Connection) -> impl IntoView {
+    let connection_id = StoredValue::new(connection.id.clone());
+    let (expanded, set_expanded) = signal(false);
+    let (state, set_state) = signal(verification::verification_state(&connection.id));
+    let (friend_key, set_friend_key) = signal(None::<FriendKey>);
+    let (safety_number, set_safety_number) = signal(None::<SafetyNumber>);
+    let (status, set_status) = signal(String::new());
+
+    // Check the friend's key as soon as the connection is opened, so a changed
+    // key is flagged even if the user never expands the panel
+    spawn_local(async move {
+        let Some(me) = identity::load_identity() else {
+            set_status.set("Verification needs a secure identity, which requires HTTPS.".to_string());
+            return;
+        };
+
+        let friend = match fetch_friend_key(&connection, &me.player_id).await {
+            Ok(friend) => friend,
+            Err(e) => {
+                console_log(&format!("Couldn't load friend's key: {}", e));
+                set_status.set(e);
+                return;
+            }
+        };
+
+        set_state.set(verification::record_friend_key(&connection.id, &friend.player_id, &friend.public_key));
+
+        match verification::compute_safety_number(&me.public_key, &friend.public_key).await {
+            Ok(number) => set_safety_number.set(Some(number)),
+            Err(e) => set_status.set(format!("Couldn't compute the safety number: {:?}", e)),
+        }
+        set_friend_key.set(Some(friend));
+    });
+
+    let handle_mark_verified = move |_| {
+        if let Some(friend) = friend_key.get() {
+            verification::mark_verified(&connection_id.get_value(), &friend.public_key);
+            set_state.set(VerificationState::Verified);
+        }
+    };
+
+    let handle_clear = move |_| {
+        verification::clear_verification(&connection_id.get_value());
+        set_state.set(verification::verification_state(&connection_id.get_value()));
+    };
+
+    view! {
+        <div class="mt-4 border-t border-gray-700 pt-4" data-test-id="verify-friend">
+            <div class="flex justify-between items-center">
+                <span class="text-sm font-medium text-gray-200">
+                    {move || match state.get() {
+                        VerificationState::Verified => "✓ Friend verified",
+                        VerificationState::KeyChanged => "⚠ Friend's key changed",
+                        VerificationState::Unverified => "Friend not verified",
+                    }}
+                </span>
+                <button
+                    class="px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200"
+                    data-test-id="verify-friend-toggle"
+                    on:click=move |_| set_expanded.update(|open| *open = !*open)
+                >
+                    {move || if expanded.get() { "Hide" } else { "Verify friend" }}
+                </button>
+            </div>
+
+            {move || (state.get() == VerificationState::KeyChanged).then(|| view! {
+                <div class="mt-2 text-sm text-yellow-300" data-test-id="key-changed-warning">
+                    "Your friend's key is different from the one you saw before. They may have \
+                     restored a new identity, or someone may be impersonating them. Compare the \
+                     safety number again before trusting this connection."
+                </div>
+            })}
+
+            {move || expanded.get().then(|| view! {
+                <div class="mt-3">
+                    {move || safety_number.get().map(|number| view! {
+                        <div>
+                            <div class="text-sm text-gray-400">
+                                "Compare this with your friend's screen, in person or over a call:"
+                            </div>
+                            <div class="mt-2 text-2xl tracking-widest text-center" data-test-id="safety-emoji">
+                                {number.emoji.concat()}
+                            </div>
+                            <div class="mt-1 font-mono text-center text-gray-300" data-test-id="safety-digits">
+                                {number.digits.clone()}
+                            </div>
+                        </div>
+                    })}
+                    {move || friend_key.get().filter(|friend| !friend.id_matches_key).map(|_| view! {
+                        <div class="mt-2 text-xs text-gray-400">
+                            "Your friend's player id predates key-based identities, so only the safety number vouches for them."
+                        </div>
+                    })}
+                    <div class="flex justify-end gap-2 mt-2">
+                        {move || (state.get() == VerificationState::Verified).then(|| view! {
+                            <button
+                                class="px-3 py-1 bg-gray-800 hover:bg-gray-600 border border-gray-600 rounded text-sm text-gray-200"
+                                on:click=handle_clear
+                            >
+                                "Unverify"
+                            </button>
+                        })}
+                        <button
+                            class="px-3 py-1 bg-green-600 hover:bg-green-700 rounded text-sm text-gray-100"
+                            data-test-id="mark-verified"
+                            prop:disabled=move || friend_key.get().is_none() || state.get() == VerificationState::Verified
+                            on:click=handle_mark_verified
+                        >
+                            "Mark as verified"
+                        </button>
+                    </div>
+                    {move || (!status.get().is_empty()).then(|| view! {
+                        <div class="mt-2 text-sm text-gray-300">{status.get()}</div>
+                    })}
+                </div>
+            })}
+        </div>
+    }
+}
@@ -0,0 +1,101 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use js_sys::{Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{window, CryptoKey, SubtleCrypto};
+
+// Thin helpers over the browser's SubtleCrypto API
+
+// crypto.subtle only exists in secure contexts (HTTPS or localhost)
+pub fn is_available() -> bool {
+    window()
+        .and_then(|w| w.crypto().ok())
+        .map(|c| Reflect::has(&c, &JsValue::from_str("subtle")).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+pub fn subtle() -> Result<SubtleCrypto, JsValue> {
+    if !is_available() {
+        return Err(JsValue::from_str("WebCrypto needs HTTPS or localhost"));
+    }
+
+    let window = window().ok_or_else(|| JsValue::from_str("No window found"))?;
+    Ok(window.crypto()?.subtle())
+}
+
+// Build an algorithm/parameter object, e.g. { name: "ECDSA", namedCurve: "P-256" }
+pub fn params(entries: &[(&str, JsValue)]) -> Result<Object, JsValue> {
+    let object = Object::new();
+    for (key, value) in entries {
+        Reflect::set(&object, &JsValue::from_str(key), value)?;
+    }
+    Ok(object)
+}
+
+// Key usages as the JS array SubtleCrypto expects
+pub fn usages(list: &[&str]) -> JsValue {
+    list.iter()
+        .map(|usage| JsValue::from_str(usage))
+        .collect::<js_sys::Array>()
+        .into()
+}
+
+// Bytes as a Uint8Array, for parameters like salt and iv
+pub fn bytes_value(bytes: &[u8]) -> JsValue {
+    Uint8Array::from(bytes).into()
+}
+
+// Await a promise resolving to an ArrayBuffer and copy it out
+pub async fn await_bytes(promise: Result<js_sys::Promise, JsValue>) -> Result<Vec<u8>, JsValue> {
+    let buffer = JsFuture::from(promise?).await?;
+    Ok(Uint8Array::new(&buffer).to_vec())
+}
+
+// Await a promise resolving to a CryptoKey
+pub async fn await_key(promise: Result<js_sys::Promise, JsValue>) -> Result<CryptoKey, JsValue> {
+    Ok(JsFuture::from(promise?).await?.unchecked_into())
+}
+
+pub fn random_bytes(len: usize) -> Result<Vec<u8>, JsValue> {
+    let window = window().ok_or_else(|| JsValue::from_str("No window found"))?;
+    let mut bytes = vec![0u8; len];
+    window.crypto()?.get_random_values_with_u8_array(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub async fn sha256(data: &[u8]) -> Result<Vec<u8>, JsValue> {
+    await_bytes(subtle()?.digest_with_str_and_u8_array("SHA-256", data)).await
+}
+
+// Export a key as a JWK, serialised with serde_json so it can be stored
+pub async fn export_jwk(key: &CryptoKey) -> Result<serde_json::Value, JsValue> {
+    let jwk = JsFuture::from(subtle()?.export_key("jwk", key)?).await?;
+    let json: String = js_sys::JSON::stringify(&jwk)?.into();
+    serde_json::from_str(&json).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+// Import a JWK previously produced by `export_jwk`
+pub async fn import_jwk(
+    jwk: &serde_json::Value,
+    algorithm: &Object,
+    extractable: bool,
+    key_usages: &[&str],
+) -> Result<CryptoKey, JsValue> {
+    let jwk: Object = js_sys::JSON::parse(&jwk.to_string())?.unchecked_into();
+    await_key(subtle()?.import_key_with_object("jwk", &jwk, algorithm, extractable, &usages(key_usages))).await
+}
+
+pub fn base64url_encode(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn base64url_decode(text: &str) -> Result<Vec<u8>, JsValue> {
+    URL_SAFE_NO_PAD
+        .decode(text.trim())
+        .map_err(|e| JsValue::from_str(&format!("Invalid base64: {}", e)))
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
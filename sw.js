@@ -0,0 +1,128 @@
+// Service worker for the Friends Connect app shell.
+//
+// Trunk gives every build's wasm/JS/CSS a content hash in its filename, and
+// index.html is the only file that knows the current names. On install we read
+// index.html, precache the assets it links to and name the cache after those
+// hashes, so a new deployment always gets a fresh cache and old ones are
+// dropped on activate.
+
+const CACHE_PREFIX = "hello-friends-";
+const RUNTIME_CACHE = CACHE_PREFIX + "runtime";
+const SCOPE = self.registration.scope;
+const INDEX_URL = new URL("index.html", SCOPE).toString();
+
+// Cross-origin resources the shell needs to render offline
+const RUNTIME_HOSTS = ["cdn.tailwindcss.com"];
+
+// Pull every local asset referenced by index.html (Trunk injects these)
+function assetsFromIndex(html) {
+  const urls = new Set([INDEX_URL, new URL("manifest.json", SCOPE).toString()]);
+  const pattern = /(?:href|src)="([^"]+)"/g;
+  let match;
+  while ((match = pattern.exec(html)) !== null) {
+    const url = new URL(match[1], SCOPE);
+    if (url.origin === self.location.origin) {
+      urls.add(url.toString());
+    }
+  }
+  return [...urls];
+}
+
+// Cache name derived from the hashed asset filenames of this build
+function cacheNameFor(assets) {
+  const hashes = assets
+    .map((url) => (url.match(/-([0-9a-f]{8,})(?:_bg)?\.(?:js|wasm|css)$/) || [])[1])
+    .filter(Boolean)
+    .sort();
+  return CACHE_PREFIX + (hashes.length ? hashes.join("-") : "dev");
+}
+
+async function currentBuild() {
+  const response = await fetch(INDEX_URL, { cache: "no-store" });
+  const html = await response.text();
+  const assets = assetsFromIndex(html);
+  return { name: cacheNameFor(assets), assets, html };
+}
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(
+    (async () => {
+      const build = await currentBuild();
+      const cache = await caches.open(build.name);
+      await cache.addAll(build.assets.filter((url) => url !== INDEX_URL));
+      await cache.put(INDEX_URL, new Response(build.html, {
+        headers: { "Content-Type": "text/html" },
+      }));
+      await caches.open(CACHE_PREFIX + "current").then((meta) =>
+        meta.put("build", new Response(build.name))
+      );
+    })()
+  );
+  // Don't take over until the page agrees to reload (see SKIP_WAITING below)
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    (async () => {
+      const meta = await caches.open(CACHE_PREFIX + "current");
+      const current = await (await meta.match("build"))?.text();
+      const keep = [current, RUNTIME_CACHE, CACHE_PREFIX + "current"];
+      for (const name of await caches.keys()) {
+        if (name.startsWith(CACHE_PREFIX) && !keep.includes(name)) {
+          await caches.delete(name);
+        }
+      }
+      await self.clients.claim();
+    })()
+  );
+});
+
+// The page posts this when the user clicks "Reload" on the update prompt
+self.addEventListener("message", (event) => {
+  if (event.data && event.data.type === "SKIP_WAITING") {
+    self.skipWaiting();
+  }
+});
+
+self.addEventListener("fetch", (event) => {
+  const request = event.request;
+  if (request.method !== "GET") {
+    return;
+  }
+
+  const url = new URL(request.url);
+
+  // Page loads: network first so deployments show up, cached shell when offline
+  if (request.mode === "navigate") {
+    event.respondWith(
+      fetch(request).catch(async () => (await caches.match(INDEX_URL)) || Response.error())
+    );
+    return;
+  }
+
+  // Hashed build assets never change, so the cache is always right
+  if (url.origin === self.location.origin) {
+    event.respondWith(
+      caches.match(request).then((cached) => cached || fetch(request))
+    );
+    return;
+  }
+
+  // Styling from the CDN: serve the cached copy, refresh it in the background
+  if (RUNTIME_HOSTS.includes(url.hostname)) {
+    event.respondWith(
+      caches.open(RUNTIME_CACHE).then(async (cache) => {
+        const cached = await cache.match(request);
+        const network = fetch(request)
+          .then((response) => {
+            cache.put(request, response.clone());
+            return response;
+          })
+          .catch(() => cached);
+        return cached || network;
+      })
+    );
+  }
+
+  // Everything else (the friends API) goes straight to the network
+});